@@ -1,4 +1,5 @@
-//! See the [`Parser`] type.
+//! See the [`Parser`] type, or the [`MsgPackParser`] type for logs using the
+//! MessagePack format.
 
 use std::collections::HashMap;
 use std::convert::Infallible;
@@ -9,6 +10,10 @@ use std::time::{Duration, SystemTime};
 
 use log::Level;
 
+mod msgpack;
+
+pub use msgpack::{parse_msgpack, MsgPackParser};
+
 /// Create a new [`Parser`].
 pub fn parse<R>(reader: R) -> Parser<R>
 where
@@ -124,7 +129,7 @@ impl<R: Read> Parser<R> {
                 // Mark the line (new line included) as parser.
                 self.parsed = (self.buf.len() - input.len()) + if input.is_empty() { 0 } else { 1 };
 
-                return Ok((!record_is_empty).then(|| record));
+                return Ok((!record_is_empty).then_some(record));
             }
 
             let (i, key) = parse_key(input).map_err(|err| self.create_line_error(err))?;
@@ -240,11 +245,11 @@ impl<R: Read> Iterator for Parser<R> {
 /// Result returned by parsing functions.
 type ParseResult<'a, T> = Result<(&'a [u8], T), ParseErrorKind>;
 
-/// Error returned by the [`Parser`].
+/// Error returned by the [`Parser`] and [`MsgPackParser`].
 #[non_exhaustive]
 pub struct ParseError {
-    /// The line in which the error occurred. This will be `None` for [I/O]
-    /// errors.
+    /// The line in which the error occurred, or the record for the
+    /// [`MsgPackParser`]. This will be `None` for [I/O] errors.
     ///
     /// [I/O]: ParseErrorKind::Io
     pub line: Option<Box<[u8]>>,
@@ -289,6 +294,8 @@ pub enum ParseErrorKind {
     InvalidFile,
     /// A value contains invalid UTF-8.
     InvalidValue,
+    /// Invalid MessagePack data, e.g. a truncated record or an unexpected type.
+    InvalidMsgPack,
     /// I/O error.
     Io(io::Error),
 }
@@ -302,7 +309,8 @@ impl PartialEq for ParseErrorKind {
             | (InvalidTimestamp, InvalidTimestamp)
             | (InvalidLevel, InvalidLevel)
             | (InvalidFile, InvalidFile)
-            | (InvalidValue, InvalidValue) => true,
+            | (InvalidValue, InvalidValue)
+            | (InvalidMsgPack, InvalidMsgPack) => true,
             (Io(s_err), Io(o_err)) => match (s_err.raw_os_error(), o_err.raw_os_error()) {
                 (Some(s), Some(o)) => s == o,
                 _ => false,
@@ -321,6 +329,7 @@ impl fmt::Display for ParseErrorKind {
             InvalidLevel => "invalid level",
             InvalidFile => "invalid file",
             InvalidValue => "invalid UTF-8 in value",
+            InvalidMsgPack => "invalid MessagePack",
             Io(err) => return err.fmt(f),
         };
         f.write_str(msg)
@@ -328,7 +337,7 @@ impl fmt::Display for ParseErrorKind {
 }
 
 /// Returns a single line.
-fn single_line<'a>(input: &'a [u8]) -> &'a [u8] {
+fn single_line(input: &[u8]) -> &[u8] {
     let mut i = 0;
     let mut quote_count = 0;
     for b in input.iter().copied() {
@@ -345,7 +354,7 @@ fn single_line<'a>(input: &'a [u8]) -> &'a [u8] {
 
 /// Removes all spaces and tabs at the start of `input`. It does not remove new
 /// lines.
-fn eat_space<'a>(input: &'a [u8]) -> &'a [u8] {
+fn eat_space(input: &[u8]) -> &[u8] {
     let mut i = 0;
     for b in input.iter().copied() {
         if b != b' ' && b != b'\t' {
@@ -357,7 +366,7 @@ fn eat_space<'a>(input: &'a [u8]) -> &'a [u8] {
 }
 
 /// Same as [`eat_space`], but removes from the start of the input.
-fn eat_space_end<'a>(input: &'a [u8]) -> &'a [u8] {
+fn eat_space_end(input: &[u8]) -> &[u8] {
     let mut i = 0;
     for b in input.iter().rev().copied() {
         if b != b' ' && b != b'\t' {
@@ -369,7 +378,7 @@ fn eat_space_end<'a>(input: &'a [u8]) -> &'a [u8] {
 }
 
 /// Calls both [`eat_space`] and [`eat_space_end`].
-fn eat_space_both<'a>(input: &'a [u8]) -> &'a [u8] {
+fn eat_space_both(input: &[u8]) -> &[u8] {
     eat_space(eat_space_end(input))
 }
 
@@ -400,7 +409,7 @@ fn parse_key<'a>(input: &'a [u8]) -> ParseResult<'a, &'a str> {
 
 /// Parse a timestamp with the format: `yyyy-mm-ddThh:mm:ss.nnnnnnZhh:mm`, e.g.
 /// `2021-02-23T13:15:48.624447Z`.
-fn parse_timestamp<'a>(value: &'a [u8]) -> Result<SystemTime, ParseErrorKind> {
+fn parse_timestamp(value: &[u8]) -> Result<SystemTime, ParseErrorKind> {
     if value.len() < 20 {
         // Shorted valid timestamp is 20: `yyyy-mm-ddThh:mm:ssZ`.
         return Err(ParseErrorKind::InvalidTimestamp);
@@ -507,7 +516,7 @@ fn parse_timestamp<'a>(value: &'a [u8]) -> Result<SystemTime, ParseErrorKind> {
 }
 
 /// Parse a log level, using [`Level::from_str`].
-fn parse_log_level<'a>(value: &'a [u8]) -> Result<Level, ParseErrorKind> {
+fn parse_log_level(value: &[u8]) -> Result<Level, ParseErrorKind> {
     match str::from_utf8(value) {
         Ok(value) => match value.parse() {
             Ok(level) => Ok(level),
@@ -517,7 +526,7 @@ fn parse_log_level<'a>(value: &'a [u8]) -> Result<Level, ParseErrorKind> {
     }
 }
 
-fn parse_string<'a>(value: &'a [u8]) -> Result<&'a str, ParseErrorKind> {
+fn parse_string(value: &[u8]) -> Result<&str, ParseErrorKind> {
     match str::from_utf8(value) {
         Ok(value) => Ok(value),
         Err(_) => Err(ParseErrorKind::InvalidValue),
@@ -526,7 +535,7 @@ fn parse_string<'a>(value: &'a [u8]) -> Result<&'a str, ParseErrorKind> {
 
 /// Parse file value, format: `path/to/file:column`, e.g.
 /// `examples/simple.rs:51`.
-fn parse_file<'a>(value: &'a [u8]) -> Result<(&'a str, u32), ParseErrorKind> {
+fn parse_file(value: &[u8]) -> Result<(&str, u32), ParseErrorKind> {
     match str::from_utf8(value) {
         Ok(value) => {
             if let Some((file, column)) = value.rsplit_once(':') {
@@ -543,7 +552,7 @@ fn parse_file<'a>(value: &'a [u8]) -> Result<(&'a str, u32), ParseErrorKind> {
 }
 
 /// Returns `(remaining_input, value)`.
-fn parse_value<'a>(input: &'a [u8]) -> (&'a [u8], &'a [u8]) {
+fn parse_value(input: &[u8]) -> (&[u8], &[u8]) {
     let input = eat_space(input);
     if input.first().copied() == Some(b'"') {
         parse_quoted_value(input)
@@ -554,7 +563,7 @@ fn parse_value<'a>(input: &'a [u8]) -> (&'a [u8], &'a [u8]) {
 
 /// See [`parse_value`], expects `input` to contain a quoted value, i.e. it
 /// starts and ends with `"`.
-fn parse_quoted_value<'a>(input: &'a [u8]) -> (&'a [u8], &'a [u8]) {
+fn parse_quoted_value(input: &[u8]) -> (&[u8], &[u8]) {
     debug_assert!(input[0] == b'"');
     let mut i = 1;
     let mut quote_count = 1; // Support quotes inside quotes.
@@ -590,7 +599,7 @@ fn parse_quoted_value<'a>(input: &'a [u8]) -> (&'a [u8], &'a [u8]) {
 }
 
 /// Parses a single value, expecting a space (` `) as value end.
-fn parse_naked_value<'a>(input: &'a [u8]) -> (&'a [u8], &'a [u8]) {
+fn parse_naked_value(input: &[u8]) -> (&[u8], &[u8]) {
     let mut i = 0;
     for b in input.iter().copied() {
         if b == b' ' || b == b'\n' {
@@ -0,0 +1,331 @@
+//! Parser for the MessagePack format, see [`MsgPackParser`].
+
+use std::io::{self, Read};
+use std::time::{Duration, SystemTime};
+
+use crate::{parse_log_level, parse_string, ParseError, ParseErrorKind, Record, Value};
+
+/// Maximum size of a single record, larger records are skipped and return an
+/// [`InvalidMsgPack`] error.
+///
+/// [`InvalidMsgPack`]: ParseErrorKind::InvalidMsgPack
+const MAX_RECORD_SIZE: usize = 16 * 1024 * 1024;
+
+/// Create a new [`MsgPackParser`].
+pub fn parse_msgpack<R>(reader: R) -> MsgPackParser<R>
+where
+    R: Read,
+{
+    MsgPackParser {
+        reader,
+        buf: Vec::with_capacity(4096),
+    }
+}
+
+/// A struct to parse logs created by std-logger's MessagePack format.
+///
+/// Each record is expected to be a 32 bit big-endian length followed by a
+/// MessagePack map of that many bytes.
+///
+/// # Notes
+///
+/// If this parser returns an [error] for an invalid record it will skip that
+/// record and continue with the next one. In that case [`ParseError::line`]
+/// contains the bytes of the map. Records larger than 16 MiB are skipped
+/// without reading them into memory.
+///
+/// [error]: ParseError
+///
+/// # Examples
+///
+/// ```
+/// use std_logger_parser::parse_msgpack;
+///
+/// # fn main() -> Result<(), std_logger_parser::ParseError> {
+/// let logs = /* Open some log file, anything that implements `io::Read`. */
+/// #    b"" as &[u8];
+///
+/// for record in parse_msgpack(logs) {
+///     let record = record?;
+///
+///     println!("parsed a record: {:?}", record);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MsgPackParser<R> {
+    reader: R,
+    /// Bytes read of the current record, including the length.
+    buf: Vec<u8>,
+}
+
+impl<R: Read> MsgPackParser<R> {
+    /// Returns the number of bytes needed for the current record, including
+    /// the length.
+    fn wanted(&self) -> usize {
+        match self.buf.get(..4) {
+            Some(&[b0, b1, b2, b3]) => 4 + u32::from_be_bytes([b0, b1, b2, b3]) as usize,
+            _ => 4,
+        }
+    }
+
+    /// Skip a record of `len` bytes that's too large to parse.
+    fn skip(&mut self, len: usize) -> Result<Record, ParseError> {
+        let kind = match io::copy(&mut (&mut self.reader).take(len as u64), &mut io::sink()) {
+            Ok(n) if n == len as u64 => ParseErrorKind::InvalidMsgPack,
+            Ok(_) => ParseErrorKind::Io(io::ErrorKind::UnexpectedEof.into()),
+            Err(err) => ParseErrorKind::Io(err),
+        };
+        Err(ParseError { line: None, kind })
+    }
+}
+
+impl<R: Read> Iterator for MsgPackParser<R> {
+    type Item = Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let wanted = self.wanted();
+            let start = self.buf.len();
+            if start >= wanted {
+                break;
+            } else if wanted - 4 > MAX_RECORD_SIZE {
+                self.buf.clear();
+                return Some(self.skip(wanted - 4));
+            }
+
+            self.buf.resize(wanted, 0);
+            match self.reader.read(&mut self.buf[start..]) {
+                Ok(0) => {
+                    self.buf.clear();
+                    if start == 0 {
+                        return None;
+                    }
+                    return Some(Err(ParseError {
+                        line: None,
+                        kind: ParseErrorKind::Io(io::ErrorKind::UnexpectedEof.into()),
+                    }));
+                }
+                Ok(n) => self.buf.truncate(start + n),
+                Err(err) => {
+                    self.buf.truncate(start);
+                    if err.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Some(Err(ParseError {
+                        line: None,
+                        kind: ParseErrorKind::Io(err),
+                    }));
+                }
+            }
+        }
+
+        let input = &self.buf[4..];
+        let result = parse_record(input).map_err(|kind| ParseError {
+            line: Some(input.to_owned().into_boxed_slice()),
+            kind,
+        });
+        self.buf.clear();
+        Some(result)
+    }
+}
+
+/// Parse a single record from a MessagePack map.
+fn parse_record(input: &[u8]) -> Result<Record, ParseErrorKind> {
+    let mut input = Decoder(input);
+    let mut record = Record::empty();
+    let mut file = None;
+    let mut line = None;
+    for _ in 0..input.map_len()? {
+        let key = match input.value()? {
+            Raw::Str(key) => match std::str::from_utf8(key) {
+                Ok(key) => key,
+                Err(_) => return Err(ParseErrorKind::KeyInvalidUt8),
+            },
+            _ => return Err(ParseErrorKind::InvalidMsgPack),
+        };
+
+        match (key, input.value()?) {
+            ("timestamp", Raw::Timestamp(timestamp)) => record.timestamp = Some(timestamp),
+            ("timestamp", _) => return Err(ParseErrorKind::InvalidTimestamp),
+            ("level", Raw::Str(level)) => record.level = parse_log_level(level)?,
+            ("level", _) => return Err(ParseErrorKind::InvalidLevel),
+            ("message", Raw::Str(msg)) => record.msg = parse_string(msg)?.to_owned(),
+            ("target", Raw::Str(target)) => record.target = parse_string(target)?.to_owned(),
+            ("module", Raw::Str(module)) => {
+                let module = parse_string(module)?;
+                if !module.is_empty() {
+                    record.module = Some(module.to_owned());
+                }
+            }
+            ("message", _) | ("target", _) | ("module", _) => {
+                return Err(ParseErrorKind::InvalidValue)
+            }
+            ("file", Raw::Str(f)) => file = Some(parse_string(f)?.to_owned()),
+            ("file", _) => return Err(ParseErrorKind::InvalidFile),
+            ("line", Raw::UInt(l)) if l <= u64::from(u32::MAX) => line = Some(l as u32),
+            ("line", _) => return Err(ParseErrorKind::InvalidFile),
+            (key, value) => {
+                let value = match value {
+                    Raw::Nil => Value::String("null".to_owned()),
+                    Raw::Bool(b) => Value::Bool(b),
+                    Raw::Int(i) => Value::Int(i),
+                    Raw::UInt(u) if u <= i64::MAX as u64 => Value::Int(u as i64),
+                    Raw::UInt(u) => Value::String(u.to_string()),
+                    Raw::Float(f) => Value::Float(f),
+                    Raw::Str(s) => Value::String(parse_string(s)?.to_owned()),
+                    Raw::Timestamp(_) => return Err(ParseErrorKind::InvalidMsgPack),
+                };
+                // TODO: what to do when overwriting a key?
+                let _ = record.key_values.insert(key.to_owned(), value);
+            }
+        }
+    }
+
+    if !input.0.is_empty() {
+        return Err(ParseErrorKind::InvalidMsgPack);
+    }
+    match (file, line) {
+        (Some(file), Some(line)) => record.file = Some((file, line)),
+        (None, None) => {}
+        _ => return Err(ParseErrorKind::InvalidFile),
+    }
+    Ok(record)
+}
+
+/// A decoded MessagePack value, limited to the types std-logger writes.
+enum Raw<'a> {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    /// Not yet validated to be UTF-8.
+    Str(&'a [u8]),
+    Timestamp(SystemTime),
+}
+
+/// Decoder of MessagePack values, following
+/// <https://github.com/msgpack/msgpack/blob/master/spec.md>.
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    /// Returns the next `n` bytes.
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ParseErrorKind> {
+        if self.0.len() < n {
+            return Err(ParseErrorKind::InvalidMsgPack);
+        }
+        let (bytes, input) = self.0.split_at(n);
+        self.0 = input;
+        Ok(bytes)
+    }
+
+    /// Returns the next `N` bytes as array.
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ParseErrorKind> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ParseErrorKind> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, ParseErrorKind> {
+        self.array().map(u16::from_be_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, ParseErrorKind> {
+        self.array().map(u32::from_be_bytes)
+    }
+
+    /// Returns the number of entries in a map.
+    fn map_len(&mut self) -> Result<u32, ParseErrorKind> {
+        match self.u8()? {
+            b @ 0x80..=0x8f => Ok(u32::from(b & 0x0f)),
+            0xde => self.u16().map(u32::from),
+            0xdf => self.u32(),
+            _ => Err(ParseErrorKind::InvalidMsgPack),
+        }
+    }
+
+    /// Returns the next value.
+    fn value(&mut self) -> Result<Raw<'a>, ParseErrorKind> {
+        let value = match self.u8()? {
+            b @ 0x00..=0x7f => Raw::UInt(u64::from(b)),
+            b @ 0xa0..=0xbf => Raw::Str(self.take(usize::from(b & 0x1f))?),
+            0xc0 => Raw::Nil,
+            0xc2 => Raw::Bool(false),
+            0xc3 => Raw::Bool(true),
+            0xca => Raw::Float(f32::from_be_bytes(self.array()?).into()),
+            0xcb => Raw::Float(f64::from_be_bytes(self.array()?)),
+            0xcc => Raw::UInt(self.u8()?.into()),
+            0xcd => Raw::UInt(self.u16()?.into()),
+            0xce => Raw::UInt(self.u32()?.into()),
+            0xcf => Raw::UInt(u64::from_be_bytes(self.array()?)),
+            0xd0 => Raw::Int(i8::from_be_bytes(self.array()?).into()),
+            0xd1 => Raw::Int(i16::from_be_bytes(self.array()?).into()),
+            0xd2 => Raw::Int(i32::from_be_bytes(self.array()?).into()),
+            0xd3 => Raw::Int(i64::from_be_bytes(self.array()?)),
+            0xd9 => {
+                let len = self.u8()?;
+                Raw::Str(self.take(len.into())?)
+            }
+            0xda => {
+                let len = self.u16()?;
+                Raw::Str(self.take(len.into())?)
+            }
+            0xdb => {
+                let len = self.u32()?;
+                Raw::Str(self.take(len as usize)?)
+            }
+            // Timestamp extension type (-1), in its 32, 64 and 96 bit forms.
+            0xd6 => {
+                self.timestamp_type()?;
+                Raw::Timestamp(timestamp(self.u32()?.into(), 0)?)
+            }
+            0xd7 => {
+                self.timestamp_type()?;
+                let value = u64::from_be_bytes(self.array()?);
+                let secs = (value & 0x3_ffff_ffff) as i64;
+                Raw::Timestamp(timestamp(secs, (value >> 34) as u32)?)
+            }
+            0xc7 => {
+                if self.u8()? != 12 {
+                    return Err(ParseErrorKind::InvalidTimestamp);
+                }
+                self.timestamp_type()?;
+                let nanos = self.u32()?;
+                let secs = i64::from_be_bytes(self.array()?);
+                Raw::Timestamp(timestamp(secs, nanos)?)
+            }
+            b @ 0xe0..=0xff => Raw::Int((b as i8).into()),
+            _ => return Err(ParseErrorKind::InvalidMsgPack),
+        };
+        Ok(value)
+    }
+
+    /// Checks the extension type is the timestamp type (-1).
+    fn timestamp_type(&mut self) -> Result<(), ParseErrorKind> {
+        match self.u8()? {
+            0xff => Ok(()),
+            _ => Err(ParseErrorKind::InvalidMsgPack),
+        }
+    }
+}
+
+/// Create a timestamp `secs` and `nanos` from the Unix epoch.
+fn timestamp(secs: i64, nanos: u32) -> Result<SystemTime, ParseErrorKind> {
+    if nanos >= 1_000_000_000 {
+        return Err(ParseErrorKind::InvalidTimestamp);
+    }
+    let nanos = Duration::from_nanos(nanos.into());
+    let time = if secs >= 0 {
+        SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))
+    } else {
+        SystemTime::UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))
+    };
+    time.and_then(|time| time.checked_add(nanos))
+        .ok_or(ParseErrorKind::InvalidTimestamp)
+}
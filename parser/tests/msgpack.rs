@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::io;
+use std::time::{Duration, SystemTime};
+
+use log::Level;
+use std_logger_parser::{parse_msgpack, ParseErrorKind, Record, Value};
+
+/// Returns a record, length prefixed, with `entries` number of pairs in
+/// `map`.
+fn record(entries: u8, map: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(map.len() as u32 + 1).to_be_bytes());
+    buf.push(0x80 | entries);
+    buf.extend_from_slice(map);
+    buf
+}
+
+fn str(buf: &mut Vec<u8>, value: &str) {
+    assert!(value.len() < 32);
+    buf.push(0xa0 | value.len() as u8);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+fn new_record(level: Level, msg: &str, target: &str) -> Record {
+    let mut record = Record::empty();
+    record.level = level;
+    record.msg = msg.to_owned();
+    record.target = target.to_owned();
+    record
+}
+
+#[test]
+fn empty() {
+    assert!(parse_msgpack(&[] as &[u8]).next().is_none());
+}
+
+#[test]
+fn full_record() {
+    let mut map = Vec::new();
+    str(&mut map, "timestamp");
+    map.extend_from_slice(&[0xc7, 12, 0xff]);
+    map.extend_from_slice(&624_447_000u32.to_be_bytes());
+    map.extend_from_slice(&1_614_086_148i64.to_be_bytes());
+    str(&mut map, "level");
+    str(&mut map, "WARN");
+    str(&mut map, "message");
+    // Messages are always written as str 32.
+    map.push(0xdb);
+    map.extend_from_slice(&5u32.to_be_bytes());
+    map.extend_from_slice(b"Hello");
+    str(&mut map, "target");
+    str(&mut map, "some_target");
+    str(&mut map, "module");
+    str(&mut map, "crate::mod");
+    str(&mut map, "int");
+    map.extend_from_slice(&[0xcd, 0x01, 0x00]);
+    str(&mut map, "neg");
+    map.push(0xff);
+    str(&mut map, "float");
+    map.push(0xcb);
+    map.extend_from_slice(&1.5f64.to_be_bytes());
+    str(&mut map, "bool");
+    map.push(0xc3);
+    str(&mut map, "str");
+    str(&mut map, "true");
+    str(&mut map, "null");
+    map.push(0xc0);
+    str(&mut map, "file");
+    str(&mut map, "src/lib.rs");
+    str(&mut map, "line");
+    map.push(123);
+    let logs = record(13, &map);
+
+    let mut expected = new_record(Level::Warn, "Hello", "some_target");
+    expected.timestamp = Some(SystemTime::UNIX_EPOCH + Duration::new(1_614_086_148, 624_447_000));
+    expected.module = Some("crate::mod".to_owned());
+    expected.file = Some(("src/lib.rs".to_owned(), 123));
+    let mut kvs = HashMap::new();
+    let _ = kvs.insert("int".to_owned(), Value::Int(256));
+    let _ = kvs.insert("neg".to_owned(), Value::Int(-1));
+    let _ = kvs.insert("float".to_owned(), Value::Float(1.5));
+    let _ = kvs.insert("bool".to_owned(), Value::Bool(true));
+    // Unlike logfmt strings are not parsed.
+    let _ = kvs.insert("str".to_owned(), Value::String("true".to_owned()));
+    let _ = kvs.insert("null".to_owned(), Value::String("null".to_owned()));
+    expected.key_values = kvs;
+
+    let mut parser = parse_msgpack(&*logs);
+    assert_eq!(parser.next().unwrap().unwrap(), expected);
+    assert!(parser.next().is_none());
+}
+
+#[test]
+fn error_and_continue() {
+    let mut logs = Vec::new();
+    let mut map = Vec::new();
+    str(&mut map, "level");
+    str(&mut map, "NOT_A_LEVEL");
+    logs.extend_from_slice(&record(1, &map));
+    let mut map = Vec::new();
+    str(&mut map, "level");
+    str(&mut map, "ERROR");
+    // Array, not supported.
+    map.push(0x90);
+    logs.extend_from_slice(&record(2, &map));
+    let mut map = Vec::new();
+    str(&mut map, "level");
+    str(&mut map, "INFO");
+    str(&mut map, "message");
+    str(&mut map, "Hi");
+    logs.extend_from_slice(&record(2, &map));
+
+    let mut parser = parse_msgpack(&*logs);
+    let err = parser.next().unwrap().unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::InvalidLevel);
+    assert_eq!(err.line.as_deref(), Some(&logs[4..23]));
+    let err = parser.next().unwrap().unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::InvalidMsgPack);
+    let got = parser.next().unwrap().unwrap();
+    assert_eq!(got, new_record(Level::Info, "Hi", ""));
+    assert!(parser.next().is_none());
+}
+
+#[test]
+fn truncated_record() {
+    let mut map = Vec::new();
+    str(&mut map, "level");
+    str(&mut map, "INFO");
+    let logs = record(1, &map);
+
+    let mut parser = parse_msgpack(&logs[..logs.len() - 2]);
+    match parser.next().unwrap().unwrap_err().kind {
+        ParseErrorKind::Io(err) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+        kind => panic!("unexpected error: {}", kind),
+    }
+    assert!(parser.next().is_none());
+}
+
+#[test]
+fn oversized_record() {
+    let mut logs = Vec::new();
+    logs.extend_from_slice(&u32::MAX.to_be_bytes());
+    logs.extend_from_slice(&[0; 64]);
+
+    let mut parser = parse_msgpack(&*logs);
+    match parser.next().unwrap().unwrap_err().kind {
+        ParseErrorKind::Io(err) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+        kind => panic!("unexpected error: {}", kind),
+    }
+    assert!(parser.next().is_none());
+
+    let mut map = Vec::new();
+    str(&mut map, "message");
+    map.push(0xdb);
+    map.extend_from_slice(&(20 * 1024 * 1024u32).to_be_bytes());
+    map.resize(map.len() + 20 * 1024 * 1024, b'a');
+    let mut logs = record(1, &map);
+    let mut map = Vec::new();
+    str(&mut map, "message");
+    str(&mut map, "Hi");
+    logs.extend_from_slice(&record(1, &map));
+
+    let mut parser = parse_msgpack(&*logs);
+    let err = parser.next().unwrap().unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::InvalidMsgPack);
+    assert!(err.line.is_none());
+    let got = parser.next().unwrap().unwrap();
+    assert_eq!(got, new_record(Level::Info, "Hi", ""));
+    assert!(parser.next().is_none());
+}
+
+#[test]
+fn timestamp_overflow() {
+    let mut logs = Vec::new();
+    for (secs, nanos) in [(i64::MAX, 999_999_999u32), (i64::MIN, 0)] {
+        let mut map = Vec::new();
+        str(&mut map, "timestamp");
+        map.extend_from_slice(&[0xc7, 12, 0xff]);
+        map.extend_from_slice(&nanos.to_be_bytes());
+        map.extend_from_slice(&secs.to_be_bytes());
+        logs.extend_from_slice(&record(1, &map));
+    }
+
+    // Whether or not these fit depends on the platform's `SystemTime`, but it
+    // should never panic.
+    let mut parser = parse_msgpack(&*logs);
+    for _ in 0..2 {
+        if let Err(err) = parser.next().unwrap() {
+            assert_eq!(err.kind, ParseErrorKind::InvalidTimestamp);
+        }
+    }
+    assert!(parser.next().is_none());
+}
@@ -83,7 +83,7 @@ impl<'a> Read for MultiSlice<'a> {
             match slice.read(&mut buf[accumulated_len..]) {
                 Ok(n) => {
                     accumulated_len += n;
-                    if n == (&*slice).len() {
+                    if n == slice.len() {
                         remove += 1;
                     } else {
                         break;
@@ -93,9 +93,9 @@ impl<'a> Read for MultiSlice<'a> {
             }
         }
 
-        let slices = std::mem::replace(&mut self.slices, &mut []);
+        let slices = std::mem::take(&mut self.slices);
         self.slices = &mut slices[remove..];
-        return Ok(accumulated_len);
+        Ok(accumulated_len)
     }
 }
 
@@ -522,8 +522,8 @@ fn invalid_lines() {
             err.line,
             expected.0,
             "got: {}, expected: {}",
-            String::from_utf8_lossy(&*err.line.as_ref().unwrap()),
-            String::from_utf8_lossy(&*expected.0.as_ref().unwrap()),
+            String::from_utf8_lossy(err.line.as_ref().unwrap()),
+            String::from_utf8_lossy(expected.0.as_ref().unwrap()),
         );
         assert_eq!(err.kind, *expected.1);
     }
@@ -1,46 +1,209 @@
 //! Configuration of the logger.
 
 use std::env;
-use std::marker::PhantomData;
+use std::sync::Arc;
 
-use log::{kv, LevelFilter, SetLoggerError};
+use log::{kv, LevelFilter, Log, SetLoggerError};
 
-use crate::format::{Format, Gcloud, Json, LogFmt};
+use crate::format::{
+    Auto, Datadog, Dynamic, Ecs, Emf, Field, Format, Gcloud, Gelf, Journald, Json, KeyCollision,
+    LogFmt, MessagePack, OpenTelemetry, Pretty, Syslog, SyslogRfc3164,
+};
+#[cfg(unix)]
+use crate::signal;
+use crate::sink::{self, Background, Overflow, Sink, Stderr, Stdout};
 #[cfg(feature = "log-panic")]
 use crate::PANIC_TARGET;
-use crate::{Logger, Targets};
+use crate::{Filter, FilterLock, Logger, LoggerHandle, SharedFilter, Targets};
 
 /// Configuration of the logger.
 ///
-/// It support three logging formats:
-///  * [`logfmt`](Config::logfmt) and
-///  * [`json`](Config::json) and
-///  * [`gcloud`](Config::gcloud).
+/// It support the following logging formats:
+///  * [`logfmt`](Config::logfmt),
+///  * [`json`](Config::json),
+///  * [`gcloud`](Config::gcloud),
+///  * [`ecs`](Config::ecs),
+///  * [`datadog`](Config::datadog),
+///  * [`gelf`](Config::gelf),
+///  * [`opentelemetry`](Config::opentelemetry),
+///  * [`emf`](Config::emf),
+///  * [`msgpack`](Config::msgpack),
+///  * [`journald`](Config::journald),
+///  * [`syslog`](Config::syslog),
+///  * [`syslog_rfc3164`](Config::syslog_rfc3164) and
+///  * [`pretty`](Config::pretty).
+///
+/// The format can also be selected at runtime using
+/// [`from_env`](Config::from_env) or
+/// [`with_pretty_on_tty`](Config::with_pretty_on_tty). Other formats can be used with
+/// [`custom`](Config::custom).
 #[derive(Debug)]
 #[must_use = "the logger must be initialised using `init` or `try_init`"]
 pub struct Config<F, Kvs> {
-    filter: LevelFilter,
+    filter: Filter,
+    /// Parts of `filter` set in code, which take precedence over the
+    /// environment, see [`Config::with_env_prefix`].
+    overrides: FilterOverrides,
     add_loc: Option<bool>,
-    targets: Targets,
     kvs: Kvs,
-    format: PhantomData<F>,
+    format: F,
+    output: Box<dyn Sink>,
+    request_output: Box<dyn Sink>,
+    /// Capacity and overflow policy of the queue if the outputs are written
+    /// on a background thread, see [`Config::with_async`].
+    async_queue: Option<(usize, Overflow)>,
+    signals: Signals,
 }
 
 impl Config<(), NoKvs> {
     /// Logfmt following <https://www.brandur.org/logfmt>.
     pub fn logfmt() -> Config<LogFmt, NoKvs> {
-        Config::new(NoKvs)
+        Config::new(LogFmt::new(), NoKvs)
     }
 
     /// Structured logging using JSON.
     pub fn json() -> Config<Json, NoKvs> {
-        Config::new(NoKvs)
+        Config::new(Json::new(), NoKvs)
     }
 
     /// Google Cloud Platform structured logging using JSON, following
     /// <https://cloud.google.com/logging/docs/structured-logging>.
     pub fn gcloud() -> Config<Gcloud, NoKvs> {
-        Config::new(NoKvs)
+        Config::new(Gcloud(()), NoKvs)
+    }
+
+    /// Elastic Common Schema (ECS) logging using JSON, following
+    /// <https://www.elastic.co/guide/en/ecs-logging/overview/current/intro.html>.
+    ///
+    /// See the [`Ecs`] format for the fields that are logged.
+    pub fn ecs() -> Config<Ecs, NoKvs> {
+        Config::new(Ecs(()), NoKvs)
+    }
+
+    /// Datadog structured logging using JSON, following
+    /// <https://docs.datadoghq.com/logs/log_configuration/attributes_naming_convention>.
+    ///
+    /// See the [`Datadog`] format for the fields that are logged.
+    pub fn datadog() -> Config<Datadog, NoKvs> {
+        Config::new(Datadog(()), NoKvs)
+    }
+
+    /// Graylog Extended Log Format (GELF) version 1.1, following
+    /// <https://go2docs.graylog.org/current/getting_in_log_data/gelf.html>.
+    ///
+    /// By default messages are still written to standard error and requests
+    /// to standard out, use [`sink::Gelf`] with [`Config::with_output`] and
+    /// [`Config::with_request_output`] to send them to Graylog instead. See
+    /// the [`Gelf`] format for the fields that are logged.
+    pub fn gelf() -> Config<Gelf, NoKvs> {
+        Config::new(Gelf(()), NoKvs)
+    }
+
+    /// OpenTelemetry logs using OTLP/JSON, following
+    /// <https://opentelemetry.io/docs/specs/otel/logs/data-model>.
+    ///
+    /// See the [`OpenTelemetry`] format for the fields that are logged and
+    /// [`Config::with_resource`] to set the resource attributes.
+    pub fn opentelemetry() -> Config<OpenTelemetry, NoKvs> {
+        Config::new(OpenTelemetry::new(), NoKvs)
+    }
+
+    /// Binary logging using MessagePack, following
+    /// <https://github.com/msgpack/msgpack/blob/master/spec.md>.
+    ///
+    /// This is a binary format, so it's best combined with an output other
+    /// than standard error, see [`Config::with_output`]. See the
+    /// [`MessagePack`] format for the fields that are logged.
+    pub fn msgpack() -> Config<MessagePack, NoKvs> {
+        Config::new(MessagePack(()), NoKvs)
+    }
+
+    /// AWS CloudWatch Embedded Metric Format (EMF), logging metrics in
+    /// `namespace`.
+    ///
+    /// Records are logged using JSON, see [`Config::with_metrics`] and
+    /// [`Config::with_dimensions`] to configure which key-value pairs are
+    /// extracted as metrics. See the [`Emf`] format for more.
+    pub fn emf(namespace: &str) -> Config<Emf, NoKvs> {
+        Config::new(Emf::new(namespace.into()), NoKvs)
+    }
+
+    /// Log to systemd-journald using its native protocol, following
+    /// <https://systemd.io/JOURNAL_NATIVE_PROTOCOL>.
+    ///
+    /// Both regular messages and requests are send to journald's socket, see
+    /// the [`Journald`] format for the fields that are logged.
+    #[cfg(unix)]
+    pub fn journald() -> Config<Journald, NoKvs> {
+        let output = Arc::new(sink::Journald::new());
+        Config::new(Journald(()), NoKvs)
+            .with_output(output.clone())
+            .with_request_output(output)
+    }
+
+    /// Syslog following RFC 5424 <https://datatracker.ietf.org/doc/html/rfc5424>.
+    ///
+    /// By default messages are still written to standard error and requests
+    /// to standard out, use [`sink::Syslog`] with [`Config::with_output`] and
+    /// [`Config::with_request_output`] to send them to a syslog daemon or
+    /// collector instead. See the [`Syslog`] format for the fields that are
+    /// logged.
+    pub fn syslog() -> Config<Syslog, NoKvs> {
+        Config::new(Syslog(()), NoKvs)
+    }
+
+    /// Syslog following RFC 3164 <https://datatracker.ietf.org/doc/html/rfc3164>,
+    /// for older syslog daemons and collectors that don't support RFC 5424.
+    ///
+    /// See [`Config::syslog`] for sending messages to a syslog daemon.
+    pub fn syslog_rfc3164() -> Config<SyslogRfc3164, NoKvs> {
+        Config::new(SyslogRfc3164(()), NoKvs)
+    }
+
+    /// Human readable format with colors, intended for terminals during
+    /// development.
+    ///
+    /// See the [`Pretty`] format for when colors are used and
+    /// [`Config::with_pretty_on_tty`] to only use it on terminals.
+    pub fn pretty() -> Config<Pretty, NoKvs> {
+        Config::new(Pretty::new(), NoKvs)
+    }
+
+    /// Select the format using the `LOG_FORMAT` environment variable.
+    ///
+    /// Supported values are `logfmt`, `json`, `gcloud`, `ecs`, `datadog`,
+    /// `gelf`, `opentelemetry` (or `otel`), `syslog`, `pretty` and `auto`,
+    /// which uses pretty if the output is a terminal and logfmt otherwise.
+    /// If the variable is not set, or holds an unsupported value, logfmt is
+    /// used.
+    ///
+    /// See the [`Dynamic`] format.
+    pub fn from_env() -> Config<Dynamic, NoKvs> {
+        Config::from_env_prefix("")
+    }
+
+    /// Select the format using the environment variable `LOG_FORMAT` prefixed
+    /// with `prefix`, e.g. `MYAPP_LOG_FORMAT`.
+    ///
+    /// The severity and targets are also read from environment variables
+    /// prefixed with `prefix`, see [`Config::with_env_prefix`] and
+    /// [`Config::from_env`].
+    pub fn from_env_prefix(prefix: &str) -> Config<Dynamic, NoKvs> {
+        let format = env::var(format!("{prefix}LOG_FORMAT"))
+            .ok()
+            .and_then(|name| Dynamic::from_name(&name))
+            .unwrap_or(Dynamic::LogFmt(LogFmt::new()));
+        Config::new(format, NoKvs).with_env_prefix(prefix)
+    }
+
+    /// Use a custom `format`.
+    ///
+    /// See the [`Format`] trait for an example.
+    pub fn custom<F>(format: F) -> Config<F, NoKvs>
+    where
+        F: Format + Send + Sync + 'static,
+    {
+        Config::new(format, NoKvs)
     }
 }
 
@@ -49,13 +212,17 @@ where
     F: Format + Send + Sync + 'static,
     Kvs: kv::Source + Send + Sync + 'static,
 {
-    fn new(kvs: Kvs) -> Config<F, Kvs> {
+    fn new(format: F, kvs: Kvs) -> Config<F, Kvs> {
         Config {
-            filter: get_max_level(),
+            filter: get_log_filter(""),
+            overrides: FilterOverrides::default(),
             add_loc: None,
-            targets: get_log_targets(),
             kvs,
-            format: PhantomData,
+            format,
+            output: Box::new(Stderr),
+            request_output: Box::new(Stdout),
+            async_queue: None,
+            signals: Signals::new(),
         }
     }
 
@@ -66,10 +233,126 @@ where
     {
         Config {
             filter: self.filter,
+            overrides: self.overrides,
             add_loc: self.add_loc,
-            targets: self.targets,
             kvs,
             format: self.format,
+            output: self.output,
+            request_output: self.request_output,
+            async_queue: self.async_queue,
+            signals: self.signals,
+        }
+    }
+
+    /// Use the [`Pretty`] format if the output is a terminal, or keep using
+    /// the current format otherwise.
+    ///
+    /// This allows for a human readable format during development, while
+    /// still using a structured format in production.
+    pub fn with_pretty_on_tty(self) -> Config<Auto<F>, Kvs> {
+        Config {
+            filter: self.filter,
+            overrides: self.overrides,
+            add_loc: self.add_loc,
+            kvs: self.kvs,
+            format: Auto::new(self.format),
+            output: self.output,
+            request_output: self.request_output,
+            async_queue: self.async_queue,
+            signals: self.signals,
+        }
+    }
+
+    /// Set the maximum severity (log level) of the messages to log.
+    ///
+    /// Defaults to the severity set in the environment, see [setting
+    /// severity].
+    ///
+    /// [setting severity]: crate#setting-severity
+    pub fn with_level(self, level: LevelFilter) -> Config<F, Kvs> {
+        Config {
+            filter: Filter {
+                level,
+                ..self.filter
+            },
+            overrides: FilterOverrides {
+                level: Some(level),
+                ..self.overrides
+            },
+            ..self
+        }
+    }
+
+    /// Only log messages with a target that starts with one of the prefixes
+    /// in `targets`. If `targets` is empty all targets are logged.
+    ///
+    /// This overwrites the targets set using the `LOG_TARGET` environment
+    /// variable, see [limiting logging targets]. Note that requests and panics
+    /// are always logged.
+    ///
+    /// [limiting logging targets]: crate#limiting-logging-targets
+    pub fn with_targets<I>(self, targets: I) -> Config<F, Kvs>
+    where
+        I: IntoIterator,
+        I::Item: Into<Box<str>>,
+    {
+        let targets: Box<[Box<str>]> = targets.into_iter().map(Into::into).collect();
+        let mut filter = self.filter;
+        filter.targets.only = targets.clone();
+        let mut overrides = self.overrides;
+        overrides.targets = Some(targets);
+        Config {
+            filter,
+            overrides,
+            ..self
+        }
+    }
+
+    /// Read the severity and targets from environment variables prefixed with
+    /// `prefix`.
+    ///
+    /// For example using the prefix `MYAPP_` reads `MYAPP_LOG`,
+    /// `MYAPP_LOG_LEVEL`, `MYAPP_TRACE`, `MYAPP_DEBUG` and `MYAPP_LOG_TARGET`,
+    /// instead of `LOG`, `LOG_LEVEL`, etc. See [setting severity] and [limiting
+    /// logging targets] for the supported values. This way multiple binaries
+    /// in the same environment can be configured separately.
+    ///
+    /// The severity and targets set using [`Config::with_level`],
+    /// [`Config::with_targets`] and [`Config::with_excluded_targets`] take
+    /// precedence over the environment, whether they are called before or
+    /// after this. Also see [`Config::from_env_prefix`] to select the format
+    /// using the environment as well.
+    ///
+    /// [setting severity]: crate#setting-severity
+    /// [limiting logging targets]: crate#limiting-logging-targets
+    pub fn with_env_prefix(self, prefix: &str) -> Config<F, Kvs> {
+        let mut filter = get_log_filter(prefix);
+        self.overrides.apply(&mut filter);
+        Config { filter, ..self }
+    }
+
+    /// Never log messages with a target that starts with one of the prefixes
+    /// in `targets`.
+    ///
+    /// This overwrites the exclusions set using the `LOG_TARGET` environment
+    /// variable, see [limiting logging targets]. Note that requests and panics
+    /// are always logged.
+    ///
+    /// [limiting logging targets]: crate#limiting-logging-targets
+    pub fn with_excluded_targets<I>(self, targets: I) -> Config<F, Kvs>
+    where
+        I: IntoIterator,
+        I::Item: Into<Box<str>>,
+    {
+        let targets: Box<[Box<str>]> = targets.into_iter().map(Into::into).collect();
+        let mut filter = self.filter;
+        filter.targets.exclude = targets.clone();
+        let mut overrides = self.overrides;
+        overrides.excluded_targets = Some(targets);
+        Config {
+            filter,
+            overrides,
+            ..self
         }
     }
 
@@ -79,10 +362,112 @@ where
     pub fn with_call_location(self, enable: bool) -> Config<F, Kvs> {
         Config {
             filter: self.filter,
+            overrides: self.overrides,
             add_loc: Some(enable),
-            targets: self.targets,
             kvs: self.kvs,
             format: self.format,
+            output: self.output,
+            request_output: self.request_output,
+            async_queue: self.async_queue,
+            signals: self.signals,
+        }
+    }
+
+    /// Write regular messages to `output`.
+    ///
+    /// Defaults to [standard error](Stderr). See the [`sink`] module for the
+    /// available sinks.
+    ///
+    /// [`sink`]: crate::sink
+    pub fn with_output<S>(self, output: S) -> Config<F, Kvs>
+    where
+        S: Sink + 'static,
+    {
+        Config {
+            output: Box::new(output),
+            ..self
+        }
+    }
+
+    /// Write requests, i.e. messages using the [`REQUEST_TARGET`] target, to
+    /// `output`.
+    ///
+    /// Defaults to [standard out](Stdout). See the [`sink`] module for the
+    /// available sinks.
+    ///
+    /// [`REQUEST_TARGET`]: crate::REQUEST_TARGET
+    /// [`sink`]: crate::sink
+    pub fn with_request_output<S>(self, output: S) -> Config<F, Kvs>
+    where
+        S: Sink + 'static,
+    {
+        Config {
+            request_output: Box::new(output),
+            ..self
+        }
+    }
+
+    /// Write records on a background thread.
+    ///
+    /// Records are still formatted on the logging thread, but are written to
+    /// the outputs by a writer thread, see the [`Background`] sink. Both
+    /// regular messages and requests use their own queue, holding at most
+    /// `capacity` records, and writer thread. What happens when a queue is full
+    /// is determined by `overflow`.
+    ///
+    /// This applies to the outputs set using [`Config::with_output`] and
+    /// [`Config::with_request_output`], regardless of whether they are called
+    /// before or after this.
+    ///
+    /// [`Background`]: sink::Background
+    pub fn with_async(self, capacity: usize, overflow: Overflow) -> Config<F, Kvs> {
+        Config {
+            async_queue: Some((capacity, overflow)),
+            ..self
+        }
+    }
+
+    /// Enable or disable reopening log files when the process receives a
+    /// `SIGHUP` signal.
+    ///
+    /// See [`reopen`] for more. Defaults to disabled, in which case `SIGHUP`
+    /// is not handled.
+    ///
+    /// [`reopen`]: crate::reopen
+    #[cfg(unix)]
+    pub fn with_reopen_on_sighup(self, enable: bool) -> Config<F, Kvs> {
+        Config {
+            signals: Signals {
+                reopen_on_sighup: enable,
+                ..self.signals
+            },
+            ..self
+        }
+    }
+
+    /// Enable or disable changing the severity (log level) using signals.
+    ///
+    /// If enabled `SIGUSR1` increases the severity by one level, e.g. from info
+    /// to debug, and `SIGUSR2` decreases it by one level, e.g. from info to
+    /// warn, but never below error. Every change is logged at the new
+    /// severity. The severity starts at the level set using
+    /// [`Config::with_level`] or the environment, see [setting severity].
+    ///
+    /// This changes the severity in the same way as
+    /// [`LoggerHandle::set_level`]. When using [`Fanout`] the severity of each
+    /// destination is changed relative to its own severity. Defaults to
+    /// disabled, in which case `SIGUSR1` and `SIGUSR2` are not handled.
+    ///
+    /// [`Fanout`]: crate::Fanout
+    /// [setting severity]: crate#setting-severity
+    #[cfg(unix)]
+    pub fn with_level_signals(self, enable: bool) -> Config<F, Kvs> {
+        Config {
+            signals: Signals {
+                change_level: enable,
+                ..self.signals
+            },
+            ..self
         }
     }
 
@@ -109,48 +494,369 @@ where
     /// [`init`]: fn.init.html
     /// [crate level documentation]: index.html
     pub fn try_init(self) -> Result<(), SetLoggerError> {
+        self.try_init_with_handle().map(|_| ())
+    }
+
+    /// Initialise the logger, returning a handle to change what messages are
+    /// logged while the process is running.
+    ///
+    /// See [`LoggerHandle`].
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the logger fails to initialise. Use
+    /// [`Config::try_init_with_handle`] if you want to handle the error
+    /// yourself.
+    pub fn init_with_handle(self) -> LoggerHandle {
+        self.try_init_with_handle()
+            .unwrap_or_else(|err| panic!("failed to initialise the logger: {err}"))
+    }
+
+    /// Try to initialise the logger, returning a handle to change what messages
+    /// are logged while the process is running.
+    ///
+    /// See [`Config::init_with_handle`].
+    pub fn try_init_with_handle(self) -> Result<LoggerHandle, SetLoggerError> {
+        let (logger, filter, signals) = self.into_logger();
+        install(logger, Box::new([filter]), signals)
+    }
+
+    /// Initialise the logger, returning a guard that flushes the logger when
+    /// dropped.
+    ///
+    /// The guard should be kept alive until the end of `main`, this ensures
+    /// that all records are written to their outputs, which is useful when
+    /// using buffered or [asynchronous] outputs. On Unix the logger is also
+    /// flushed when the process exits using [`process::exit`], on other
+    /// platforms drop the guard before calling it.
+    ///
+    /// [asynchronous]: Config::with_async
+    /// [`process::exit`]: std::process::exit
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the logger fails to initialise. Use
+    /// [`Config::try_init_with_guard`] if you want to handle the error
+    /// yourself.
+    pub fn init_with_guard(self) -> FlushGuard {
+        self.try_init_with_guard()
+            .unwrap_or_else(|err| panic!("failed to initialise the logger: {err}"))
+    }
+
+    /// Try to initialise the logger, returning a guard that flushes the logger
+    /// when dropped.
+    ///
+    /// See [`Config::init_with_guard`] and [`Config::try_init`].
+    pub fn try_init_with_guard(self) -> Result<FlushGuard, SetLoggerError> {
+        self.try_init_with_handle().map(FlushGuard::new)
+    }
+
+    /// Returns the logger, its filter and the signals to handle.
+    pub(crate) fn into_logger(self) -> (Box<dyn Log>, SharedFilter, Signals) {
+        let (output, request_output) = match self.async_queue {
+            Some((capacity, overflow)) => (
+                Box::new(Background::with_capacity(self.output, capacity, overflow)) as _,
+                Box::new(Background::with_capacity(
+                    self.request_output,
+                    capacity,
+                    overflow,
+                )) as _,
+            ),
+            None => (self.output, self.request_output),
+        };
+        let mut format = self.format;
+        format.set_terminal(output.is_terminal());
+        let filter = Arc::new(FilterLock::new(self.filter));
         let logger = Box::new(Logger {
-            filter: self.filter,
-            add_loc: self.add_loc.unwrap_or(self.filter >= LevelFilter::Debug),
-            targets: self.targets,
+            filter: filter.clone(),
+            add_loc: self.add_loc,
             kvs: self.kvs,
-            format: self.format,
+            format,
+            output,
+            request_output,
         });
-        log::set_boxed_logger(logger)?;
-        log::set_max_level(self.filter);
+        (logger, filter, self.signals)
+    }
+}
 
-        #[cfg(feature = "log-panic")]
-        std::panic::set_hook(Box::new(log_panic));
-        Ok(())
+impl<Kvs> Config<LogFmt, Kvs> {
+    /// Log the built-in `field` using `name` as key, e.g. `severity` instead
+    /// of `lvl`.
+    pub fn with_field_name(mut self, field: Field, name: &str) -> Config<LogFmt, Kvs> {
+        self.format.set_field_name(field, Some(name));
+        self
+    }
+
+    /// Don't log the built-in `field`.
+    pub fn without_field(mut self, field: Field) -> Config<LogFmt, Kvs> {
+        self.format.set_field_name(field, None);
+        self
     }
 }
 
-/// Get the maximum log level based on the environment.
-pub(crate) fn get_max_level() -> LevelFilter {
-    for var in &["LOG", "LOG_LEVEL"] {
-        if let Ok(level) = env::var(var) {
-            if let Ok(level) = level.parse() {
-                return level;
+impl<Kvs> Config<Json, Kvs> {
+    /// Log the built-in `field` using `name` as key, e.g. `severity` instead
+    /// of `level`.
+    pub fn with_field_name(mut self, field: Field, name: &str) -> Config<Json, Kvs> {
+        self.format.set_field_name(field, Some(name));
+        self
+    }
+
+    /// Don't log the built-in `field`.
+    pub fn without_field(mut self, field: Field) -> Config<Json, Kvs> {
+        self.format.set_field_name(field, None);
+        self
+    }
+
+    /// Log all key-values, from the record and those added using
+    /// [`Config::with_kvs`], in a nested object with `key`, e.g.
+    /// `"fields":{"user_id":123}`, rather than in the top-level object.
+    pub fn with_nested_kvs(mut self, key: &str) -> Config<Json, Kvs> {
+        self.format.set_kvs_key(key);
+        self
+    }
+
+    /// Set how key-values with the same key as a built-in field are handled,
+    /// see [`KeyCollision`]. Defaults to [`KeyCollision::Duplicate`].
+    pub fn with_key_collision(mut self, collision: KeyCollision) -> Config<Json, Kvs> {
+        self.format.set_key_collision(collision);
+        self
+    }
+
+    /// Set the prefix used for key-values that collide with a built-in field
+    /// when using [`KeyCollision::Prefix`]. Defaults to `fields.`.
+    pub fn with_collision_prefix(mut self, prefix: &str) -> Config<Json, Kvs> {
+        self.format.set_collision_prefix(prefix);
+        self
+    }
+}
+
+impl<Kvs> Config<Emf, Kvs> {
+    /// Extract the key-value pairs with a key in `metrics` as metrics, e.g.
+    /// `latency_ms`. Only numeric values are extracted.
+    pub fn with_metrics<I>(mut self, metrics: I) -> Config<Emf, Kvs>
+    where
+        I: IntoIterator,
+        I::Item: Into<Box<str>>,
+    {
+        self.format
+            .set_metrics(metrics.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Use the key-value pairs with a key in `dimensions` as dimensions of the
+    /// metrics, e.g. `service` or `route`. Only the dimensions present in a
+    /// record are used.
+    pub fn with_dimensions<I>(mut self, dimensions: I) -> Config<Emf, Kvs>
+    where
+        I: IntoIterator,
+        I::Item: Into<Box<str>>,
+    {
+        self.format
+            .set_dimensions(dimensions.into_iter().map(Into::into).collect());
+        self
+    }
+}
+
+impl<Kvs> Config<OpenTelemetry, Kvs> {
+    /// Set the resource attributes logged with each record, e.g.
+    /// `service.name` and `deployment.environment.name`.
+    ///
+    /// Defaults to `service.name` set to the name of the executable.
+    pub fn with_resource<R>(self, resource: R) -> Config<OpenTelemetry, Kvs>
+    where
+        R: kv::Source,
+    {
+        Config {
+            format: OpenTelemetry::with_resource(&resource),
+            ..self
+        }
+    }
+}
+
+/// Parts of a [`Filter`] set in code, `None` if not set.
+#[derive(Debug, Default)]
+struct FilterOverrides {
+    level: Option<LevelFilter>,
+    targets: Option<Box<[Box<str>]>>,
+    excluded_targets: Option<Box<[Box<str>]>>,
+}
+
+impl FilterOverrides {
+    /// Overwrite the parts of `filter` that are set.
+    fn apply(&self, filter: &mut Filter) {
+        if let Some(level) = self.level {
+            filter.level = level;
+        }
+        if let Some(targets) = &self.targets {
+            filter.targets.only = targets.clone();
+        }
+        if let Some(targets) = &self.excluded_targets {
+            filter.targets.exclude = targets.clone();
+        }
+    }
+}
+
+/// Signals to handle, only supported on Unix.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Signals {
+    /// Reopen log files on `SIGHUP`.
+    reopen_on_sighup: bool,
+    /// Change the severity on `SIGUSR1` and `SIGUSR2`.
+    change_level: bool,
+}
+
+impl Signals {
+    /// Don't handle any signals.
+    pub(crate) const fn new() -> Signals {
+        Signals {
+            reopen_on_sighup: false,
+            change_level: false,
+        }
+    }
+
+    /// Handle the signals of both `self` and `other`.
+    pub(crate) const fn merge(self, other: Signals) -> Signals {
+        Signals {
+            reopen_on_sighup: self.reopen_on_sighup || other.reopen_on_sighup,
+            change_level: self.change_level || other.change_level,
+        }
+    }
+}
+
+/// Install `logger`, using `filters`, as the global logger.
+pub(crate) fn install(
+    logger: Box<dyn Log>,
+    filters: Box<[SharedFilter]>,
+    signals: Signals,
+) -> Result<LoggerHandle, SetLoggerError> {
+    log::set_boxed_logger(logger)?;
+    let handle = LoggerHandle::new(filters);
+    log::set_max_level(handle.level());
+
+    #[cfg(unix)]
+    if signals.reopen_on_sighup {
+        if let Err(err) = signal::install(libc::SIGHUP, signal::reopen_handler) {
+            log::warn!("failed to install SIGHUP signal handler: {err}");
+        }
+    }
+    #[cfg(unix)]
+    if signals.change_level {
+        if let Err(err) = signal::install_level_handlers(handle.clone()) {
+            log::warn!("failed to install SIGUSR1 and SIGUSR2 signal handlers: {err}");
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = signals;
+
+    #[cfg(feature = "log-panic")]
+    std::panic::set_hook(Box::new(log_panic));
+    Ok(handle)
+}
+
+/// Guard that flushes the logger when dropped.
+///
+/// Created by [`Config::init_with_guard`].
+#[derive(Debug)]
+#[must_use = "the logger is flushed when the guard is dropped"]
+pub struct FlushGuard {
+    handle: LoggerHandle,
+}
+
+impl FlushGuard {
+    /// Create a new guard, also flushing the logger when the process exits.
+    pub(crate) fn new(handle: LoggerHandle) -> FlushGuard {
+        #[cfg(unix)]
+        {
+            extern "C" fn flush_at_exit() {
+                log::logger().flush();
+            }
+            // SAFETY: `flush_at_exit` is safe to call at any point.
+            if unsafe { libc::atexit(flush_at_exit) } != 0 {
+                log::warn!("failed to register the logger to be flushed at exit");
             }
         }
+        FlushGuard { handle }
     }
 
-    if env::var("TRACE").is_ok() {
+    /// Returns the handle to the logger.
+    pub const fn handle(&self) -> &LoggerHandle {
+        &self.handle
+    }
+}
+
+impl Drop for FlushGuard {
+    fn drop(&mut self) {
+        log::logger().flush();
+    }
+}
+
+/// Get the log filter based on the environment, using environment variables
+/// prefixed with `prefix`.
+pub(crate) fn get_log_filter(prefix: &str) -> Filter {
+    let var = |name: &str| env::var(format!("{prefix}{name}"));
+    let level = if var("TRACE").is_ok() {
         LevelFilter::Trace
-    } else if env::var("DEBUG").is_ok() {
+    } else if var("DEBUG").is_ok() {
         LevelFilter::Debug
     } else {
         LevelFilter::Info
+    };
+
+    let filter = ["LOG", "LOG_LEVEL"]
+        .into_iter()
+        .filter_map(|name| var(name).ok())
+        .find_map(|value| parse_filter(&value, level))
+        .unwrap_or_else(|| Filter::new(level));
+    Filter {
+        targets: get_log_targets(prefix),
+        ..filter
     }
 }
 
-/// Get the targets to log, if any.
-pub(crate) fn get_log_targets() -> Targets {
-    match env::var("LOG_TARGET") {
-        Ok(ref targets) if !targets.is_empty() => {
-            Targets::Only(targets.split(',').map(Into::into).collect())
+/// Parse a filter in the format `level,target=level,...`, using `level` if no
+/// global level is set. Returns `None` if `value` holds no valid level or
+/// directive.
+fn parse_filter(value: &str, mut level: LevelFilter) -> Option<Filter> {
+    let mut valid = false;
+    let mut directives = Vec::new();
+    for part in value.split(',').map(str::trim) {
+        match part.split_once('=') {
+            Some((target, target_level)) => {
+                if let Ok(target_level) = target_level.trim().parse() {
+                    directives.push((target.trim().into(), target_level));
+                    valid = true;
+                }
+            }
+            None => {
+                if let Ok(global_level) = part.parse() {
+                    level = global_level;
+                    valid = true;
+                }
+            }
         }
-        _ => Targets::All,
+    }
+    valid.then(|| Filter::with_directives(level, directives))
+}
+
+/// Get the targets to log, if any, using the environment variable prefixed with
+/// `prefix`.
+///
+/// Targets prefixed with `-` are excluded.
+pub(crate) fn get_log_targets(prefix: &str) -> Targets {
+    let mut only = Vec::new();
+    let mut exclude = Vec::new();
+    if let Ok(targets) = env::var(format!("{prefix}LOG_TARGET")) {
+        for target in targets.split(',').filter(|target| !target.is_empty()) {
+            match target.strip_prefix('-') {
+                Some(target) => exclude.push(target.into()),
+                None => only.push(target.into()),
+            }
+        }
+    }
+    Targets {
+        only: only.into_boxed_slice(),
+        exclude: exclude.into_boxed_slice(),
     }
 }
 
@@ -189,6 +895,12 @@ fn log_panic(info: &std::panic::PanicInfo<'_>) {
             .args(format_args!("thread '{thread_name}' {info}"))
             .build(),
     );
+    // The process might be aborted after the panic, make sure the panic is
+    // actually written. Unless the panic came from the writer thread of a
+    // background sink, which would then wait on itself.
+    if !crate::sink::background::is_writer_thread() {
+        log::logger().flush();
+    }
 }
 
 /// No initial key-values.
@@ -0,0 +1,180 @@
+//! Logging to multiple destinations.
+
+use std::fmt;
+
+use log::{kv, Log, Metadata, Record, SetLoggerError};
+
+use crate::config::{install, Config, FlushGuard, Signals};
+use crate::format::Format;
+use crate::{LoggerHandle, SharedFilter};
+
+/// Logger that writes to multiple destinations, each with its own
+/// configuration.
+///
+/// Each destination is configured using a [`Config`], which determines the
+/// format, severity, targets and outputs used. A record is logged by all
+/// destinations that have the record's severity and target enabled.
+///
+/// # Examples
+///
+/// Log at debug level in logfmt to a local file, and at info level using
+/// gcloud's JSON format to standard error.
+///
+/// ```no_run
+/// use log::LevelFilter;
+/// use std_logger::sink::LogFile;
+/// use std_logger::{Config, Fanout};
+///
+/// # fn main() -> std::io::Result<()> {
+/// Fanout::new()
+///     .with(
+///         Config::logfmt()
+///             .with_level(LevelFilter::Debug)
+///             .with_output(LogFile::open("/var/log/my_app/debug.log")?)
+///             .with_request_output(LogFile::open("/var/log/my_app/access.log")?),
+///     )
+///     .with(Config::gcloud().with_level(LevelFilter::Info))
+///     .init();
+/// # Ok(())
+/// # }
+/// ```
+#[must_use = "the logger must be initialised using `init` or `try_init`"]
+pub struct Fanout {
+    loggers: Vec<Box<dyn Log>>,
+    /// Filters of all `loggers`.
+    filters: Vec<SharedFilter>,
+    signals: Signals,
+}
+
+impl Fanout {
+    /// Create a new logger without any destinations.
+    pub const fn new() -> Fanout {
+        Fanout {
+            loggers: Vec::new(),
+            filters: Vec::new(),
+            signals: Signals::new(),
+        }
+    }
+
+    /// Add a destination configured using `config`.
+    ///
+    /// If any of the configurations enables reopening files on `SIGHUP`, or
+    /// changing the severity using signals, it's enabled for all destinations.
+    pub fn with<F, Kvs>(mut self, config: Config<F, Kvs>) -> Fanout
+    where
+        F: Format + Send + Sync + 'static,
+        Kvs: kv::Source + Send + Sync + 'static,
+    {
+        let (logger, filter, signals) = config.into_logger();
+        self.loggers.push(logger);
+        self.filters.push(filter);
+        self.signals = self.signals.merge(signals);
+        self
+    }
+
+    /// Initialise the logger.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the logger fails to initialise. Use
+    /// [`Fanout::try_init`] if you want to handle the error yourself.
+    pub fn init(self) {
+        self.try_init()
+            .unwrap_or_else(|err| panic!("failed to initialise the logger: {err}"));
+    }
+
+    /// Try to initialise the logger.
+    ///
+    /// Unlike [`Fanout::init`] this doesn't panic when the logger fails to
+    /// initialise.
+    pub fn try_init(self) -> Result<(), SetLoggerError> {
+        self.try_init_with_handle().map(|_| ())
+    }
+
+    /// Initialise the logger, returning a handle to change what messages are
+    /// logged while the process is running.
+    ///
+    /// See [`Config::init_with_handle`].
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the logger fails to initialise. Use
+    /// [`Fanout::try_init_with_handle`] if you want to handle the error
+    /// yourself.
+    pub fn init_with_handle(self) -> LoggerHandle {
+        self.try_init_with_handle()
+            .unwrap_or_else(|err| panic!("failed to initialise the logger: {err}"))
+    }
+
+    /// Try to initialise the logger, returning a handle to change what messages
+    /// are logged while the process is running.
+    ///
+    /// See [`Config::init_with_handle`].
+    pub fn try_init_with_handle(self) -> Result<LoggerHandle, SetLoggerError> {
+        let logger = Box::new(FanoutLogger {
+            loggers: self.loggers.into_boxed_slice(),
+        });
+        install(logger, self.filters.into_boxed_slice(), self.signals)
+    }
+
+    /// Initialise the logger, returning a guard that flushes the logger when
+    /// dropped.
+    ///
+    /// See [`Config::init_with_guard`].
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the logger fails to initialise. Use
+    /// [`Fanout::try_init_with_guard`] if you want to handle the error
+    /// yourself.
+    pub fn init_with_guard(self) -> FlushGuard {
+        self.try_init_with_guard()
+            .unwrap_or_else(|err| panic!("failed to initialise the logger: {err}"))
+    }
+
+    /// Try to initialise the logger, returning a guard that flushes the logger
+    /// when dropped.
+    ///
+    /// See [`Config::init_with_guard`].
+    pub fn try_init_with_guard(self) -> Result<FlushGuard, SetLoggerError> {
+        self.try_init_with_handle().map(FlushGuard::new)
+    }
+}
+
+impl Default for Fanout {
+    fn default() -> Fanout {
+        Fanout::new()
+    }
+}
+
+impl fmt::Debug for Fanout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fanout")
+            .field("destinations", &self.loggers.len())
+            .field("signals", &self.signals)
+            .finish()
+    }
+}
+
+/// `Log` implementation of [`Fanout`].
+struct FanoutLogger {
+    loggers: Box<[Box<dyn Log>]>,
+}
+
+impl Log for FanoutLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.loggers.iter().any(|logger| logger.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        for logger in &self.loggers {
+            logger.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        for logger in &self.loggers {
+            logger.flush();
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Datadog structured logging using JSON, following
+//! <https://docs.datadoghq.com/logs/log_configuration/attributes_naming_convention>.
+
+use std::fmt::Write;
+use std::io::IoSlice;
+
+use log::kv::VisitSource;
+use log::{kv, Record};
+
+use crate::format::json::{self, Buf, KeyValueVisitor};
+use crate::format::{Buffer, Format, BUFS_SIZE};
+use crate::PANIC_TARGET;
+
+/// Keys of the key-value pairs that are promoted to the `dd` object.
+const DD_KEYS: [&str; 5] = [
+    "dd.trace_id",
+    "dd.span_id",
+    "dd.service",
+    "dd.env",
+    "dd.version",
+];
+
+/// Datadog structured logging using JSON, following
+/// <https://docs.datadoghq.com/logs/log_configuration/attributes_naming_convention>.
+///
+/// Created using [`Config::datadog`].
+///
+/// This is similar to the [`Json`] format, but uses Datadog's reserved and
+/// standard attributes:
+///  * `status` instead of `level`, panics are logged with the `critical`
+///    status,
+///  * `logger.name` for the target and `logger.method_name` for the module,
+///  * `logger.file_name` for the file and `logger.line` for the line (as
+///    number),
+///  * `error.kind`, `error.message` and `error.stack` for panics,
+///  * the `dd.trace_id`, `dd.span_id`, `dd.service`, `dd.env` and `dd.version`
+///    key-value pairs are logged in the `dd` object, used to correlate logs
+///    with traces.
+///
+/// [`Config::datadog`]: crate::Config::datadog
+/// [`Json`]: crate::format::Json
+#[derive(Debug)]
+pub struct Datadog(pub(crate) ());
+
+impl Format for Datadog {
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        add_loc: bool,
+    ) -> &'b [IoSlice<'b>] {
+        let is_panic = record.level() == log::Level::Error && record.target() == PANIC_TARGET;
+
+        // Write all parts of the buffer that need formatting.
+        json::write_start(buf);
+        #[cfg(feature = "timestamp")]
+        json::write_timestamp(buf);
+        json::write_msg(buf, record.args());
+        write_key_values(buf, record, kvs, is_panic);
+        if add_loc {
+            json::write_line(buf, record.line().unwrap_or(0));
+        }
+
+        // The first part of the message is the timestamp and status, e.g.
+        // `{"timestamp":"2020-12-31T12:32:23.906132Z","status":"info`.
+        bufs[0] = IoSlice::new(json::timestamp(buf));
+        bufs[1] = IoSlice::new(b"\"status\":\"");
+        bufs[2] = IoSlice::new(if is_panic {
+            b"critical"
+        } else {
+            status(record.level())
+        });
+        // The message, e.g. `","message":"some message`.
+        bufs[3] = IoSlice::new(b"\",\"message\":\"");
+        bufs[4] = IoSlice::new(json::msg(buf));
+        // The target and module, e.g.
+        // `","logger":{"name":"request","method_name":"stored::http`.
+        bufs[5] = IoSlice::new(b"\",\"logger\":{\"name\":\"");
+        bufs[6] = IoSlice::new(record.target().as_bytes());
+        bufs[7] = IoSlice::new(b"\",\"method_name\":\"");
+        bufs[8] = IoSlice::new(record.module_path().unwrap_or("").as_bytes());
+        // Optional file and line, e.g.
+        // `","file_name":"some_file.rs","line":123}`, ending the `logger`
+        // object.
+        let n = if add_loc {
+            bufs[9] = IoSlice::new(b"\",\"file_name\":\"");
+            bufs[10] = IoSlice::new(record.file().unwrap_or("??").as_bytes());
+            bufs[11] = IoSlice::new(b"\",\"line\":");
+            bufs[12] = IoSlice::new(json::line(buf));
+            bufs[13] = IoSlice::new(b"}");
+            14
+        } else {
+            bufs[9] = IoSlice::new(b"\"}");
+            10
+        };
+        // Any key value pairs supplied by the user, the `dd` object and the
+        // error of a panic, and a line end.
+        bufs[n] = IoSlice::new(json::key_values(buf));
+        bufs[n + 1] = IoSlice::new(b"}\n");
+        &bufs[..n + 2]
+    }
+}
+
+/// Write the key-values, the `dd` object and the error of a panic.
+#[inline]
+fn write_key_values<Kvs: kv::Source>(buf: &mut Buffer, record: &Record, kvs: &Kvs, is_panic: bool) {
+    let mut visitor = DatadogVisitor {
+        buf: &mut buf.buf,
+        dd: Vec::new(),
+        stack: Vec::new(),
+        is_panic,
+    };
+    record
+        .key_values()
+        .visit(&mut visitor)
+        .unwrap_or_else(|_| unreachable!());
+    kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+    let DatadogVisitor { dd, stack, .. } = visitor;
+    if !dd.is_empty() {
+        buf.buf.extend_from_slice(b",\"dd\":{");
+        // Skip the leading comma.
+        buf.buf.extend_from_slice(&dd[1..]);
+        buf.buf.push(b'}');
+    }
+    if is_panic {
+        buf.buf
+            .extend_from_slice(b",\"error\":{\"kind\":\"panic\",\"message\":\"");
+        json::write_escaped(&mut buf.buf, record.args());
+        buf.buf.push(b'"');
+        if !stack.is_empty() {
+            buf.buf.extend_from_slice(b",\"stack\":\"");
+            buf.buf.extend_from_slice(&stack);
+            buf.buf.push(b'"');
+        }
+        buf.buf.push(b'}');
+    }
+    buf.indices[1] = buf.buf.len();
+}
+
+/// Same as [`KeyValueVisitor`], but writes the `dd.*` key-values to `dd` and
+/// the `backtrace` of a panic to `stack`.
+struct DatadogVisitor<'b> {
+    buf: &'b mut Vec<u8>,
+    dd: Vec<u8>,
+    stack: Vec<u8>,
+    is_panic: bool,
+}
+
+impl<'b, 'kvs> VisitSource<'kvs> for DatadogVisitor<'b> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let k = key.as_str();
+        if DD_KEYS.contains(&k) {
+            let key = kv::Key::from_str(&k[3..]);
+            KeyValueVisitor(&mut self.dd).visit_pair(key, value)
+        } else if self.is_panic && k == "backtrace" {
+            Buf(&mut self.stack)
+                .write_fmt(format_args!("{value}"))
+                .unwrap_or_else(|_| unreachable!());
+            Ok(())
+        } else {
+            KeyValueVisitor(self.buf).visit_pair(key, value)
+        }
+    }
+}
+
+#[inline]
+const fn status(level: log::Level) -> &'static [u8] {
+    const STATUSES: [&[u8]; 6] = [b"off", b"error", b"warn", b"info", b"debug", b"trace"];
+    STATUSES[level as usize]
+}
@@ -0,0 +1,102 @@
+//! Format selected at runtime.
+
+use std::io::IoSlice;
+
+use log::{kv, Record};
+
+use crate::format::{
+    Auto, Buffer, Datadog, Ecs, Format, Gcloud, Gelf, Json, LogFmt, OpenTelemetry, Pretty, Syslog,
+    BUFS_SIZE,
+};
+
+/// Format selected at runtime, e.g. using the `LOG_FORMAT` environment
+/// variable, see [`Config::from_env`].
+///
+/// This uses an `enum` to dispatch to the selected format, rather than dynamic
+/// dispatch.
+///
+/// [`Config::from_env`]: crate::Config::from_env
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Dynamic {
+    /// Use the [`LogFmt`] format.
+    LogFmt(LogFmt),
+    /// Use the [`Json`] format.
+    Json(Json),
+    /// Use the [`Gcloud`] format.
+    Gcloud(Gcloud),
+    /// Use the [`Ecs`] format.
+    Ecs(Ecs),
+    /// Use the [`Datadog`] format.
+    Datadog(Datadog),
+    /// Use the [`Gelf`] format.
+    Gelf(Gelf),
+    /// Use the [`OpenTelemetry`] format.
+    OpenTelemetry(OpenTelemetry),
+    /// Use the [`Syslog`] format.
+    Syslog(Syslog),
+    /// Use the [`Pretty`] format.
+    Pretty(Pretty),
+    /// Use the [`Pretty`] format if the output is a terminal, [`LogFmt`]
+    /// otherwise.
+    Auto(Auto<LogFmt>),
+}
+
+impl Dynamic {
+    /// Returns the format with `name`, if any.
+    pub(crate) fn from_name(name: &str) -> Option<Dynamic> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "logfmt" => Some(Dynamic::LogFmt(LogFmt::new())),
+            "json" => Some(Dynamic::Json(Json::new())),
+            "gcloud" => Some(Dynamic::Gcloud(Gcloud(()))),
+            "ecs" => Some(Dynamic::Ecs(Ecs(()))),
+            "datadog" => Some(Dynamic::Datadog(Datadog(()))),
+            "gelf" => Some(Dynamic::Gelf(Gelf(()))),
+            "opentelemetry" | "otel" => Some(Dynamic::OpenTelemetry(OpenTelemetry::new())),
+            "syslog" => Some(Dynamic::Syslog(Syslog(()))),
+            "pretty" => Some(Dynamic::Pretty(Pretty::new())),
+            "auto" => Some(Dynamic::Auto(Auto::new(LogFmt::new()))),
+            _ => None,
+        }
+    }
+}
+
+impl Format for Dynamic {
+    #[inline]
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        add_loc: bool,
+    ) -> &'b [IoSlice<'b>] {
+        match self {
+            Dynamic::LogFmt(format) => format.format(bufs, buf, record, kvs, add_loc),
+            Dynamic::Json(format) => format.format(bufs, buf, record, kvs, add_loc),
+            Dynamic::Gcloud(format) => format.format(bufs, buf, record, kvs, add_loc),
+            Dynamic::Ecs(format) => format.format(bufs, buf, record, kvs, add_loc),
+            Dynamic::Datadog(format) => format.format(bufs, buf, record, kvs, add_loc),
+            Dynamic::Gelf(format) => format.format(bufs, buf, record, kvs, add_loc),
+            Dynamic::OpenTelemetry(format) => format.format(bufs, buf, record, kvs, add_loc),
+            Dynamic::Syslog(format) => format.format(bufs, buf, record, kvs, add_loc),
+            Dynamic::Pretty(format) => format.format(bufs, buf, record, kvs, add_loc),
+            Dynamic::Auto(format) => format.format(bufs, buf, record, kvs, add_loc),
+        }
+    }
+
+    fn set_terminal(&mut self, is_terminal: bool) {
+        match self {
+            Dynamic::LogFmt(format) => format.set_terminal(is_terminal),
+            Dynamic::Json(format) => format.set_terminal(is_terminal),
+            Dynamic::Gcloud(format) => format.set_terminal(is_terminal),
+            Dynamic::Ecs(format) => format.set_terminal(is_terminal),
+            Dynamic::Datadog(format) => format.set_terminal(is_terminal),
+            Dynamic::Gelf(format) => format.set_terminal(is_terminal),
+            Dynamic::OpenTelemetry(format) => format.set_terminal(is_terminal),
+            Dynamic::Syslog(format) => format.set_terminal(is_terminal),
+            Dynamic::Pretty(format) => format.set_terminal(is_terminal),
+            Dynamic::Auto(format) => format.set_terminal(is_terminal),
+        }
+    }
+}
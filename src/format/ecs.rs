@@ -0,0 +1,152 @@
+//! Elastic Common Schema (ECS) logging using JSON, following
+//! <https://www.elastic.co/guide/en/ecs-logging/overview/current/intro.html>.
+
+use std::fmt::Write;
+use std::io::IoSlice;
+
+use log::kv::VisitSource;
+use log::{kv, Record};
+
+use crate::format::json::{self, Buf, KeyValueVisitor};
+use crate::format::{Buffer, Format, BUFS_SIZE};
+use crate::PANIC_TARGET;
+
+/// Elastic Common Schema (ECS) logging using JSON, following
+/// <https://www.elastic.co/guide/en/ecs-logging/overview/current/intro.html>.
+///
+/// Created using [`Config::ecs`].
+///
+/// The following ECS fields are logged: `@timestamp`, `log.level`, `message`,
+/// `ecs.version`, `log.logger` (the target) and, if enabled,
+/// `log.origin.file.name` and `log.origin.file.line`. Key-value pairs are
+/// logged as is. Panics are logged with the `critical` log level, the message
+/// is also logged as `error.message` and the backtrace as `error.stack_trace`.
+///
+/// [`Config::ecs`]: crate::Config::ecs
+#[derive(Debug)]
+pub struct Ecs(pub(crate) ());
+
+impl Format for Ecs {
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        add_loc: bool,
+    ) -> &'b [IoSlice<'b>] {
+        let is_panic = record.level() == log::Level::Error && record.target() == PANIC_TARGET;
+
+        // Write all parts of the buffer that need formatting.
+        json::write_start(buf);
+        #[cfg(feature = "timestamp")]
+        json::write_timestamp(buf);
+        json::write_msg(buf, record.args());
+        write_key_values(buf, record, kvs, is_panic);
+        if add_loc {
+            json::write_line(buf, record.line().unwrap_or(0));
+        }
+
+        // The timestamp, e.g. `{"@timestamp":"2020-12-31T12:32:23.906132Z",`.
+        // Or without a timestamp, i.e. `{`.
+        #[cfg(feature = "timestamp")]
+        {
+            bufs[0] = IoSlice::new(b"{\"@timestamp\":\"");
+            // Skip `{"timestamp":"` written by `json::write_timestamp`.
+            bufs[1] = IoSlice::new(&json::timestamp(buf)[14..]);
+        }
+        #[cfg(not(feature = "timestamp"))]
+        {
+            bufs[0] = IoSlice::new(json::timestamp(buf));
+            bufs[1] = IoSlice::new(b"");
+        }
+        // The log level, e.g. `"log.level":"info`.
+        bufs[2] = IoSlice::new(b"\"log.level\":\"");
+        bufs[3] = IoSlice::new(if is_panic {
+            b"critical"
+        } else {
+            level(record.level())
+        });
+        // The message, e.g. `","message":"some message`.
+        bufs[4] = IoSlice::new(b"\",\"message\":\"");
+        bufs[5] = IoSlice::new(json::msg(buf));
+        // The ECS version and the target, e.g.
+        // `","ecs.version":"1.6.0","log.logger":"request`.
+        bufs[6] = IoSlice::new(b"\",\"ecs.version\":\"1.6.0\",\"log.logger\":\"");
+        bufs[7] = IoSlice::new(record.target().as_bytes());
+        // Any key value pairs supplied by the user, and the error of a panic.
+        bufs[8] = IoSlice::new(json::key_values(buf));
+        // Optional file, e.g.
+        // `,"log.origin.file.name":"some_file.rs","log.origin.file.line":123}`,
+        // and a line end.
+        let n = if add_loc {
+            bufs[9] = IoSlice::new(b",\"log.origin.file.name\":\"");
+            bufs[10] = IoSlice::new(record.file().unwrap_or("??").as_bytes());
+            bufs[11] = IoSlice::new(b"\",\"log.origin.file.line\":");
+            bufs[12] = IoSlice::new(json::line(buf));
+            bufs[13] = IoSlice::new(b"}\n");
+            14
+        } else {
+            bufs[9] = IoSlice::new(b"}\n");
+            10
+        };
+        &bufs[..n]
+    }
+}
+
+/// Same as [`json::write_key_values`], but for panics the backtrace is logged
+/// as `error.stack_trace` along with the message as `error.message`.
+#[inline]
+fn write_key_values<Kvs: kv::Source>(buf: &mut Buffer, record: &Record, kvs: &Kvs, is_panic: bool) {
+    if !is_panic {
+        json::write_key_values(buf, record.key_values(), kvs);
+        return;
+    }
+
+    buf.buf.push(b'"');
+    let mut visitor = PanicVisitor {
+        buf: &mut buf.buf,
+        stack_trace: Vec::new(),
+    };
+    record
+        .key_values()
+        .visit(&mut visitor)
+        .unwrap_or_else(|_| unreachable!());
+    kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+    let stack_trace = visitor.stack_trace;
+    buf.buf.extend_from_slice(b",\"error.message\":\"");
+    json::write_escaped(&mut buf.buf, record.args());
+    buf.buf.push(b'"');
+    if !stack_trace.is_empty() {
+        buf.buf.extend_from_slice(b",\"error.stack_trace\":\"");
+        buf.buf.extend_from_slice(&stack_trace);
+        buf.buf.push(b'"');
+    }
+    buf.indices[1] = buf.buf.len();
+}
+
+/// Same as [`KeyValueVisitor`], but writes the `backtrace` to `stack_trace`
+/// instead.
+struct PanicVisitor<'b> {
+    buf: &'b mut Vec<u8>,
+    stack_trace: Vec<u8>,
+}
+
+impl<'b, 'kvs> VisitSource<'kvs> for PanicVisitor<'b> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        if key.as_str() == "backtrace" {
+            Buf(&mut self.stack_trace)
+                .write_fmt(format_args!("{value}"))
+                .unwrap_or_else(|_| unreachable!());
+            Ok(())
+        } else {
+            KeyValueVisitor(self.buf).visit_pair(key, value)
+        }
+    }
+}
+
+#[inline]
+const fn level(level: log::Level) -> &'static [u8] {
+    const LEVELS: [&[u8]; 6] = [b"off", b"error", b"warn", b"info", b"debug", b"trace"];
+    LEVELS[level as usize]
+}
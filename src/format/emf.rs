@@ -0,0 +1,176 @@
+//! AWS CloudWatch Embedded Metric Format (EMF), following
+//! <https://docs.aws.amazon.com/AmazonCloudWatch/latest/monitoring/CloudWatch_Embedded_Metric_Format_Specification.html>.
+
+use std::io::IoSlice;
+use std::time::{Duration, SystemTime};
+
+use log::kv::VisitSource;
+use log::{kv, Record};
+
+use crate::format::json;
+use crate::format::{Buffer, Format, BUFS_SIZE};
+
+/// AWS CloudWatch Embedded Metric Format (EMF), following
+/// <https://docs.aws.amazon.com/AmazonCloudWatch/latest/monitoring/CloudWatch_Embedded_Metric_Format_Specification.html>.
+///
+/// Created using [`Config::emf`].
+///
+/// Records are formatted the same way as the [`Json`] format. If a record
+/// has one or more numeric key-value pairs with a key that is configured as
+/// metric (see [`Config::with_metrics`]), the `_aws` metadata object is
+/// added. This tells CloudWatch to extract those key-value pairs as metrics,
+/// in the configured namespace, using the configured dimensions (see
+/// [`Config::with_dimensions`]) that are present in the record. All key-value
+/// pairs, including metrics and dimensions, are also logged as regular
+/// properties.
+///
+/// [`Config::emf`]: crate::Config::emf
+/// [`Json`]: crate::format::Json
+/// [`Config::with_metrics`]: crate::Config::with_metrics
+/// [`Config::with_dimensions`]: crate::Config::with_dimensions
+#[derive(Debug)]
+pub struct Emf {
+    namespace: Box<str>,
+    dimensions: Box<[Box<str>]>,
+    metrics: Box<[Box<str>]>,
+}
+
+impl Emf {
+    /// Create a new format logging metrics in `namespace`.
+    pub(crate) fn new(namespace: Box<str>) -> Emf {
+        Emf {
+            namespace,
+            dimensions: Box::new([]),
+            metrics: Box::new([]),
+        }
+    }
+
+    pub(crate) fn set_dimensions(&mut self, dimensions: Box<[Box<str>]>) {
+        self.dimensions = dimensions;
+    }
+
+    pub(crate) fn set_metrics(&mut self, metrics: Box<[Box<str>]>) {
+        self.metrics = metrics;
+    }
+
+    /// Write the `_aws` metadata object for the metrics in the record, if
+    /// any, e.g. `,"_aws":{"Timestamp":1608719876123,"CloudWatchMetrics":[..]}`.
+    fn write_metadata<Kvs: kv::Source>(&self, buf: &mut Vec<u8>, record: &Record, kvs: &Kvs) {
+        if self.metrics.is_empty() {
+            return;
+        }
+
+        let mut visitor = MetricVisitor {
+            emf: self,
+            dimensions: Vec::new(),
+            metrics: Vec::new(),
+        };
+        record
+            .key_values()
+            .visit(&mut visitor)
+            .unwrap_or_else(|_| unreachable!());
+        kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+        if visitor.metrics.is_empty() {
+            return;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO);
+        let mut itoa = itoa::Buffer::new();
+        buf.extend_from_slice(b",\"_aws\":{\"Timestamp\":");
+        buf.extend_from_slice(itoa.format(now.as_millis()).as_bytes());
+        buf.extend_from_slice(b",\"CloudWatchMetrics\":[{\"Namespace\":\"");
+        json::write_escaped(buf, &format_args!("{}", self.namespace));
+        buf.extend_from_slice(b"\",\"Dimensions\":[[");
+        write_names(buf, &visitor.dimensions, b"\"", b"\"");
+        buf.extend_from_slice(b"]],\"Metrics\":[");
+        write_names(buf, &visitor.metrics, b"{\"Name\":\"", b"\"}");
+        buf.extend_from_slice(b"]}]}");
+    }
+}
+
+/// Write `names` separated by a comma, each name between `prefix` and
+/// `suffix`.
+fn write_names(buf: &mut Vec<u8>, names: &[&str], prefix: &[u8], suffix: &[u8]) {
+    for (i, name) in names.iter().enumerate() {
+        if i != 0 {
+            buf.push(b',');
+        }
+        buf.extend_from_slice(prefix);
+        json::write_escaped(buf, &format_args!("{name}"));
+        buf.extend_from_slice(suffix);
+    }
+}
+
+impl Format for Emf {
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        add_loc: bool,
+    ) -> &'b [IoSlice<'b>] {
+        // Write all parts of the buffer that need formatting.
+        json::write_start(buf);
+        #[cfg(feature = "timestamp")]
+        json::write_timestamp(buf);
+        json::write_msg(buf, record.args());
+        json::write_key_values(buf, record.key_values(), kvs);
+        // The metadata is written as part of the key-values.
+        self.write_metadata(&mut buf.buf, record, kvs);
+        buf.indices[1] = buf.buf.len();
+        if add_loc {
+            json::write_line(buf, record.line().unwrap_or(0));
+        }
+
+        // From here on this is the same as the `Json` format.
+        bufs[0] = IoSlice::new(json::timestamp(buf));
+        bufs[1] = IoSlice::new(b"\"level\":\"");
+        bufs[2] = IoSlice::new(record.level().as_str().as_bytes());
+        bufs[3] = IoSlice::new(b"\",\"message\":\"");
+        bufs[4] = IoSlice::new(json::msg(buf));
+        bufs[5] = IoSlice::new(b"\",\"target\":\"");
+        bufs[6] = IoSlice::new(record.target().as_bytes());
+        bufs[7] = IoSlice::new(b"\",\"module\":\"");
+        bufs[8] = IoSlice::new(record.module_path().unwrap_or("").as_bytes());
+        bufs[9] = IoSlice::new(json::key_values(buf));
+        let n = if add_loc {
+            bufs[10] = IoSlice::new(b",\"file\":\"");
+            bufs[11] = IoSlice::new(record.file().unwrap_or("??").as_bytes());
+            bufs[12] = IoSlice::new(b"\",\"line\":\"");
+            bufs[13] = IoSlice::new(json::line(buf));
+            bufs[14] = IoSlice::new(b"\"}\n");
+            15
+        } else {
+            bufs[10] = IoSlice::new(b"}\n");
+            11
+        };
+        &bufs[..n]
+    }
+}
+
+/// Collects the configured dimensions and metrics present in the key-values.
+struct MetricVisitor<'e> {
+    emf: &'e Emf,
+    dimensions: Vec<&'e str>,
+    metrics: Vec<&'e str>,
+}
+
+impl<'e, 'kvs> VisitSource<'kvs> for MetricVisitor<'e> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let key = key.as_str();
+        if let Some(name) = self.emf.metrics.iter().find(|name| ***name == *key) {
+            // Only numbers can be metrics.
+            if value.to_f64().is_some() && !self.metrics.contains(&&**name) {
+                self.metrics.push(name);
+            }
+        } else if let Some(name) = self.emf.dimensions.iter().find(|name| ***name == *key) {
+            if !self.dimensions.contains(&&**name) {
+                self.dimensions.push(name);
+            }
+        }
+        Ok(())
+    }
+}
@@ -11,11 +11,16 @@ use crate::PANIC_TARGET;
 
 /// Google Cloud Platform structured logging using JSON, following
 /// <https://cloud.google.com/logging/docs/structured-logging>.
-#[allow(missing_debug_implementations)]
-pub enum Gcloud {}
+///
+/// Created using [`Config::gcloud`].
+///
+/// [`Config::gcloud`]: crate::Config::gcloud
+#[derive(Debug)]
+pub struct Gcloud(pub(crate) ());
 
 impl Format for Gcloud {
     fn format<'b, Kvs: kv::Source>(
+        &'b self,
         bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
         buf: &'b mut Buffer,
         record: &'b Record,
@@ -23,7 +28,7 @@ impl Format for Gcloud {
         add_loc: bool,
     ) -> &'b [IoSlice<'b>] {
         // Write all parts of the buffer that need formatting.
-        buf.buf[0] = b'{';
+        json::write_start(buf);
         #[cfg(feature = "timestamp")]
         json::write_timestamp(buf);
         json::write_msg(buf, record.args());
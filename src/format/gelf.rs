@@ -0,0 +1,201 @@
+//! Graylog Extended Log Format (GELF), following
+//! <https://go2docs.graylog.org/current/getting_in_log_data/gelf.html>.
+
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::io::IoSlice;
+
+use log::kv::VisitSource;
+use log::{kv, Record};
+
+use crate::format::json::{self, Buf, KeyValueVisitor};
+use crate::format::syslog::{hostname, severity};
+use crate::format::{Buffer, Format, BUFS_SIZE};
+use crate::PANIC_TARGET;
+
+/// Graylog Extended Log Format (GELF) version 1.1, following
+/// <https://go2docs.graylog.org/current/getting_in_log_data/gelf.html>.
+///
+/// Created using [`Config::gelf`].
+///
+/// The following fields are logged:
+///  * `version`: always `1.1`,
+///  * `host`: the hostname of the machine,
+///  * `short_message`: the first line of the message, or `-` if that's empty,
+///  * `full_message`: the entire message, only if it's longer than a single
+///    line, and for panics followed by the backtrace,
+///  * `timestamp`: seconds since Unix epoch, with microseconds as fraction
+///    (only if the *timestamp* feature is enabled),
+///  * `level`: the syslog severity, see the [`Syslog`] format,
+///  * `_target` and `_module`: the target and module path of the record,
+///  * `_file` and `_line`: if enabled, the file and line.
+///
+/// All key-value pairs are logged as additional fields, with their keys
+/// prefixed with `_`. Characters not allowed in field names, i.e. anything
+/// other than ASCII letters, digits, `_`, `.` and `-`, are replaced with `_`.
+/// As `_id` is reserved the key `id` is logged as `__id`.
+///
+/// Records can be send to Graylog using the [`sink::Gelf`] sink.
+///
+/// [`Config::gelf`]: crate::Config::gelf
+/// [`Syslog`]: crate::format::Syslog
+/// [`sink::Gelf`]: crate::sink::Gelf
+#[derive(Debug)]
+pub struct Gelf(pub(crate) ());
+
+impl Format for Gelf {
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        add_loc: bool,
+    ) -> &'b [IoSlice<'b>] {
+        let is_panic = record.level() == log::Level::Error && record.target() == PANIC_TARGET;
+        let buf = &mut buf.buf;
+        buf.clear();
+        buf.extend_from_slice(b"{\"version\":\"1.1\",\"host\":\"");
+        json::write_escaped(buf, &format_args!("{}", hostname()));
+
+        // The message.
+        buf.extend_from_slice(b"\",\"short_message\":\"");
+        let msg_start = buf.len();
+        json::write_escaped(buf, record.args());
+        let msg_end = buf.len();
+        // Fields other than the message, written after the full message.
+        let mut visitor = FieldVisitor {
+            buf: Vec::new(),
+            backtrace: Vec::new(),
+            is_panic,
+        };
+        record
+            .key_values()
+            .visit(&mut visitor)
+            .unwrap_or_else(|_| unreachable!());
+        kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+        let first_line = first_line_end(&buf[msg_start..msg_end]).map(|n| msg_start + n);
+        if first_line.is_some() || !visitor.backtrace.is_empty() {
+            buf.extend_from_slice(b"\",\"full_message\":\"");
+            buf.extend_from_within(msg_start..msg_end);
+            if !visitor.backtrace.is_empty() {
+                buf.extend_from_slice(b"\\n");
+                buf.extend_from_slice(&visitor.backtrace);
+            }
+            if let Some(end) = first_line {
+                // Only keep the first line of the short message.
+                let _ = buf.drain(end..msg_end);
+            }
+        }
+        // The short message can't be empty.
+        if first_line.unwrap_or(msg_end) == msg_start {
+            buf.insert(msg_start, b'-');
+        }
+
+        #[cfg(feature = "timestamp")]
+        {
+            buf.extend_from_slice(b"\",\"timestamp\":");
+            write_timestamp(buf);
+            buf.extend_from_slice(b",\"level\":");
+        }
+        #[cfg(not(feature = "timestamp"))]
+        buf.extend_from_slice(b"\",\"level\":");
+        let level = if is_panic {
+            2 // Critical.
+        } else {
+            severity(record.level())
+        };
+        buf.push(b'0' + level);
+        buf.extend_from_slice(b",\"_target\":\"");
+        json::write_escaped(buf, &format_args!("{}", record.target()));
+        buf.extend_from_slice(b"\",\"_module\":\"");
+        json::write_escaped(buf, &format_args!("{}", record.module_path().unwrap_or("")));
+        buf.push(b'"');
+        buf.extend_from_slice(&visitor.buf);
+        if add_loc {
+            buf.extend_from_slice(b",\"_file\":\"");
+            json::write_escaped(buf, &format_args!("{}", record.file().unwrap_or("??")));
+            buf.extend_from_slice(b"\",\"_line\":");
+            let mut itoa = itoa::Buffer::new();
+            buf.extend_from_slice(itoa.format(record.line().unwrap_or(0)).as_bytes());
+        }
+        buf.extend_from_slice(b"}\n");
+
+        bufs[0] = IoSlice::new(buf);
+        &bufs[..1]
+    }
+}
+
+/// Returns the end of the first line in the JSON escaped `msg`, if it has more
+/// than one line.
+fn first_line_end(msg: &[u8]) -> Option<usize> {
+    let mut i = 0;
+    while i < msg.len() {
+        if msg[i] == b'\\' {
+            if msg.get(i + 1) == Some(&b'n') {
+                return Some(i);
+            }
+            // Skip the escaped character.
+            i += 1;
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Write the current time as seconds since Unix epoch, with microseconds as
+/// fraction, e.g. `1608719876.123456`.
+#[cfg(feature = "timestamp")]
+fn write_timestamp(buf: &mut Vec<u8>) {
+    use std::time::{Duration, SystemTime};
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO);
+    let mut itoa = itoa::Buffer::new();
+    buf.extend_from_slice(itoa.format(now.as_secs()).as_bytes());
+    buf.push(b'.');
+    let micros = itoa.format(now.subsec_micros());
+    buf.resize(buf.len() + 6 - micros.len(), b'0');
+    buf.extend_from_slice(micros.as_bytes());
+}
+
+/// Writes key value pairs as additional fields, i.e. with the key prefixed
+/// with `_`, except for the backtrace of a panic which is written to
+/// `backtrace`.
+struct FieldVisitor {
+    buf: Vec<u8>,
+    backtrace: Vec<u8>,
+    is_panic: bool,
+}
+
+impl<'kvs> VisitSource<'kvs> for FieldVisitor {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        if self.is_panic && key.as_str() == "backtrace" {
+            Buf(&mut self.backtrace)
+                .write_fmt(format_args!("{value}"))
+                .unwrap_or_else(|_| unreachable!());
+            return Ok(());
+        }
+
+        let key = field_name(key.as_str());
+        // `_id` is reserved.
+        let prefix = if key == "id" { "__" } else { "_" };
+        KeyValueVisitor(&mut self.buf).write_pair(prefix, &key, value)
+    }
+}
+
+/// Returns `key` with the characters not allowed in the name of an additional
+/// field replaced with `_`.
+fn field_name(key: &str) -> Cow<'_, str> {
+    let is_valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-');
+    if key.chars().all(is_valid) {
+        Cow::Borrowed(key)
+    } else {
+        let key = key
+            .chars()
+            .map(|c| if is_valid(c) { c } else { '_' })
+            .collect();
+        Cow::Owned(key)
+    }
+}
@@ -0,0 +1,153 @@
+//! Native systemd-journald protocol, following
+//! <https://systemd.io/JOURNAL_NATIVE_PROTOCOL>.
+
+use std::fmt::{self, Write};
+use std::io::IoSlice;
+
+use log::kv::VisitSource;
+use log::{kv, Record};
+
+use crate::format::{Buffer, Format, BUFS_SIZE};
+use crate::PANIC_TARGET;
+
+/// Native systemd-journald protocol, following
+/// <https://systemd.io/JOURNAL_NATIVE_PROTOCOL>.
+///
+/// Created using [`Config::journald`], which also sends the records to
+/// journald.
+///
+/// The following fields are logged:
+///  * `PRIORITY`: the log level mapped to a syslog priority.
+///  * `MESSAGE`: the message.
+///  * `TARGET`: the log target.
+///  * `CODE_MODULE`: the module path, if any.
+///  * `CODE_FILE` and `CODE_LINE`: the file and line, if any.
+///  * All key-values, with the key converted to a valid journal field name,
+///    e.g. `user_id` becomes `USER_ID`.
+///
+/// [`Config::journald`]: crate::Config::journald
+#[derive(Debug)]
+pub struct Journald(pub(crate) ());
+
+impl Format for Journald {
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        _: bool,
+    ) -> &'b [IoSlice<'b>] {
+        let buf = &mut buf.buf;
+        buf.clear();
+        buf.extend_from_slice(b"PRIORITY=");
+        if record.level() == log::Level::Error && record.target() == PANIC_TARGET {
+            // If we're panicking we increase the priority to critical.
+            buf.push(b'2');
+        } else {
+            buf.push(priority(record.level()));
+        }
+        buf.push(b'\n');
+        write_field(buf, b"MESSAGE", record.args());
+        write_field(buf, b"TARGET", &format_args!("{}", record.target()));
+        if let Some(module) = record.module_path() {
+            write_field(buf, b"CODE_MODULE", &format_args!("{module}"));
+        }
+        if let Some(file) = record.file() {
+            write_field(buf, b"CODE_FILE", &format_args!("{file}"));
+        }
+        if let Some(line) = record.line() {
+            buf.extend_from_slice(b"CODE_LINE=");
+            let mut itoa = itoa::Buffer::new();
+            buf.extend_from_slice(itoa.format(line).as_bytes());
+            buf.push(b'\n');
+        }
+        let mut visitor = KeyValueVisitor(buf);
+        record
+            .key_values()
+            .visit(&mut visitor)
+            .unwrap_or_else(|_| unreachable!());
+        kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+
+        bufs[0] = IoSlice::new(buf);
+        &bufs[..1]
+    }
+}
+
+/// Returns the syslog priority for `level`, as ASCII digit.
+#[inline]
+const fn priority(level: log::Level) -> u8 {
+    b'0' + super::syslog::severity(level)
+}
+
+/// Write the field `name` with `value`.
+///
+/// Uses `NAME=value\n` if `value` doesn't contain a new line, or the binary
+/// safe `NAME\n<64 bit little endian length>value\n` if it does.
+fn write_field(buf: &mut Vec<u8>, name: &[u8], value: &fmt::Arguments) {
+    buf.extend_from_slice(name);
+    let separator_index = buf.len();
+    buf.push(b'=');
+    let value_start = buf.len();
+    if let Some(value) = value.as_str() {
+        buf.extend_from_slice(value.as_bytes());
+    } else {
+        RawBuf(buf)
+            .write_fmt(*value)
+            .unwrap_or_else(|_| unreachable!());
+    }
+
+    if buf[value_start..].contains(&b'\n') {
+        let len = (buf.len() - value_start) as u64;
+        buf[separator_index] = b'\n';
+        let _ = buf.splice(value_start..value_start, len.to_le_bytes());
+    }
+    buf.push(b'\n');
+}
+
+/// Writes key-values as journal fields, see [`write_field`].
+struct KeyValueVisitor<'b>(&'b mut Vec<u8>);
+
+impl<'b, 'kvs> VisitSource<'kvs> for KeyValueVisitor<'b> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let mut name = [0; MAX_FIELD_NAME_LEN];
+        let name = field_name(key.as_str(), &mut name);
+        if !name.is_empty() {
+            write_field(self.0, name, &format_args!("{value}"));
+        }
+        Ok(())
+    }
+}
+
+/// Maximum length of a journal field name.
+const MAX_FIELD_NAME_LEN: usize = 64;
+
+/// Convert `key` into a valid journal field name.
+///
+/// Journal field names may only contain uppercase letters, digits and
+/// underscores, must start with a letter and may be at most 64 characters long.
+/// All other characters are replaced with an underscore, leading characters
+/// that are not letters are removed.
+fn field_name<'n>(key: &str, name: &'n mut [u8; MAX_FIELD_NAME_LEN]) -> &'n [u8] {
+    let key = key.trim_start_matches(|c: char| !c.is_ascii_alphabetic());
+    let mut len = 0;
+    for (b, n) in key.bytes().zip(name.iter_mut()) {
+        *n = match b {
+            b'a'..=b'z' => b.to_ascii_uppercase(),
+            b'A'..=b'Z' | b'0'..=b'9' => b,
+            _ => b'_',
+        };
+        len += 1;
+    }
+    &name[..len]
+}
+
+/// [`fmt::Write`] implementation that writes the string as is.
+struct RawBuf<'b>(&'b mut Vec<u8>);
+
+impl<'b> fmt::Write for RawBuf<'b> {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        self.0.extend_from_slice(string.as_bytes());
+        Ok(())
+    }
+}
@@ -7,23 +7,136 @@ use log::kv::{VisitSource, VisitValue};
 use log::{kv, Record};
 
 #[cfg(feature = "timestamp")]
-use crate::format::format_timestamp;
-use crate::format::{Buffer, Format, BUFS_SIZE};
+use crate::format::{format_timestamp, TIMESTAMP_LEN};
+use crate::format::{Buffer, Field, FieldNames, Format, BUFS_SIZE, N_FIELDS};
+
+/// Default names of the built-in fields, in the order of [`Field`].
+const FIELD_NAMES: [&str; 7] = [
+    "timestamp",
+    "level",
+    "message",
+    "target",
+    "module",
+    "file",
+    "line",
+];
 
 /// Structured logging using JSON.
-#[allow(missing_debug_implementations)]
-pub enum Json {}
+///
+/// Created using [`Config::json`].
+///
+/// The names of the built-in fields can be changed using
+/// [`Config::with_field_name`] and fields can be omitted using
+/// [`Config::without_field`].
+///
+/// By default the key-values are logged as members of the top-level object,
+/// which means a key-value with the same key as a built-in field, e.g.
+/// `level`, results in a duplicate key. Use [`Config::with_nested_kvs`] to log
+/// the key-values in a nested object instead, or
+/// [`Config::with_key_collision`] to change how these collisions are handled.
+///
+/// [`Config::json`]: crate::Config::json
+/// [`Config::with_field_name`]: crate::Config::with_field_name
+/// [`Config::without_field`]: crate::Config::without_field
+/// [`Config::with_nested_kvs`]: crate::Config::with_nested_kvs
+/// [`Config::with_key_collision`]: crate::Config::with_key_collision
+#[derive(Debug)]
+pub struct Json {
+    /// Configured options, `None` if the defaults are used.
+    options: Option<Box<Options>>,
+}
+
+/// Options for the [`Json`] format, changing these from the defaults means
+/// the format can't use static slices for the field names.
+#[derive(Debug)]
+struct Options {
+    names: FieldNames,
+    /// Key of the object to log the key-values in.
+    kvs_key: Option<Box<str>>,
+    collision: KeyCollision,
+    /// Prefix used for [`KeyCollision::Prefix`].
+    collision_prefix: Box<str>,
+}
+
+impl Json {
+    pub(crate) const fn new() -> Json {
+        Json { options: None }
+    }
+
+    fn options(&mut self) -> &mut Options {
+        self.options.get_or_insert_with(|| {
+            Box::new(Options {
+                names: FieldNames::new(FIELD_NAMES),
+                kvs_key: None,
+                collision: KeyCollision::Duplicate,
+                collision_prefix: "fields.".into(),
+            })
+        })
+    }
+
+    /// Set the name of `field`, `None` omits the field.
+    pub(crate) fn set_field_name(&mut self, field: Field, name: Option<&str>) {
+        self.options().names.set(field, name);
+    }
+
+    /// Log the key-values in an object with `key`.
+    pub(crate) fn set_kvs_key(&mut self, key: &str) {
+        self.options().kvs_key = Some(key.into());
+    }
+
+    pub(crate) fn set_key_collision(&mut self, collision: KeyCollision) {
+        self.options().collision = collision;
+    }
+
+    pub(crate) fn set_collision_prefix(&mut self, prefix: &str) {
+        self.options().collision_prefix = prefix.into();
+    }
+}
+
+/// How to handle a key-value with the same key as a built-in field in the
+/// [`Json`] format.
+///
+/// Only key-values logged in the top-level object can collide with the
+/// built-in fields, key-values in a nested object (see
+/// [`Config::with_nested_kvs`]) never collide. Omitted fields (see
+/// [`Config::without_field`]) and the file and line when these are not logged
+/// also don't collide.
+///
+/// [`Config::with_nested_kvs`]: crate::Config::with_nested_kvs
+/// [`Config::without_field`]: crate::Config::without_field
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum KeyCollision {
+    /// Log both, resulting in a duplicate key in the object. This is the
+    /// default.
+    Duplicate,
+    /// Prefix the key of the key-value, with `fields.` by default, e.g.
+    /// `fields.level`. See [`Config::with_collision_prefix`].
+    ///
+    /// [`Config::with_collision_prefix`]: crate::Config::with_collision_prefix
+    Prefix,
+    /// The key-value overwrites the built-in field, i.e. the built-in field is
+    /// not logged.
+    Overwrite,
+    /// Drop the key-value.
+    Drop,
+}
 
 impl Format for Json {
     fn format<'b, Kvs: kv::Source>(
+        &'b self,
         bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
         buf: &'b mut Buffer,
         record: &'b Record,
         kvs: &Kvs,
         add_loc: bool,
     ) -> &'b [IoSlice<'b>] {
+        if let Some(options) = self.options.as_deref() {
+            return format_options(options, bufs, buf, record, kvs, add_loc);
+        }
+
         // Write all parts of the buffer that need formatting.
-        buf.buf[0] = b'{';
+        write_start(buf);
         #[cfg(feature = "timestamp")]
         write_timestamp(buf);
         write_msg(buf, record.args());
@@ -67,16 +180,210 @@ impl Format for Json {
     }
 }
 
+/// Same as [`Json::format`], but using the configured options. Because the
+/// field names are not static this writes the entire record into `buf`.
+fn format_options<'b, Kvs: kv::Source>(
+    options: &Options,
+    bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+    buf: &'b mut Buffer,
+    record: &Record,
+    kvs: &Kvs,
+    add_loc: bool,
+) -> &'b [IoSlice<'b>] {
+    // Built-in fields that are overwritten by a key-value.
+    let mut overwritten = [false; N_FIELDS];
+    if options.kvs_key.is_none() && options.collision == KeyCollision::Overwrite {
+        let mut visitor = CollisionVisitor {
+            names: &options.names,
+            add_loc,
+            found: &mut overwritten,
+        };
+        record
+            .key_values()
+            .visit(&mut visitor)
+            .unwrap_or_else(|_| unreachable!());
+        kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+    }
+    let name = |field: Field| {
+        if overwritten[field as usize] {
+            None
+        } else {
+            options.names.get(field)
+        }
+    };
+
+    let buf = &mut buf.buf;
+    buf.clear();
+    // All fields are written as `,"name":"value"`, the first comma is replaced
+    // with the start of the object below.
+    #[cfg(feature = "timestamp")]
+    if write_field_name(buf, name(Field::Timestamp)) {
+        let start = buf.len();
+        buf.resize(start + TIMESTAMP_LEN, 0);
+        format_timestamp(&mut buf[start..]);
+        buf.push(b'"');
+    }
+    write_field(buf, name(Field::Level), record.level().as_str());
+    if write_field_name(buf, name(Field::Message)) {
+        write_escaped(buf, record.args());
+        buf.push(b'"');
+    }
+    write_field(buf, name(Field::Target), record.target());
+    write_field(buf, name(Field::Module), record.module_path().unwrap_or(""));
+    if let Some(key) = options.kvs_key.as_deref() {
+        // Write the key-values as `,"key":{"k":"v"}`, or nothing if there are
+        // no key-values.
+        let start = buf.len();
+        buf.extend_from_slice(b",\"");
+        let _ = Buf(buf).write_str(key);
+        buf.extend_from_slice(b"\":");
+        let kvs_start = buf.len();
+        write_kvs(buf, record.key_values());
+        write_kvs(buf, kvs);
+        if buf.len() == kvs_start {
+            buf.truncate(start);
+        } else {
+            // Replace the comma of the first key-value.
+            buf[kvs_start] = b'{';
+            buf.push(b'}');
+        }
+    } else {
+        let mut visitor = CollidingVisitor {
+            visitor: KeyValueVisitor(buf),
+            names: &options.names,
+            add_loc,
+            collision: options.collision,
+            prefix: &options.collision_prefix,
+        };
+        record
+            .key_values()
+            .visit(&mut visitor)
+            .unwrap_or_else(|_| unreachable!());
+        kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+    }
+    if add_loc {
+        write_field(buf, name(Field::File), record.file().unwrap_or("??"));
+        if write_field_name(buf, name(Field::Line)) {
+            let mut itoa = itoa::Buffer::new();
+            buf.extend_from_slice(itoa.format(record.line().unwrap_or(0)).as_bytes());
+            buf.push(b'"');
+        }
+    }
+    match buf.first_mut() {
+        Some(b) => *b = b'{',
+        None => buf.push(b'{'),
+    }
+    buf.extend_from_slice(b"}\n");
+
+    bufs[0] = IoSlice::new(buf);
+    &bufs[..1]
+}
+
+/// Write `,"name":"value"`, if the field is not omitted (`name` is `None`).
+#[inline]
+fn write_field(buf: &mut Vec<u8>, name: Option<&str>, value: &str) {
+    if write_field_name(buf, name) {
+        let _ = Buf(buf).write_str(value);
+        buf.push(b'"');
+    }
+}
+
+/// Write `,"name":"`, returns `false` if the field is omitted (`name` is
+/// `None`).
+#[inline]
+fn write_field_name(buf: &mut Vec<u8>, name: Option<&str>) -> bool {
+    match name {
+        Some(name) => {
+            buf.extend_from_slice(b",\"");
+            let _ = Buf(buf).write_str(name);
+            buf.extend_from_slice(b"\":\"");
+            true
+        }
+        None => false,
+    }
+}
+
+/// Returns the built-in field that is logged with the name `key`, if any.
+fn builtin_field(names: &FieldNames, key: &str, add_loc: bool) -> Option<Field> {
+    const FIELDS: [Field; N_FIELDS] = [
+        Field::Timestamp,
+        Field::Level,
+        Field::Message,
+        Field::Target,
+        Field::Module,
+        Field::File,
+        Field::Line,
+    ];
+    FIELDS.into_iter().find(|&field| {
+        let logged = match field {
+            Field::Timestamp => cfg!(feature = "timestamp"),
+            Field::File | Field::Line => add_loc,
+            _ => true,
+        };
+        logged && names.get(field) == Some(key)
+    })
+}
+
+/// Marks the built-in fields that collide with a key-value in `found`.
+struct CollisionVisitor<'a> {
+    names: &'a FieldNames,
+    add_loc: bool,
+    found: &'a mut [bool; N_FIELDS],
+}
+
+impl<'a, 'kvs> VisitSource<'kvs> for CollisionVisitor<'a> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, _: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        if let Some(field) = builtin_field(self.names, key.as_str(), self.add_loc) {
+            self.found[field as usize] = true;
+        }
+        Ok(())
+    }
+}
+
+/// Same as [`KeyValueVisitor`], but handles key-values that collide with a
+/// built-in field according to `collision`.
+struct CollidingVisitor<'a, 'b> {
+    visitor: KeyValueVisitor<'b>,
+    names: &'a FieldNames,
+    add_loc: bool,
+    collision: KeyCollision,
+    /// Prefix used for [`KeyCollision::Prefix`].
+    prefix: &'a str,
+}
+
+impl<'a, 'b, 'kvs> VisitSource<'kvs> for CollidingVisitor<'a, 'b> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        if builtin_field(self.names, key.as_str(), self.add_loc).is_none() {
+            return self.visitor.visit_pair(key, value);
+        }
+        match self.collision {
+            KeyCollision::Prefix => self.visitor.write_pair(self.prefix, key.as_str(), value),
+            KeyCollision::Drop => Ok(()),
+            KeyCollision::Duplicate | KeyCollision::Overwrite => {
+                self.visitor.visit_pair(key, value)
+            }
+        }
+    }
+}
+
 /// Index of the end of `{"timestamp":"0000-00-00T00:00:00.000000Z",`.
 #[cfg(feature = "timestamp")]
 const TS_END_INDEX: usize = 43;
 #[cfg(not(feature = "timestamp"))]
 const TS_END_INDEX: usize = 1;
 
+/// Write the start of the JSON object, must be called before
+/// [`write_timestamp`] and [`write_msg`].
+#[inline]
+pub(crate) fn write_start(buf: &mut Buffer) {
+    buf.buf.resize(TS_END_INDEX, 0);
+    buf.buf[0] = b'{';
+}
+
 #[inline]
 #[cfg(feature = "timestamp")]
 pub(crate) fn write_timestamp(buf: &mut Buffer) {
-    let _ = buf.buf[TS_END_INDEX];
+    let _ = buf.buf[TS_END_INDEX - 1];
     buf.buf[1] = b'"';
     buf.buf[2] = b't';
     buf.buf[3] = b'i';
@@ -103,16 +410,18 @@ pub(crate) fn timestamp(buf: &Buffer) -> &[u8] {
 #[inline]
 pub(crate) fn write_msg(buf: &mut Buffer, args: &fmt::Arguments) {
     buf.buf.truncate(TS_END_INDEX);
+    write_escaped(&mut buf.buf, args);
+    buf.indices[0] = buf.buf.len();
+}
+
+/// Write `args` into `buf` escaped as JSON string.
+#[inline]
+pub(super) fn write_escaped(buf: &mut Vec<u8>, args: &fmt::Arguments) {
     if let Some(msg) = args.as_str() {
-        Buf(&mut buf.buf)
-            .write_str(msg)
-            .unwrap_or_else(|_| unreachable!());
+        Buf(buf).write_str(msg).unwrap_or_else(|_| unreachable!());
     } else {
-        Buf(&mut buf.buf)
-            .write_fmt(*args)
-            .unwrap_or_else(|_| unreachable!());
+        Buf(buf).write_fmt(*args).unwrap_or_else(|_| unreachable!());
     }
-    buf.indices[0] = buf.buf.len();
 }
 
 #[inline]
@@ -129,12 +438,18 @@ pub(crate) fn write_key_values<Kvs: kv::Source>(
     buf.buf.extend_from_slice(b"\"");
     // TODO: see if we can add to the slice of `IoSlice` using the keys
     // and string values.
-    let mut visitor = KeyValueVisitor(&mut buf.buf);
-    kvs1.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
-    kvs2.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+    write_kvs(&mut buf.buf, kvs1);
+    write_kvs(&mut buf.buf, &kvs2);
     buf.indices[1] = buf.buf.len();
 }
 
+/// Write the key-values in `kvs` into `buf`, see [`KeyValueVisitor`].
+#[inline]
+pub(super) fn write_kvs(buf: &mut Vec<u8>, kvs: &dyn kv::Source) {
+    let mut visitor = KeyValueVisitor(buf);
+    kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+}
+
 #[inline]
 pub(crate) fn key_values(buf: &Buffer) -> &[u8] {
     &buf.buf[buf.indices[0]..buf.indices[1]]
@@ -157,11 +472,18 @@ pub(crate) fn line(buf: &Buffer) -> &[u8] {
 /// `"user_name":"Thomas","user_id":123,"is_admin":true`.
 pub(super) struct KeyValueVisitor<'b>(pub(super) &'b mut Vec<u8>);
 
-impl<'b, 'kvs> VisitSource<'kvs> for KeyValueVisitor<'b> {
-    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+impl<'b> KeyValueVisitor<'b> {
+    /// Write `,"{prefix}{key}":value`.
+    pub(super) fn write_pair(
+        &mut self,
+        prefix: &str,
+        key: &str,
+        value: kv::Value<'_>,
+    ) -> Result<(), kv::Error> {
         self.0.push(b',');
         self.0.push(b'"');
-        let _ = fmt::Write::write_str(&mut Buf(self.0), key.as_str());
+        let _ = fmt::Write::write_str(&mut Buf(self.0), prefix);
+        let _ = fmt::Write::write_str(&mut Buf(self.0), key);
         self.0.push(b'"');
         self.0.push(b':');
         #[cfg(feature = "serde1")]
@@ -172,6 +494,12 @@ impl<'b, 'kvs> VisitSource<'kvs> for KeyValueVisitor<'b> {
     }
 }
 
+impl<'b, 'kvs> VisitSource<'kvs> for KeyValueVisitor<'b> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        self.write_pair("", key.as_str(), value)
+    }
+}
+
 impl<'b, 'v> VisitValue<'v> for KeyValueVisitor<'b> {
     fn visit_any(&mut self, value: kv::Value) -> Result<(), kv::Error> {
         self.0.push(b'\"');
@@ -470,7 +798,9 @@ impl<'b> serde_core::ser::SerializeSeq for &mut KeyValueVisitor<'b> {
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        let _ = self.0.pop_if(|b| *b == b',');
+        if self.0.last() == Some(&b',') {
+            let _ = self.0.pop();
+        }
         self.0.push(b']');
         Ok(())
     }
@@ -551,7 +881,9 @@ impl<'b> serde_core::ser::SerializeMap for &mut KeyValueVisitor<'b> {
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        let _ = self.0.pop_if(|b| *b == b',');
+        if self.0.last() == Some(&b',') {
+            let _ = self.0.pop();
+        }
         self.0.push(b'}');
         Ok(())
     }
@@ -7,22 +7,66 @@ use log::kv::{VisitSource, VisitValue};
 use log::{kv, Record};
 
 #[cfg(feature = "timestamp")]
-use crate::format::format_timestamp;
-use crate::format::{Buffer, Format, BUFS_SIZE};
+use crate::format::{format_timestamp, TIMESTAMP_LEN};
+use crate::format::{Buffer, Field, FieldNames, Format, BUFS_SIZE};
+
+/// Default names of the built-in fields, in the order of [`Field`].
+const FIELD_NAMES: [&str; 7] = ["ts", "lvl", "msg", "target", "module", "file", "line"];
 
 /// Logfmt following <https://www.brandur.org/logfmt>.
-#[allow(missing_debug_implementations)]
-pub enum LogFmt {}
+///
+/// Created using [`Config::logfmt`].
+///
+/// The names of the built-in fields can be changed using
+/// [`Config::with_field_name`] and fields can be omitted using
+/// [`Config::without_field`]. Note that the line is logged as part of the
+/// file, e.g. `file="src/lib.rs:12"`, the name of the line field is only used
+/// if the file field is omitted.
+///
+/// With the *serde1* feature enabled structured key-values are flattened into
+/// multiple key-value pairs using dotted keys, e.g. `user.id=1
+/// user.name="Thomas"`, elements of sequences use their index as key, e.g.
+/// `ids.0=1 ids.1=2`. Empty maps and sequences are logged as `{}` and `[]`
+/// respectively. Spaces, `=`, `"` and control characters in the keys of maps
+/// are replaced with `_`.
+///
+/// [`Config::logfmt`]: crate::Config::logfmt
+/// [`Config::with_field_name`]: crate::Config::with_field_name
+/// [`Config::without_field`]: crate::Config::without_field
+#[derive(Debug)]
+pub struct LogFmt {
+    /// Configured field names, `None` if the default names are used.
+    fields: Option<Box<FieldNames>>,
+}
+
+impl LogFmt {
+    pub(crate) const fn new() -> LogFmt {
+        LogFmt { fields: None }
+    }
+
+    /// Set the name of `field`, `None` omits the field.
+    pub(crate) fn set_field_name(&mut self, field: Field, name: Option<&str>) {
+        self.fields
+            .get_or_insert_with(|| Box::new(FieldNames::new(FIELD_NAMES)))
+            .set(field, name);
+    }
+}
 
 impl Format for LogFmt {
     fn format<'b, Kvs: kv::Source>(
+        &'b self,
         bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
         buf: &'b mut Buffer,
         record: &'b Record,
         kvs: &Kvs,
         add_loc: bool,
     ) -> &'b [IoSlice<'b>] {
+        if let Some(fields) = self.fields.as_deref() {
+            return format_fields(fields, bufs, buf, record, kvs, add_loc);
+        }
+
         // Write all parts of the buffer that need formatting.
+        buf.buf.resize(TS_END_INDEX, 0);
         #[cfg(feature = "timestamp")]
         write_timestamp(buf);
         write_msg(buf, record.args());
@@ -64,6 +108,86 @@ impl Format for LogFmt {
     }
 }
 
+/// Same as [`LogFmt::format`], but using the configured field names. Because
+/// the names are not static this writes the entire record into `buf`.
+fn format_fields<'b, Kvs: kv::Source>(
+    fields: &FieldNames,
+    bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+    buf: &'b mut Buffer,
+    record: &Record,
+    kvs: &Kvs,
+    add_loc: bool,
+) -> &'b [IoSlice<'b>] {
+    let buf = &mut buf.buf;
+    buf.clear();
+    // All fields are written as ` name="value"`, the first space is skipped
+    // below.
+    #[cfg(feature = "timestamp")]
+    if write_field_name(buf, fields, Field::Timestamp) {
+        let start = buf.len();
+        buf.resize(start + TIMESTAMP_LEN, 0);
+        format_timestamp(&mut buf[start..]);
+        buf.push(b'"');
+    }
+    write_field(
+        buf,
+        fields,
+        Field::Level,
+        format_args!("{}", record.level()),
+    );
+    write_field(buf, fields, Field::Message, *record.args());
+    write_kvs(buf, record.key_values());
+    write_kvs(buf, kvs);
+    write_field(
+        buf,
+        fields,
+        Field::Target,
+        format_args!("{}", record.target()),
+    );
+    let module = record.module_path().unwrap_or("");
+    write_field(buf, fields, Field::Module, format_args!("{module}"));
+    if add_loc {
+        let file = record.file().unwrap_or("??");
+        let line = record.line().unwrap_or(0);
+        match (fields.get(Field::File), fields.get(Field::Line)) {
+            (Some(_), Some(_)) => {
+                write_field(buf, fields, Field::File, format_args!("{file}:{line}"));
+            }
+            (Some(_), None) => write_field(buf, fields, Field::File, format_args!("{file}")),
+            (None, Some(_)) => write_field(buf, fields, Field::Line, format_args!("{line}")),
+            (None, None) => {}
+        }
+    }
+    buf.push(b'\n');
+
+    let start = usize::from(buf.first() == Some(&b' '));
+    bufs[0] = IoSlice::new(&buf[start..]);
+    &bufs[..1]
+}
+
+/// Write ` name="value"` for `field`, if it's not omitted.
+#[inline]
+fn write_field(buf: &mut Vec<u8>, fields: &FieldNames, field: Field, value: fmt::Arguments) {
+    if write_field_name(buf, fields, field) {
+        write_escaped(buf, &value);
+        buf.push(b'"');
+    }
+}
+
+/// Write ` name="` for `field`, returns `false` if the field is omitted.
+#[inline]
+fn write_field_name(buf: &mut Vec<u8>, fields: &FieldNames, field: Field) -> bool {
+    match fields.get(field) {
+        Some(name) => {
+            buf.push(b' ');
+            Buf(buf).extend_from_slice(name.as_bytes());
+            buf.extend_from_slice(b"=\"");
+            true
+        }
+        None => false,
+    }
+}
+
 /// Index of the end of `ts="..."`.
 #[cfg(feature = "timestamp")]
 const TS_END_INDEX: usize = 33;
@@ -73,7 +197,7 @@ const TS_END_INDEX: usize = 0;
 #[inline]
 #[cfg(feature = "timestamp")]
 fn write_timestamp(buf: &mut Buffer) {
-    let _ = buf.buf[TS_END_INDEX];
+    let _ = buf.buf[TS_END_INDEX - 1];
     buf.buf[0] = b't';
     buf.buf[1] = b's';
     buf.buf[2] = b'=';
@@ -91,16 +215,18 @@ fn timestamp(buf: &Buffer) -> &[u8] {
 #[inline]
 fn write_msg(buf: &mut Buffer, args: &fmt::Arguments) {
     buf.buf.truncate(TS_END_INDEX);
+    write_escaped(&mut buf.buf, args);
+    buf.indices[0] = buf.buf.len();
+}
+
+/// Write `args` into `buf` escaping quotes and new lines.
+#[inline]
+pub(super) fn write_escaped(buf: &mut Vec<u8>, args: &fmt::Arguments) {
     if let Some(msg) = args.as_str() {
-        Buf(&mut buf.buf)
-            .write_str(msg)
-            .unwrap_or_else(|_| unreachable!());
+        Buf(buf).write_str(msg).unwrap_or_else(|_| unreachable!());
     } else {
-        Buf(&mut buf.buf)
-            .write_fmt(*args)
-            .unwrap_or_else(|_| unreachable!());
+        Buf(buf).write_fmt(*args).unwrap_or_else(|_| unreachable!());
     }
-    buf.indices[0] = buf.buf.len();
 }
 
 #[inline]
@@ -113,12 +239,18 @@ fn write_key_values<Kvs: kv::Source>(buf: &mut Buffer, kvs1: &dyn kv::Source, kv
     buf.buf.extend_from_slice(b"\"");
     // TODO: see if we can add to the slice of `IoSlice` using the keys
     // and string values.
-    let mut visitor = KeyValueVisitor(&mut buf.buf);
-    kvs1.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
-    kvs2.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+    write_kvs(&mut buf.buf, kvs1);
+    write_kvs(&mut buf.buf, &kvs2);
     buf.indices[1] = buf.buf.len();
 }
 
+/// Write the key-values in `kvs` into `buf`, see [`KeyValueVisitor`].
+#[inline]
+pub(super) fn write_kvs(buf: &mut Vec<u8>, kvs: &dyn kv::Source) {
+    let mut visitor = KeyValueVisitor(buf);
+    kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+}
+
 #[inline]
 fn key_values(buf: &Buffer) -> &[u8] {
     &buf.buf[buf.indices[0]..buf.indices[1]]
@@ -143,12 +275,25 @@ fn line(buf: &Buffer) -> &[u8] {
 struct KeyValueVisitor<'b>(&'b mut Vec<u8>);
 
 impl<'b, 'kvs> VisitSource<'kvs> for KeyValueVisitor<'b> {
+    #[cfg(not(feature = "serde1"))]
     fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
         self.0.push(b' ');
         Buf(self.0).extend_from_slice(key.as_str().as_bytes());
         self.0.push(b'=');
         value.visit(self)
     }
+
+    #[cfg(feature = "serde1")]
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let mut serializer = Flatten {
+            buf: self.0,
+            key: key.as_str(),
+            suffix: String::new(),
+            in_key: false,
+            compounds: Vec::new(),
+        };
+        serde_core::Serialize::serialize(&value, &mut serializer).map_err(kv::Error::boxed)
+    }
 }
 
 impl<'b, 'v> VisitValue<'v> for KeyValueVisitor<'b> {
@@ -212,6 +357,449 @@ impl<'b, 'v> VisitValue<'v> for KeyValueVisitor<'b> {
     }
 }
 
+/// Serializer that flattens structured values into multiple key-value pairs
+/// using dotted keys. Fields of maps and structs use their name, e.g.
+/// `user.id=1 user.name="Thomas"`, elements of sequences and tuples use their
+/// index, e.g. `ids.0=1 ids.1=2`. Empty maps and sequences are logged as `{}`
+/// and `[]` respectively, e.g. `tags=[]`.
+#[cfg(feature = "serde1")]
+struct Flatten<'k, 'b> {
+    buf: &'b mut Vec<u8>,
+    /// Key of the key-value pair.
+    key: &'k str,
+    /// Suffix of the key for nested values, e.g. `.name`.
+    suffix: String,
+    /// If `true` the value is appended to `suffix`, used for map keys.
+    in_key: bool,
+    /// Length of `suffix`, the index of the next element and the length of
+    /// `buf` for each map or sequence being serialized.
+    compounds: Vec<(usize, usize, usize)>,
+}
+
+#[cfg(feature = "serde1")]
+impl<'k, 'b> Flatten<'k, 'b> {
+    /// Write a single value, or append it to the key if we're serializing a
+    /// map key.
+    fn write_value<F>(&mut self, value: &dyn fmt::Display, write: F) -> Result<(), fmt::Error>
+    where
+        F: FnOnce(&mut KeyValueVisitor<'_>) -> Result<(), kv::Error>,
+    {
+        if self.in_key {
+            return write!(KeyBuf(&mut self.suffix), "{value}");
+        }
+        self.buf.push(b' ');
+        Buf(self.buf).extend_from_slice(self.key.as_bytes());
+        Buf(self.buf).extend_from_slice(self.suffix.as_bytes());
+        self.buf.push(b'=');
+        write(&mut KeyValueVisitor(self.buf)).map_err(|_| fmt::Error)
+    }
+
+    /// Start a map or sequence.
+    fn start_compound(&mut self) {
+        self.compounds.push((self.suffix.len(), 0, self.buf.len()));
+    }
+
+    /// Set the key suffix to `.name` for the next field.
+    fn field(&mut self, name: &str) {
+        if let Some((len, _, _)) = self.compounds.last() {
+            self.suffix.truncate(*len);
+        }
+        self.suffix.push('.');
+        self.suffix.push_str(name);
+    }
+
+    /// Set the key suffix to `.index` for the next element.
+    fn element(&mut self) {
+        if let Some((len, index, _)) = self.compounds.last_mut() {
+            self.suffix.truncate(*len);
+            let _ = write!(self.suffix, ".{index}");
+            *index += 1;
+        }
+    }
+
+    /// End a map or sequence, started with [`Flatten::start_compound`]. If
+    /// nothing was written `empty` is written as value, e.g. `[]`.
+    fn end_compound(&mut self, empty: &'static str) -> Result<(), fmt::Error> {
+        if let Some((len, _, buf_len)) = self.compounds.pop() {
+            self.suffix.truncate(len);
+            if self.buf.len() == buf_len {
+                return self.write_value(&empty, |visitor| {
+                    visitor.0.extend_from_slice(empty.as_bytes());
+                    Ok(())
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes map keys into the key suffix of [`Flatten`], replacing spaces, `=`,
+/// `"` and control characters, which are not allowed in logfmt keys, with
+/// `_`.
+#[cfg(feature = "serde1")]
+struct KeyBuf<'s>(&'s mut String);
+
+#[cfg(feature = "serde1")]
+impl<'s> fmt::Write for KeyBuf<'s> {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        for c in string.chars() {
+            let c = match c {
+                ' ' | '=' | '"' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            };
+            self.0.push(c);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl<'k, 'b> serde_core::Serializer for &mut Flatten<'k, 'b> {
+    type Ok = ();
+    type Error = fmt::Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.write_value(&v, |visitor| visitor.visit_bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.write_value(&v, |visitor| visitor.visit_i64(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.write_value(&v, |visitor| visitor.visit_i128(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.write_value(&v, |visitor| visitor.visit_u64(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.write_value(&v, |visitor| visitor.visit_u128(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.write_value(&v, |visitor| visitor.visit_f64(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        // A char encoded as UTF-8 takes 4 bytes at most.
+        let mut buf = [0; 4];
+        self.serialize_str(v.encode_utf8(&mut buf))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.write_value(&v, |visitor| visitor.visit_str(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        use serde_core::ser::SerializeSeq;
+        let mut serializer = self.serialize_seq(Some(v.len()))?;
+        for b in v {
+            serializer.serialize_element(b)?;
+        }
+        serializer.end()
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.write_value(&"null", |visitor| visitor.visit_null())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + serde_core::Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.serialize_none()
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + serde_core::Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + serde_core::Serialize,
+    {
+        // Serialise as a map using the variant name as key and the value as value.
+        let mut serializer = self.serialize_struct(name, 1)?;
+        serde_core::ser::SerializeStruct::serialize_field(&mut serializer, variant, value)?;
+        serde_core::ser::SerializeStruct::end(serializer)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        self.start_compound();
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        // Serialise as a map using the variant name as key and the sequence as
+        // value, both ended in `SerializeTupleVariant::end`.
+        self.start_compound();
+        self.field(variant);
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        self.start_compound();
+        Ok(self)
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        // Serialise as a map using the variant name as key and the struct as
+        // value, both ended in `SerializeStructVariant::end`.
+        self.start_compound();
+        self.field(variant);
+        self.serialize_map(Some(len))
+    }
+
+    fn collect_str<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + fmt::Display,
+    {
+        self.write_value(&value, |visitor| {
+            visitor.visit_any(kv::Value::from_display(&value))
+        })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl<'k, 'b> serde_core::ser::SerializeSeq for &mut Flatten<'k, 'b> {
+    type Ok = ();
+    type Error = fmt::Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde_core::Serialize,
+    {
+        self.element();
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.end_compound("[]")
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl<'k, 'b> serde_core::ser::SerializeTuple for &mut Flatten<'k, 'b> {
+    type Ok = ();
+    type Error = fmt::Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde_core::Serialize,
+    {
+        serde_core::ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        serde_core::ser::SerializeSeq::end(self)
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl<'k, 'b> serde_core::ser::SerializeTupleStruct for &mut Flatten<'k, 'b> {
+    type Ok = ();
+    type Error = fmt::Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde_core::Serialize,
+    {
+        serde_core::ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        serde_core::ser::SerializeSeq::end(self)
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl<'k, 'b> serde_core::ser::SerializeTupleVariant for &mut Flatten<'k, 'b> {
+    type Ok = ();
+    type Error = fmt::Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde_core::Serialize,
+    {
+        serde_core::ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        // End both the sequence and the variant.
+        self.end_compound("[]")?;
+        self.end_compound("{}")
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl<'k, 'b> serde_core::ser::SerializeMap for &mut Flatten<'k, 'b> {
+    type Ok = ();
+    type Error = fmt::Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde_core::Serialize,
+    {
+        self.field("");
+        self.in_key = true;
+        let res = key.serialize(&mut **self);
+        self.in_key = false;
+        res
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde_core::Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.end_compound("{}")
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl<'k, 'b> serde_core::ser::SerializeStruct for &mut Flatten<'k, 'b> {
+    type Ok = ();
+    type Error = fmt::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde_core::Serialize,
+    {
+        self.field(key);
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.end_compound("{}")
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl<'k, 'b> serde_core::ser::SerializeStructVariant for &mut Flatten<'k, 'b> {
+    type Ok = ();
+    type Error = fmt::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + serde_core::Serialize,
+    {
+        serde_core::ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        // End both the struct and the variant.
+        self.end_compound("{}")?;
+        self.end_compound("{}")
+    }
+}
+
 /// [`fmt::Write`] implementation that writes escaped quotes.
 struct Buf<'b>(&'b mut Vec<u8>);
 
@@ -1,17 +1,100 @@
+//! Formatting of log records.
+//!
+//! The formats provided by this crate are [`LogFmt`], [`Json`], [`Gcloud`],
+//! [`Ecs`], [`Datadog`], [`Gelf`], [`OpenTelemetry`], [`Emf`], [`MessagePack`],
+//! [`Journald`], [`Syslog`], [`SyslogRfc3164`] and [`Pretty`]. [`Dynamic`] can
+//! be used to select one of the formats at runtime and [`Auto`] to use
+//! [`Pretty`] only on terminals.
+//! Other formats can be added by implementing the [`Format`] trait and using
+//! [`Config::custom`].
+//!
+//! [`Config::custom`]: crate::Config::custom
+
+use std::fmt;
 use std::io::IoSlice;
 
 use log::{kv, Record};
 
-pub(crate) mod logfmt;
-pub(crate) use logfmt::LogFmt;
+mod logfmt;
+pub use logfmt::LogFmt;
+
+mod json;
+pub use json::{Json, KeyCollision};
+
+mod gcloud;
+pub use gcloud::Gcloud;
+
+mod ecs;
+pub use ecs::Ecs;
+
+mod datadog;
+pub use datadog::Datadog;
+
+mod gelf;
+pub use gelf::Gelf;
+
+mod opentelemetry;
+pub use opentelemetry::OpenTelemetry;
+
+mod emf;
+pub use emf::Emf;
 
-pub(crate) mod json;
-pub(crate) use json::Json;
+mod msgpack;
+pub use msgpack::MessagePack;
 
-pub(crate) mod gcloud;
-pub(crate) use gcloud::Gcloud;
+mod journald;
+pub use journald::Journald;
+
+mod syslog;
+pub use syslog::{Syslog, SyslogRfc3164};
+
+mod pretty;
+pub use pretty::{Auto, Pretty};
+
+mod dynamic;
+pub use dynamic::Dynamic;
 
 /// Trait that defines how to format a [`log::Record`].
+///
+/// # Examples
+///
+/// A format that only logs the level and message, e.g. `INFO: some message`.
+///
+/// ```
+/// use std::io::IoSlice;
+///
+/// use log::{kv, Record};
+/// use std_logger::format::{Buffer, Format, BUFS_SIZE};
+///
+/// #[derive(Debug)]
+/// struct Simple;
+///
+/// impl Format for Simple {
+///     fn format<'b, Kvs: kv::Source>(
+///         &'b self,
+///         bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+///         buf: &'b mut Buffer,
+///         record: &'b Record,
+///         _kvs: &Kvs,
+///         _add_loc: bool,
+///     ) -> &'b [IoSlice<'b>] {
+///         // First write everything that needs formatting into `buf`.
+///         buf.clear();
+///         buf.write_logfmt_escaped(*record.args());
+///
+///         // Then point the `bufs` to the static parts and `buf`.
+///         bufs[0] = IoSlice::new(record.level().as_str().as_bytes());
+///         bufs[1] = IoSlice::new(b": ");
+///         bufs[2] = IoSlice::new(buf.as_bytes());
+///         bufs[3] = IoSlice::new(b"\n");
+///         &bufs[..4]
+///     }
+/// }
+///
+/// # fn main() {
+/// std_logger::Config::custom(Simple).init();
+/// # }
+/// ```
 pub trait Format {
     /// Formats a log `record`.
     ///
@@ -19,19 +102,85 @@ pub trait Format {
     /// it resets itself. The returned slices is based on `bufs`, which is used
     /// to order the writable buffers.
     ///
+    /// The key-values in `kvs` are the ones supplied by
+    /// [`Config::with_kvs`], which are logged with every record, in addition
+    /// to the key-values in `record`.
+    ///
     /// If `add_loc` is `true` the file and line are added.
+    ///
+    /// [`Config::with_kvs`]: crate::Config::with_kvs
     fn format<'b, Kvs: kv::Source>(
+        &'b self,
         bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
         buf: &'b mut Buffer,
         record: &'b Record,
         kvs: &Kvs,
         add_loc: bool,
     ) -> &'b [IoSlice<'b>];
+
+    /// Called when the logger is created, with `is_terminal` set if the
+    /// output for regular messages is a terminal, see [`Sink::is_terminal`].
+    ///
+    /// This is used by [`Pretty`] and [`Auto`] to only use colors, or the
+    /// pretty format, on terminals. Defaults to doing nothing.
+    ///
+    /// [`Sink::is_terminal`]: crate::sink::Sink::is_terminal
+    fn set_terminal(&mut self, _is_terminal: bool) {}
 }
 
 /// Number of buffers the format functions require.
 pub const BUFS_SIZE: usize = 16;
 
+/// Built-in field of a record.
+///
+/// Used to rename or omit the field in the [`Json`] and [`LogFmt`] formats,
+/// see [`Config::with_field_name`] and [`Config::without_field`].
+///
+/// [`Config::with_field_name`]: crate::Config::with_field_name
+/// [`Config::without_field`]: crate::Config::without_field
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Field {
+    /// Time the record was logged, only logged if the *timestamp* feature is
+    /// enabled.
+    Timestamp,
+    /// Log level.
+    Level,
+    /// Message.
+    Message,
+    /// Target of the record.
+    Target,
+    /// Module path.
+    Module,
+    /// Source file, only logged if enabled.
+    File,
+    /// Line in the source file, only logged if enabled.
+    Line,
+}
+
+/// Number of [`Field`]s.
+const N_FIELDS: usize = 7;
+
+/// Names of the built-in [`Field`]s, `None` if the field is omitted.
+#[derive(Debug)]
+pub(crate) struct FieldNames([Option<Box<str>>; N_FIELDS]);
+
+impl FieldNames {
+    /// Create names using the `defaults`, in the order of [`Field`].
+    fn new(defaults: [&str; N_FIELDS]) -> FieldNames {
+        FieldNames(defaults.map(|name| Some(name.into())))
+    }
+
+    fn set(&mut self, field: Field, name: Option<&str>) {
+        self.0[field as usize] = name.map(Into::into);
+    }
+
+    /// Returns the name of `field`, or `None` if it's omitted.
+    fn get(&self, field: Field) -> Option<&str> {
+        self.0[field as usize].as_deref()
+    }
+}
+
 /// Number of indices used in `Buffer`:
 /// 0) Message.
 /// 1) Key value pairs.
@@ -47,26 +196,95 @@ pub struct Buffer {
 
 impl Buffer {
     /// Create a new format `Buffer`.
-    pub(crate) fn new() -> Buffer {
+    pub fn new() -> Buffer {
         Buffer {
-            buf: vec![0; 2048],
+            buf: Vec::with_capacity(2048),
             indices: [0; N_INDICES],
         }
     }
+
+    /// Remove all bytes from the buffer.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+
+    /// Returns the number of bytes in the buffer.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns `true` if the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns the bytes written to the buffer.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Write `bytes` to the buffer as is, i.e. without escaping.
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Write the current time to the buffer in the following format:
+    /// `YYYY-MM-DDThh:mm:ss.SSSSSSZ`. For example:
+    /// `2020-12-31T11:00:01.743357Z`.
+    #[cfg(feature = "timestamp")]
+    pub fn write_timestamp(&mut self) {
+        let start = self.buf.len();
+        self.buf.resize(start + TIMESTAMP_LEN, 0);
+        format_timestamp(&mut self.buf[start..]);
+    }
+
+    /// Write `args` to the buffer, escaped as the content of a JSON string.
+    pub fn write_json_escaped(&mut self, args: fmt::Arguments<'_>) {
+        json::write_escaped(&mut self.buf, &args);
+    }
+
+    /// Write the key-values in `kvs` to the buffer as members of a JSON object,
+    /// in the following format: `,"key":"value"`. For example:
+    /// `,"user_name":"Thomas","user_id":123,"is_admin":true`.
+    pub fn write_json_key_values(&mut self, kvs: &dyn kv::Source) {
+        json::write_kvs(&mut self.buf, kvs);
+    }
+
+    /// Write `args` to the buffer, escaped as the content of a logfmt value
+    /// between quotes.
+    pub fn write_logfmt_escaped(&mut self, args: fmt::Arguments<'_>) {
+        logfmt::write_escaped(&mut self.buf, &args);
+    }
+
+    /// Write the key-values in `kvs` to the buffer in logfmt, in the following
+    /// format: ` key="value"`. For example:
+    /// ` user_name="Thomas" user_id=123 is_admin=true`.
+    pub fn write_logfmt_key_values(&mut self, kvs: &dyn kv::Source) {
+        logfmt::write_kvs(&mut self.buf, kvs);
+    }
 }
 
+impl Default for Buffer {
+    fn default() -> Buffer {
+        Buffer::new()
+    }
+}
+
+/// Length of the timestamp written by [`format_timestamp`].
+#[cfg(feature = "timestamp")]
+const TIMESTAMP_LEN: usize = 27;
+
 /// Format the timestamp in the following format:
 /// `YYYY-MM-DDThh:mm:ss.SSSSSSZ`. For example:
 /// `2020-12-31T11:00:01.743357Z`.
 ///
 /// # Notes
 ///
-/// The `buf` must come from [`Buffer::ts`] as it only overwrites the date, not
-/// the format.
+/// The `buf` must be at least [`TIMESTAMP_LEN`] bytes long.
 #[inline]
 #[cfg(feature = "timestamp")]
 fn format_timestamp(buf: &mut [u8]) {
-    let _ = buf[26];
+    let _ = buf[TIMESTAMP_LEN - 1];
     let timestamp = crate::timestamp::Timestamp::now();
     let mut itoa = itoa::Buffer::new();
     buf[0..4].copy_from_slice(itoa.format(timestamp.year).as_bytes());
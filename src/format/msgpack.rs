@@ -0,0 +1,316 @@
+//! Binary logging using MessagePack, following
+//! <https://github.com/msgpack/msgpack/blob/master/spec.md>.
+
+use std::fmt::{self, Write};
+use std::io::IoSlice;
+
+use log::kv::{VisitSource, VisitValue};
+use log::{kv, Record};
+
+use crate::format::{Buffer, Format, BUFS_SIZE};
+
+/// Binary logging using MessagePack, following
+/// <https://github.com/msgpack/msgpack/blob/master/spec.md>.
+///
+/// Created using [`Config::msgpack`].
+///
+/// Each record is written as a 32 bit big-endian length, followed by a
+/// MessagePack map of that many bytes. The map holds the same fields as the
+/// [`Json`] format:
+///  * `timestamp`: using the MessagePack timestamp extension type (only if the
+///    *timestamp* feature is enabled),
+///  * `level`, `message`, `target` and `module`: as strings,
+///  * all key-value pairs, integers, floats, booleans and strings keep their
+///    type, other values are formatted as string,
+///  * `file` and `line`: if enabled, as string and integer respectively.
+///
+/// Records can be read using the `std-logger-parser` crate, which skips
+/// records larger than 16 MiB. Records that would be larger are written
+/// without the key-value pairs and with the message truncated, and have an
+/// additional `truncated` field set to `true`.
+///
+/// [`Config::msgpack`]: crate::Config::msgpack
+/// [`Json`]: crate::format::Json
+#[derive(Debug)]
+pub struct MessagePack(pub(crate) ());
+
+impl Format for MessagePack {
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        add_loc: bool,
+    ) -> &'b [IoSlice<'b>] {
+        let buf = &mut buf.buf;
+        write_record(buf, record, Some(kvs), add_loc);
+        if buf.len() - 4 > MAX_RECORD_SIZE {
+            write_record(buf, record, None::<&Kvs>, add_loc);
+        }
+
+        bufs[0] = IoSlice::new(buf);
+        &bufs[..1]
+    }
+}
+
+/// Maximum size of a record, excluding the length, that the
+/// `std-logger-parser` crate reads.
+const MAX_RECORD_SIZE: usize = 16 * 1024 * 1024;
+
+/// Maximum size of the message of a record that is too large, leaving room
+/// for the other fields.
+const MAX_TRUNCATED_MESSAGE_SIZE: usize = MAX_RECORD_SIZE - 64 * 1024;
+
+/// Write `record` into `buf`, including the length prefix.
+///
+/// If `kvs` is `None` the record was too large, in which case the key-values
+/// are not written and the message is truncated.
+fn write_record<Kvs: kv::Source>(
+    buf: &mut Vec<u8>,
+    record: &Record,
+    kvs: Option<&Kvs>,
+    add_loc: bool,
+) {
+    buf.clear();
+    // Length prefix and map header (map 32), both set below.
+    buf.extend_from_slice(&[0; 4]);
+    buf.push(0xdf);
+    buf.extend_from_slice(&[0; 4]);
+
+    let mut n: u32 = 4;
+    #[cfg(feature = "timestamp")]
+    {
+        write_str(buf, "timestamp");
+        write_timestamp(buf);
+        n += 1;
+    }
+    write_str(buf, "level");
+    write_str(buf, record.level().as_str());
+    write_str(buf, "message");
+    if kvs.is_some() {
+        write_fmt_str(buf, *record.args());
+    } else {
+        write_truncated_fmt_str(buf, *record.args(), MAX_TRUNCATED_MESSAGE_SIZE);
+    }
+    write_str(buf, "target");
+    write_str(buf, record.target());
+    write_str(buf, "module");
+    write_str(buf, record.module_path().unwrap_or(""));
+
+    if let Some(kvs) = kvs {
+        let mut visitor = KeyValueVisitor { buf, n: 0 };
+        record
+            .key_values()
+            .visit(&mut visitor)
+            .unwrap_or_else(|_| unreachable!());
+        kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+        n += visitor.n;
+    } else {
+        write_str(buf, "truncated");
+        buf.push(0xc3);
+        n += 1;
+    }
+
+    if add_loc {
+        write_str(buf, "file");
+        write_str(buf, record.file().unwrap_or("??"));
+        write_str(buf, "line");
+        write_uint(buf, record.line().unwrap_or(0).into());
+        n += 2;
+    }
+
+    // Only the message and key-values can make a record larger than
+    // `MAX_RECORD_SIZE`, which are limited if `kvs` is `None`.
+    let len = u32::try_from(buf.len() - 4).unwrap_or_else(|_| unreachable!());
+    buf[..4].copy_from_slice(&len.to_be_bytes());
+    buf[5..9].copy_from_slice(&n.to_be_bytes());
+}
+
+/// Write `value` as string.
+fn write_str(buf: &mut Vec<u8>, value: &str) {
+    let len = value.len();
+    #[allow(clippy::cast_possible_truncation)]
+    if len < 32 {
+        buf.push(0xa0 | len as u8);
+    } else if len <= u8::MAX as usize {
+        buf.extend_from_slice(&[0xd9, len as u8]);
+    } else if len <= u16::MAX as usize {
+        buf.push(0xda);
+        buf.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        buf.push(0xdb);
+        buf.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+    buf.extend_from_slice(value.as_bytes());
+}
+
+/// Write `args` as string (str 32).
+fn write_fmt_str(buf: &mut Vec<u8>, args: fmt::Arguments) {
+    buf.push(0xdb);
+    let start = buf.len();
+    buf.extend_from_slice(&[0; 4]);
+    if let Some(value) = args.as_str() {
+        buf.extend_from_slice(value.as_bytes());
+    } else {
+        RawBuf(buf)
+            .write_fmt(args)
+            .unwrap_or_else(|_| unreachable!());
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let len = (buf.len() - start - 4) as u32;
+    buf[start..start + 4].copy_from_slice(&len.to_be_bytes());
+}
+
+/// Write `args` as string (str 32), truncated to at most `max_len` bytes.
+fn write_truncated_fmt_str(buf: &mut Vec<u8>, args: fmt::Arguments, max_len: usize) {
+    let start = buf.len();
+    write_fmt_str(buf, args);
+    let str_start = start + 5;
+    if buf.len() - str_start > max_len {
+        // Don't split a character.
+        let mut end = str_start + max_len;
+        while (buf[end] & 0xc0) == 0x80 {
+            end -= 1;
+        }
+        buf.truncate(end);
+        let len = u32::try_from(end - str_start).unwrap_or_else(|_| unreachable!());
+        buf[start + 1..str_start].copy_from_slice(&len.to_be_bytes());
+    }
+}
+
+/// Write `value` as unsigned integer.
+fn write_uint(buf: &mut Vec<u8>, value: u64) {
+    #[allow(clippy::cast_possible_truncation)]
+    if value < 128 {
+        buf.push(value as u8);
+    } else if value <= u64::from(u8::MAX) {
+        buf.extend_from_slice(&[0xcc, value as u8]);
+    } else if value <= u64::from(u16::MAX) {
+        buf.push(0xcd);
+        buf.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u64::from(u32::MAX) {
+        buf.push(0xce);
+        buf.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        buf.push(0xcf);
+        buf.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Write `value` as signed integer.
+fn write_int(buf: &mut Vec<u8>, value: i64) {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    if value >= 0 {
+        write_uint(buf, value as u64);
+    } else if value >= -32 {
+        // Negative fixint.
+        buf.push(value as u8);
+    } else if value >= i64::from(i8::MIN) {
+        buf.extend_from_slice(&[0xd0, value as u8]);
+    } else if value >= i64::from(i16::MIN) {
+        buf.push(0xd1);
+        buf.extend_from_slice(&(value as i16).to_be_bytes());
+    } else if value >= i64::from(i32::MIN) {
+        buf.push(0xd2);
+        buf.extend_from_slice(&(value as i32).to_be_bytes());
+    } else {
+        buf.push(0xd3);
+        buf.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Write the current time using the timestamp extension type (timestamp 96).
+#[cfg(feature = "timestamp")]
+fn write_timestamp(buf: &mut Vec<u8>) {
+    use std::time::{Duration, SystemTime};
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO);
+    // Ext 8, 12 bytes, type -1.
+    buf.extend_from_slice(&[0xc7, 12, 0xff]);
+    buf.extend_from_slice(&now.subsec_nanos().to_be_bytes());
+    #[allow(clippy::cast_possible_wrap)]
+    buf.extend_from_slice(&(now.as_secs() as i64).to_be_bytes());
+}
+
+/// Writes key value pairs as map entries, counting the number of pairs in
+/// `n`.
+struct KeyValueVisitor<'b> {
+    buf: &'b mut Vec<u8>,
+    n: u32,
+}
+
+impl<'b, 'kvs> VisitSource<'kvs> for KeyValueVisitor<'b> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        write_str(self.buf, key.as_str());
+        value.visit(&mut *self)?;
+        self.n += 1;
+        Ok(())
+    }
+}
+
+impl<'b, 'v> VisitValue<'v> for KeyValueVisitor<'b> {
+    fn visit_any(&mut self, value: kv::Value) -> Result<(), kv::Error> {
+        write_fmt_str(self.buf, format_args!("{value}"));
+        Ok(())
+    }
+
+    fn visit_null(&mut self) -> Result<(), kv::Error> {
+        self.buf.push(0xc0);
+        Ok(())
+    }
+
+    fn visit_u64(&mut self, value: u64) -> Result<(), kv::Error> {
+        write_uint(self.buf, value);
+        Ok(())
+    }
+
+    fn visit_i64(&mut self, value: i64) -> Result<(), kv::Error> {
+        write_int(self.buf, value);
+        Ok(())
+    }
+
+    fn visit_u128(&mut self, value: u128) -> Result<(), kv::Error> {
+        match u64::try_from(value) {
+            Ok(value) => write_uint(self.buf, value),
+            Err(_) => write_fmt_str(self.buf, format_args!("{value}")),
+        }
+        Ok(())
+    }
+
+    fn visit_i128(&mut self, value: i128) -> Result<(), kv::Error> {
+        match i64::try_from(value) {
+            Ok(value) => write_int(self.buf, value),
+            Err(_) => write_fmt_str(self.buf, format_args!("{value}")),
+        }
+        Ok(())
+    }
+
+    fn visit_f64(&mut self, value: f64) -> Result<(), kv::Error> {
+        self.buf.push(0xcb);
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        Ok(())
+    }
+
+    fn visit_bool(&mut self, value: bool) -> Result<(), kv::Error> {
+        self.buf.push(if value { 0xc3 } else { 0xc2 });
+        Ok(())
+    }
+
+    fn visit_str(&mut self, value: &str) -> Result<(), kv::Error> {
+        write_str(self.buf, value);
+        Ok(())
+    }
+}
+
+/// [`fmt::Write`] implementation that writes the string as is.
+struct RawBuf<'b>(&'b mut Vec<u8>);
+
+impl<'b> fmt::Write for RawBuf<'b> {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        self.0.extend_from_slice(string.as_bytes());
+        Ok(())
+    }
+}
@@ -0,0 +1,285 @@
+//! OpenTelemetry logs using OTLP/JSON, following
+//! <https://opentelemetry.io/docs/specs/otel/logs/data-model>.
+
+use std::fmt::{self, Write};
+use std::io::IoSlice;
+
+use log::kv::{VisitSource, VisitValue};
+use log::{kv, Record};
+
+use crate::format::json::{self, Buf};
+use crate::format::syslog::app_name;
+use crate::format::{Buffer, Format, BUFS_SIZE};
+use crate::PANIC_TARGET;
+
+/// OpenTelemetry logs using OTLP/JSON, following
+/// <https://opentelemetry.io/docs/specs/otel/logs/data-model>.
+///
+/// Created using [`Config::opentelemetry`].
+///
+/// Each record is logged as a single OTLP/JSON export request, on a single
+/// line, which can be read by for example the OpenTelemetry collector's
+/// `otlpjsonfile` receiver. The log record has the following fields:
+///  * `timeUnixNano`: only if the *timestamp* feature is enabled,
+///  * `severityNumber` and `severityText`: based on the log level, panics are
+///    logged with the `FATAL` severity,
+///  * `body`: the message,
+///  * `attributes`: all key-value pairs, the module path as `code.namespace`
+///    and, if enabled, the file and line as `code.filepath` and `code.lineno`,
+///  * `traceId` and `spanId`: the `trace_id` and `span_id` key-value pairs, if
+///    present (these are not added to the attributes).
+///
+/// The target is used as the name of the instrumentation scope. The resource
+/// attributes default to `service.name` set to the name of the executable, this
+/// can be changed using [`Config::with_resource`].
+///
+/// [`Config::opentelemetry`]: crate::Config::opentelemetry
+/// [`Config::with_resource`]: crate::Config::with_resource
+#[derive(Debug)]
+pub struct OpenTelemetry {
+    /// Encoded resource attributes.
+    resource: Box<[u8]>,
+}
+
+impl OpenTelemetry {
+    /// Create a new format with `service.name` as resource attribute.
+    pub(crate) fn new() -> OpenTelemetry {
+        OpenTelemetry::with_resource(&("service.name", app_name()))
+    }
+
+    /// Create a new format with `resource` as resource attributes.
+    pub(crate) fn with_resource(resource: &dyn kv::Source) -> OpenTelemetry {
+        let mut buf = Vec::new();
+        resource
+            .visit(&mut AttributeVisitor::new(&mut buf))
+            .unwrap_or_else(|_| unreachable!());
+        if buf.last() == Some(&b',') {
+            let _ = buf.pop();
+        }
+        OpenTelemetry {
+            resource: buf.into_boxed_slice(),
+        }
+    }
+}
+
+impl Format for OpenTelemetry {
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        add_loc: bool,
+    ) -> &'b [IoSlice<'b>] {
+        let buf = &mut buf.buf;
+        buf.clear();
+        buf.extend_from_slice(b"{\"resourceLogs\":[{\"resource\":{\"attributes\":[");
+        buf.extend_from_slice(&self.resource);
+        buf.extend_from_slice(b"]},\"scopeLogs\":[{\"scope\":{\"name\":\"");
+        json::write_escaped(buf, &format_args!("{}", record.target()));
+        buf.extend_from_slice(b"\"},\"logRecords\":[{");
+
+        #[cfg(feature = "timestamp")]
+        {
+            use std::time::{Duration, SystemTime};
+
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or(Duration::ZERO);
+            let mut itoa = itoa::Buffer::new();
+            buf.extend_from_slice(b"\"timeUnixNano\":\"");
+            buf.extend_from_slice(itoa.format(now.as_nanos()).as_bytes());
+            buf.extend_from_slice(b"\",");
+        }
+
+        let (number, text): (&[u8], &[u8]) =
+            if record.level() == log::Level::Error && record.target() == PANIC_TARGET {
+                (b"21", b"FATAL")
+            } else {
+                severity(record.level())
+            };
+        buf.extend_from_slice(b"\"severityNumber\":");
+        buf.extend_from_slice(number);
+        buf.extend_from_slice(b",\"severityText\":\"");
+        buf.extend_from_slice(text);
+        buf.extend_from_slice(b"\",\"body\":{\"stringValue\":\"");
+        json::write_escaped(buf, record.args());
+        buf.extend_from_slice(b"\"},\"attributes\":[");
+
+        let mut visitor = AttributeVisitor::new(buf);
+        record
+            .key_values()
+            .visit(&mut visitor)
+            .unwrap_or_else(|_| unreachable!());
+        kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+        if let Some(module) = record.module_path() {
+            visitor.write_attribute("code.namespace", kv::Value::from(module));
+        }
+        if add_loc {
+            visitor.write_attribute(
+                "code.filepath",
+                kv::Value::from(record.file().unwrap_or("??")),
+            );
+            visitor.write_attribute("code.lineno", kv::Value::from(record.line().unwrap_or(0)));
+        }
+        let AttributeVisitor {
+            buf,
+            trace_id,
+            span_id,
+        } = visitor;
+        if buf.last() == Some(&b',') {
+            let _ = buf.pop();
+        }
+        buf.push(b']');
+        for (key, id) in [("traceId", trace_id), ("spanId", span_id)] {
+            if !id.is_empty() {
+                buf.extend_from_slice(b",\"");
+                buf.extend_from_slice(key.as_bytes());
+                buf.extend_from_slice(b"\":\"");
+                buf.extend_from_slice(&id);
+                buf.push(b'"');
+            }
+        }
+        buf.extend_from_slice(b"}]}]}]}\n");
+
+        bufs[0] = IoSlice::new(buf);
+        &bufs[..1]
+    }
+}
+
+/// Returns the severity number and text for `level`.
+#[inline]
+const fn severity(level: log::Level) -> (&'static [u8], &'static [u8]) {
+    match level {
+        log::Level::Error => (b"17", b"ERROR"),
+        log::Level::Warn => (b"13", b"WARN"),
+        log::Level::Info => (b"9", b"INFO"),
+        log::Level::Debug => (b"5", b"DEBUG"),
+        log::Level::Trace => (b"1", b"TRACE"),
+    }
+}
+
+/// Formats key value pairs as OTLP/JSON attributes, in the following format:
+/// `{"key":"key","value":{"stringValue":"value"}},`. The `trace_id` and
+/// `span_id` values are written to `trace_id` and `span_id` instead.
+struct AttributeVisitor<'b> {
+    buf: &'b mut Vec<u8>,
+    trace_id: Vec<u8>,
+    span_id: Vec<u8>,
+}
+
+impl<'b> AttributeVisitor<'b> {
+    const fn new(buf: &'b mut Vec<u8>) -> AttributeVisitor<'b> {
+        AttributeVisitor {
+            buf,
+            trace_id: Vec::new(),
+            span_id: Vec::new(),
+        }
+    }
+
+    fn write_attribute(&mut self, key: &str, value: kv::Value) {
+        self.buf.extend_from_slice(b"{\"key\":\"");
+        let _ = Buf(self.buf).write_str(key);
+        self.buf.extend_from_slice(b"\",\"value\":{");
+        value
+            .visit(&mut ValueVisitor(self.buf))
+            .unwrap_or_else(|_| unreachable!());
+        self.buf.extend_from_slice(b"}},");
+    }
+}
+
+impl<'b, 'kvs> VisitSource<'kvs> for AttributeVisitor<'b> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let id = match key.as_str() {
+            "trace_id" => &mut self.trace_id,
+            "span_id" => &mut self.span_id,
+            key => {
+                self.write_attribute(key, value);
+                return Ok(());
+            }
+        };
+        id.clear();
+        Buf(id)
+            .write_fmt(format_args!("{value}"))
+            .unwrap_or_else(|_| unreachable!());
+        Ok(())
+    }
+}
+
+/// Formats a value as OTLP/JSON `AnyValue`, without the surrounding braces.
+struct ValueVisitor<'b>(&'b mut Vec<u8>);
+
+impl<'b> ValueVisitor<'b> {
+    /// Write `"kind":value`, if `quoted` the value is written as JSON string.
+    fn write(&mut self, kind: &[u8], quoted: bool, value: fmt::Arguments) {
+        self.0.push(b'"');
+        self.0.extend_from_slice(kind);
+        self.0.extend_from_slice(b"\":");
+        if quoted {
+            self.0.push(b'"');
+        }
+        Buf(self.0)
+            .write_fmt(value)
+            .unwrap_or_else(|_| unreachable!());
+        if quoted {
+            self.0.push(b'"');
+        }
+    }
+}
+
+impl<'b, 'v> VisitValue<'v> for ValueVisitor<'b> {
+    fn visit_any(&mut self, value: kv::Value) -> Result<(), kv::Error> {
+        self.write(b"stringValue", true, format_args!("{value}"));
+        Ok(())
+    }
+
+    fn visit_null(&mut self) -> Result<(), kv::Error> {
+        // Empty value.
+        Ok(())
+    }
+
+    fn visit_u64(&mut self, value: u64) -> Result<(), kv::Error> {
+        // 64 bit integers are encoded as string in JSON. `intValue` is signed,
+        // so larger values are logged as string.
+        let kind: &[u8] = if value > i64::MAX as u64 {
+            b"stringValue"
+        } else {
+            b"intValue"
+        };
+        self.write(kind, true, format_args!("{value}"));
+        Ok(())
+    }
+
+    fn visit_i64(&mut self, value: i64) -> Result<(), kv::Error> {
+        self.write(b"intValue", true, format_args!("{value}"));
+        Ok(())
+    }
+
+    fn visit_f64(&mut self, value: f64) -> Result<(), kv::Error> {
+        if value.is_finite() {
+            let mut buf = zmij::Buffer::new();
+            self.write(b"doubleValue", false, format_args!("{}", buf.format(value)));
+        } else {
+            // JSON doesn't support NaN or infinity, they are encoded as string.
+            let value = if value.is_nan() {
+                "NaN"
+            } else if value.is_sign_positive() {
+                "Infinity"
+            } else {
+                "-Infinity"
+            };
+            self.write(b"doubleValue", true, format_args!("{value}"));
+        }
+        Ok(())
+    }
+
+    fn visit_bool(&mut self, value: bool) -> Result<(), kv::Error> {
+        self.write(b"boolValue", false, format_args!("{value}"));
+        Ok(())
+    }
+
+    fn visit_str(&mut self, value: &str) -> Result<(), kv::Error> {
+        self.write(b"stringValue", true, format_args!("{value}"));
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+//! Human readable format for terminals.
+
+use std::fmt::{self, Write};
+use std::io::IoSlice;
+
+use log::kv::VisitSource;
+use log::{kv, Level, Record};
+
+use crate::format::{logfmt, Buffer, Format, BUFS_SIZE};
+use crate::PANIC_TARGET;
+
+/// Human readable format, intended for terminals during development.
+///
+/// Created using [`Config::pretty`].
+///
+/// Messages look like the following: `12:32:23.906 INFO  some message
+/// key="value" my_crate::module`, where the timestamp is in local time
+/// (when the *timestamp* feature is enabled), the level is colored and the
+/// key-value pairs and target are dimmed. Backtraces of panics are printed on
+/// the following lines.
+///
+/// Colors are only used when the output is a terminal (see
+/// [`Sink::is_terminal`]) and the `NO_COLOR` environment variable is not set,
+/// see <https://no-color.org>.
+///
+/// [`Config::pretty`]: crate::Config::pretty
+/// [`Sink::is_terminal`]: crate::sink::Sink::is_terminal
+#[derive(Debug)]
+pub struct Pretty {
+    pub(crate) colors: bool,
+}
+
+impl Pretty {
+    /// Create a new pretty format, using colors if `NO_COLOR` is not set.
+    ///
+    /// Colors are disabled in [`Format::set_terminal`] if the output is not a
+    /// terminal.
+    pub(crate) fn new() -> Pretty {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Pretty { colors: !no_color }
+    }
+}
+
+/// Reset all attributes.
+const RESET: &[u8] = b"\x1b[0m";
+/// Dimmed text.
+const DIM: &[u8] = b"\x1b[2m";
+
+impl Format for Pretty {
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        add_loc: bool,
+    ) -> &'b [IoSlice<'b>] {
+        let buf = &mut buf.buf;
+        buf.clear();
+        #[cfg(feature = "timestamp")]
+        {
+            write_local_time(buf);
+            buf.push(b' ');
+        }
+
+        let (color, level): (&[u8], &[u8]) = match record.level() {
+            Level::Error => (b"\x1b[1;31m", b"ERROR"),
+            Level::Warn => (b"\x1b[1;33m", b"WARN "),
+            Level::Info => (b"\x1b[32m", b"INFO "),
+            Level::Debug => (b"\x1b[34m", b"DEBUG"),
+            Level::Trace => (b"\x1b[35m", b"TRACE"),
+        };
+        self.write_colored(buf, color, level);
+        buf.push(b' ');
+        RawBuf(buf)
+            .write_fmt(*record.args())
+            .unwrap_or_else(|_| unreachable!());
+
+        // Key-values, except for the backtrace of panics which we write on the
+        // following lines.
+        let mut visitor = KeyValueVisitor {
+            buf: Vec::new(),
+            backtrace: Vec::new(),
+            is_panic: record.target() == PANIC_TARGET,
+        };
+        record
+            .key_values()
+            .visit(&mut visitor)
+            .unwrap_or_else(|_| unreachable!());
+        kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+        if !visitor.buf.is_empty() {
+            buf.push(b' ');
+            // Skip the leading space.
+            self.write_colored(buf, DIM, &visitor.buf[1..]);
+        }
+
+        buf.push(b' ');
+        if self.colors {
+            buf.extend_from_slice(DIM);
+        }
+        buf.extend_from_slice(record.target().as_bytes());
+        if add_loc {
+            buf.extend_from_slice(b" (");
+            buf.extend_from_slice(record.file().unwrap_or("??").as_bytes());
+            buf.push(b':');
+            let mut itoa = itoa::Buffer::new();
+            buf.extend_from_slice(itoa.format(record.line().unwrap_or(0)).as_bytes());
+            buf.push(b')');
+        }
+        if self.colors {
+            buf.extend_from_slice(RESET);
+        }
+        buf.push(b'\n');
+
+        if !visitor.backtrace.is_empty() {
+            let mut backtrace = &*visitor.backtrace;
+            while let [rest @ .., b] = backtrace {
+                if !b.is_ascii_whitespace() {
+                    break;
+                }
+                backtrace = rest;
+            }
+            self.write_colored(buf, DIM, backtrace);
+            buf.push(b'\n');
+        }
+
+        bufs[0] = IoSlice::new(buf);
+        &bufs[..1]
+    }
+
+    fn set_terminal(&mut self, is_terminal: bool) {
+        self.colors = self.colors && is_terminal;
+    }
+}
+
+impl Pretty {
+    /// Write `value` using `color`, if colors are enabled.
+    fn write_colored(&self, buf: &mut Vec<u8>, color: &[u8], value: &[u8]) {
+        if self.colors {
+            buf.extend_from_slice(color);
+            buf.extend_from_slice(value);
+            buf.extend_from_slice(RESET);
+        } else {
+            buf.extend_from_slice(value);
+        }
+    }
+}
+
+/// Write the current local time in the format `HH:MM:SS.mmm`.
+#[cfg(feature = "timestamp")]
+fn write_local_time(buf: &mut Vec<u8>) {
+    let (hour, min, sec, millis) = local_time();
+    let mut itoa = itoa::Buffer::new();
+    for (value, sep) in [(hour, b':'), (min, b':'), (sec, b'.')] {
+        if value < 10 {
+            buf.push(b'0');
+        }
+        buf.extend_from_slice(itoa.format(value).as_bytes());
+        buf.push(sep);
+    }
+    if millis < 100 {
+        buf.push(b'0');
+    }
+    if millis < 10 {
+        buf.push(b'0');
+    }
+    buf.extend_from_slice(itoa.format(millis).as_bytes());
+}
+
+/// Returns the current local time as hour, minute, second and millisecond.
+#[cfg(all(feature = "timestamp", unix))]
+fn local_time() -> (u32, u32, u32, u32) {
+    use std::mem::MaybeUninit;
+    use std::time::{Duration, SystemTime};
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO);
+    #[allow(clippy::cast_possible_wrap)]
+    let secs = now.as_secs() as libc::time_t;
+    let mut tm = MaybeUninit::uninit();
+    // SAFETY: both pointers are valid, `localtime_r` initialises `tm` if it
+    // returns a non-null pointer.
+    match unsafe { libc::localtime_r(&secs, tm.as_mut_ptr()).as_ref() } {
+        #[allow(clippy::cast_sign_loss)]
+        Some(tm) => (
+            tm.tm_hour as u32,
+            tm.tm_min as u32,
+            tm.tm_sec as u32,
+            now.subsec_millis(),
+        ),
+        None => utc_time(),
+    }
+}
+
+/// Returns the current local time as hour, minute, second and millisecond.
+///
+/// On non-Unix platforms this uses UTC.
+#[cfg(all(feature = "timestamp", not(unix)))]
+fn local_time() -> (u32, u32, u32, u32) {
+    utc_time()
+}
+
+/// Returns the current UTC time as hour, minute, second and millisecond.
+#[cfg(feature = "timestamp")]
+fn utc_time() -> (u32, u32, u32, u32) {
+    let timestamp = crate::timestamp::Timestamp::now();
+    (
+        u32::from(timestamp.hour),
+        u32::from(timestamp.min),
+        u32::from(timestamp.sec),
+        timestamp.micro / 1000,
+    )
+}
+
+/// Formats key value pairs in logfmt, except for the backtrace of a panic
+/// which is written as is to `backtrace`.
+struct KeyValueVisitor {
+    buf: Vec<u8>,
+    backtrace: Vec<u8>,
+    is_panic: bool,
+}
+
+impl<'kvs> VisitSource<'kvs> for KeyValueVisitor {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        if self.is_panic && key.as_str() == "backtrace" {
+            RawBuf(&mut self.backtrace)
+                .write_fmt(format_args!("{value}"))
+                .unwrap_or_else(|_| unreachable!());
+        } else {
+            logfmt::write_kvs(&mut self.buf, &(key, value));
+        }
+        Ok(())
+    }
+}
+
+/// [`fmt::Write`] implementation that writes the string as is.
+struct RawBuf<'b>(&'b mut Vec<u8>);
+
+impl<'b> fmt::Write for RawBuf<'b> {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        self.0.extend_from_slice(string.as_bytes());
+        Ok(())
+    }
+}
+
+/// Format that uses [`Pretty`] if the output is a terminal, or the structured
+/// format `F` otherwise.
+///
+/// Created using [`Config::with_pretty_on_tty`].
+///
+/// [`Config::with_pretty_on_tty`]: crate::Config::with_pretty_on_tty
+#[derive(Debug)]
+pub enum Auto<F> {
+    /// The output is a terminal.
+    Pretty(Pretty),
+    /// The output is not a terminal.
+    Structured(F),
+}
+
+impl<F> Auto<F> {
+    /// Use `format`, until [`Format::set_terminal`] switches to [`Pretty`].
+    pub(crate) fn new(format: F) -> Auto<F> {
+        Auto::Structured(format)
+    }
+}
+
+impl<F: Format> Format for Auto<F> {
+    #[inline]
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        add_loc: bool,
+    ) -> &'b [IoSlice<'b>] {
+        match self {
+            Auto::Pretty(format) => format.format(bufs, buf, record, kvs, add_loc),
+            Auto::Structured(format) => format.format(bufs, buf, record, kvs, add_loc),
+        }
+    }
+
+    fn set_terminal(&mut self, is_terminal: bool) {
+        match self {
+            Auto::Structured(_) if is_terminal => {
+                let mut format = Pretty::new();
+                format.set_terminal(is_terminal);
+                *self = Auto::Pretty(format);
+            }
+            Auto::Pretty(format) => format.set_terminal(is_terminal),
+            Auto::Structured(format) => format.set_terminal(is_terminal),
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Syslog, following RFC 5424 <https://datatracker.ietf.org/doc/html/rfc5424>
+//! or RFC 3164 <https://datatracker.ietf.org/doc/html/rfc3164>.
+
+use std::fmt::{self, Write};
+use std::io::IoSlice;
+use std::sync::OnceLock;
+use std::{env, process};
+
+use log::kv::VisitSource;
+use log::{kv, Record};
+
+use crate::format::{logfmt, Buffer, Format, BUFS_SIZE};
+use crate::PANIC_TARGET;
+
+/// Syslog facility used for all messages: user-level messages.
+const FACILITY: u8 = 1;
+
+/// SD-ID of the structured data element holding the key-value pairs. Uses the
+/// private enterprise number reserved for documentation, see RFC 5612.
+const SD_ID: &[u8] = b"kv@32473";
+
+/// Value used if a field is not available.
+const NIL: &[u8] = b"-";
+
+/// Syslog following RFC 5424 <https://datatracker.ietf.org/doc/html/rfc5424>.
+///
+/// Created using [`Config::syslog`].
+///
+/// Messages are formatted as `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID
+/// [SD-ELEMENT] MSG`, where:
+///  * `PRI` is based on the user-level facility and the log level, see below,
+///  * `TIMESTAMP` is the time in UTC, or `-` if the *timestamp* feature is
+///    disabled,
+///  * `HOSTNAME` is the hostname of the machine,
+///  * `APP-NAME` is the name of the executable,
+///  * `PROCID` is the process id,
+///  * `MSGID` is the log target,
+///  * `SD-ELEMENT` holds all key-value pairs, using the `kv@32473` SD-ID, or `-`
+///    if there are none,
+///  * `MSG` is the message.
+///
+/// The log levels are mapped to the following syslog severities: error to
+/// error, warn to warning, info to informational and both debug and trace to
+/// debug. Panics are logged using the critical severity.
+///
+/// [`Config::syslog`]: crate::Config::syslog
+#[derive(Debug)]
+pub struct Syslog(pub(crate) ());
+
+impl Format for Syslog {
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        add_loc: bool,
+    ) -> &'b [IoSlice<'b>] {
+        let buf = &mut buf.buf;
+        buf.clear();
+        write_pri(buf, record);
+        buf.extend_from_slice(b"1 ");
+        #[cfg(feature = "timestamp")]
+        {
+            let start = buf.len();
+            buf.resize(start + super::TIMESTAMP_LEN, 0);
+            super::format_timestamp(&mut buf[start..]);
+        }
+        #[cfg(not(feature = "timestamp"))]
+        buf.extend_from_slice(NIL);
+        buf.push(b' ');
+        write_header_field(buf, hostname(), 255);
+        buf.push(b' ');
+        write_header_field(buf, app_name(), 48);
+        buf.push(b' ');
+        let mut itoa = itoa::Buffer::new();
+        buf.extend_from_slice(itoa.format(process::id()).as_bytes());
+        buf.push(b' ');
+        write_header_field(buf, record.target(), 32);
+        buf.push(b' ');
+
+        // Structured data.
+        let sd_start = buf.len();
+        buf.push(b'[');
+        buf.extend_from_slice(SD_ID);
+        let params_start = buf.len();
+        let mut visitor = KeyValueVisitor(buf);
+        record
+            .key_values()
+            .visit(&mut visitor)
+            .unwrap_or_else(|_| unreachable!());
+        kvs.visit(&mut visitor).unwrap_or_else(|_| unreachable!());
+        if add_loc {
+            buf.extend_from_slice(b" file=\"");
+            write_param_value(buf, &format_args!("{}", record.file().unwrap_or("??")));
+            buf.push(b':');
+            buf.extend_from_slice(itoa.format(record.line().unwrap_or(0)).as_bytes());
+            buf.push(b'"');
+        }
+        if buf.len() == params_start {
+            // No parameters.
+            buf.truncate(sd_start);
+            buf.extend_from_slice(NIL);
+        } else {
+            buf.push(b']');
+        }
+
+        buf.push(b' ');
+        RawBuf(buf)
+            .write_fmt(*record.args())
+            .unwrap_or_else(|_| unreachable!());
+
+        bufs[0] = IoSlice::new(buf);
+        bufs[1] = IoSlice::new(b"\n");
+        &bufs[..2]
+    }
+}
+
+/// Syslog following RFC 3164 <https://datatracker.ietf.org/doc/html/rfc3164>,
+/// also known as the BSD syslog format.
+///
+/// Created using [`Config::syslog_rfc3164`].
+///
+/// Messages are formatted as `<PRI>TIMESTAMP HOSTNAME TAG[PID]: MSG`, where
+/// `TIMESTAMP` is formatted as `Mmm dd hh:mm:ss` in UTC (not logged if the
+/// *timestamp* feature is disabled), `TAG` is the name of the executable and
+/// `MSG` is the message followed by the key-value pairs in logfmt. `PRI` is
+/// determined in the same way as for the [`Syslog`] format.
+///
+/// [`Config::syslog_rfc3164`]: crate::Config::syslog_rfc3164
+#[derive(Debug)]
+pub struct SyslogRfc3164(pub(crate) ());
+
+impl Format for SyslogRfc3164 {
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        add_loc: bool,
+    ) -> &'b [IoSlice<'b>] {
+        let buf = &mut buf.buf;
+        buf.clear();
+        write_pri(buf, record);
+        #[cfg(feature = "timestamp")]
+        {
+            write_rfc3164_timestamp(buf);
+            buf.push(b' ');
+            write_header_field(buf, hostname(), 255);
+            buf.push(b' ');
+        }
+        write_header_field(buf, app_name(), 32);
+        buf.push(b'[');
+        let mut itoa = itoa::Buffer::new();
+        buf.extend_from_slice(itoa.format(process::id()).as_bytes());
+        buf.extend_from_slice(b"]: ");
+        RawBuf(buf)
+            .write_fmt(*record.args())
+            .unwrap_or_else(|_| unreachable!());
+        logfmt::write_kvs(buf, record.key_values());
+        logfmt::write_kvs(buf, kvs);
+        buf.extend_from_slice(b" target=\"");
+        logfmt::write_escaped(buf, &format_args!("{}", record.target()));
+        buf.push(b'"');
+        if add_loc {
+            buf.extend_from_slice(b" file=\"");
+            logfmt::write_escaped(buf, &format_args!("{}", record.file().unwrap_or("??")));
+            buf.push(b':');
+            buf.extend_from_slice(itoa.format(record.line().unwrap_or(0)).as_bytes());
+            buf.push(b'"');
+        }
+
+        bufs[0] = IoSlice::new(buf);
+        bufs[1] = IoSlice::new(b"\n");
+        &bufs[..2]
+    }
+}
+
+/// Returns the syslog severity for `level`.
+#[inline]
+pub(crate) const fn severity(level: log::Level) -> u8 {
+    // NOTE: syslog doesn't have trace messages so we use debug twice.
+    const SEVERITIES: [u8; 6] = [0, 3, 4, 6, 7, 7];
+    SEVERITIES[level as usize]
+}
+
+/// Write `<PRI>`.
+fn write_pri(buf: &mut Vec<u8>, record: &Record) {
+    let severity = if record.level() == log::Level::Error && record.target() == PANIC_TARGET {
+        // If we're panicking we increase the severity to critical.
+        2
+    } else {
+        severity(record.level())
+    };
+    let pri = FACILITY * 8 + severity;
+    buf.push(b'<');
+    let mut itoa = itoa::Buffer::new();
+    buf.extend_from_slice(itoa.format(pri).as_bytes());
+    buf.push(b'>');
+}
+
+/// Write a header field, which may only contain printable ASCII characters
+/// and is limited to `max_len` characters. Invalid characters are replaced
+/// with an underscore. Writes `-` if `value` is empty.
+fn write_header_field(buf: &mut Vec<u8>, value: &str, max_len: usize) {
+    if value.is_empty() {
+        buf.extend_from_slice(NIL);
+        return;
+    }
+    buf.extend(value.bytes().take(max_len).map(|b| match b {
+        33..=126 => b,
+        _ => b'_',
+    }));
+}
+
+/// Write a timestamp in the format `Mmm dd hh:mm:ss`, e.g. `Jan  1 12:00:00`.
+#[cfg(feature = "timestamp")]
+fn write_rfc3164_timestamp(buf: &mut Vec<u8>) {
+    const MONTHS: [&[u8]; 12] = [
+        b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov",
+        b"Dec",
+    ];
+    let timestamp = crate::timestamp::Timestamp::now();
+    buf.extend_from_slice(MONTHS[(timestamp.month - 1) as usize]);
+    buf.push(b' ');
+    if timestamp.day < 10 {
+        buf.push(b' ');
+    }
+    let mut itoa = itoa::Buffer::new();
+    buf.extend_from_slice(itoa.format(timestamp.day).as_bytes());
+    for (sep, value) in [
+        (b' ', timestamp.hour),
+        (b':', timestamp.min),
+        (b':', timestamp.sec),
+    ] {
+        buf.push(sep);
+        if value < 10 {
+            buf.push(b'0');
+        }
+        buf.extend_from_slice(itoa.format(value).as_bytes());
+    }
+}
+
+/// Returns the hostname of the machine, or an empty string if unknown.
+pub(crate) fn hostname() -> &'static str {
+    static HOSTNAME: OnceLock<String> = OnceLock::new();
+    HOSTNAME.get_or_init(|| {
+        #[cfg(unix)]
+        {
+            let mut buf = [0u8; 256];
+            // SAFETY: `buf` is valid for `buf.len()` bytes.
+            let res = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+            if res == 0 {
+                let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+                return String::from_utf8_lossy(&buf[..len]).into_owned();
+            }
+        }
+        env::var("HOSTNAME")
+            .or_else(|_| env::var("COMPUTERNAME"))
+            .unwrap_or_default()
+    })
+}
+
+/// Returns the name of the executable, or an empty string if unknown.
+pub(crate) fn app_name() -> &'static str {
+    static APP_NAME: OnceLock<String> = OnceLock::new();
+    APP_NAME.get_or_init(|| {
+        env::args_os()
+            .next()
+            .as_ref()
+            .and_then(|arg0| std::path::Path::new(arg0).file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    })
+}
+
+/// Formats key value pairs as structured data parameters, in the following
+/// format: ` key="value"`.
+struct KeyValueVisitor<'b>(&'b mut Vec<u8>);
+
+impl<'b, 'kvs> VisitSource<'kvs> for KeyValueVisitor<'b> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push(b' ');
+        // PARAM-NAME may only contain printable ASCII characters, except for
+        // `=`, space, `]` and `"`, and is at most 32 characters long.
+        self.0
+            .extend(key.as_str().bytes().take(32).map(|b| match b {
+                b'=' | b' ' | b']' | b'"' => b'_',
+                33..=126 => b,
+                _ => b'_',
+            }));
+        self.0.extend_from_slice(b"=\"");
+        write_param_value(self.0, &format_args!("{value}"));
+        self.0.push(b'"');
+        Ok(())
+    }
+}
+
+/// Write a structured data parameter value, escaping `"`, `\` and `]`.
+fn write_param_value(buf: &mut Vec<u8>, args: &fmt::Arguments) {
+    ParamBuf(buf)
+        .write_fmt(*args)
+        .unwrap_or_else(|_| unreachable!());
+}
+
+/// [`fmt::Write`] implementation that escapes structured data parameter values.
+struct ParamBuf<'b>(&'b mut Vec<u8>);
+
+impl<'b> fmt::Write for ParamBuf<'b> {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        for b in string.bytes() {
+            if matches!(b, b'"' | b'\\' | b']') {
+                self.0.push(b'\\');
+            }
+            self.0.push(b);
+        }
+        Ok(())
+    }
+}
+
+/// [`fmt::Write`] implementation that writes the string as is.
+struct RawBuf<'b>(&'b mut Vec<u8>);
+
+impl<'b> fmt::Write for RawBuf<'b> {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        self.0.extend_from_slice(string.as_bytes());
+        Ok(())
+    }
+}
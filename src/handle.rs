@@ -0,0 +1,159 @@
+//! Changing the logger's configuration at runtime.
+
+use std::slice;
+use std::sync::Arc;
+
+use log::LevelFilter;
+
+use crate::{Filter, SharedFilter};
+
+/// Handle to the logger, used to change what messages are logged while the
+/// process is running.
+///
+/// Returned by [`Config::init_with_handle`] and [`Fanout::init_with_handle`]
+/// (and related functions).
+/// The handle can be cloned and shared between threads. When using [`Fanout`]
+/// changes made using the handle apply to all destinations, use
+/// [`LoggerHandle::destination`] to get a handle for a single destination.
+///
+/// All changes also update the maximum severity of the [`log`] crate, see
+/// [`log::set_max_level`].
+///
+/// [`Config::init_with_handle`]: crate::Config::init_with_handle
+/// [`Fanout`]: crate::Fanout
+/// [`Fanout::init_with_handle`]: crate::Fanout::init_with_handle
+///
+/// # Examples
+///
+/// Enable debug logging at runtime.
+///
+/// ```
+/// use log::LevelFilter;
+///
+/// let handle = std_logger::Config::logfmt().init_with_handle();
+/// // Enable debug logging for an incident.
+/// handle.set_level(LevelFilter::Debug);
+/// # assert_eq!(log::max_level(), LevelFilter::Debug);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LoggerHandle {
+    /// Filters of all destinations.
+    filters: Arc<[SharedFilter]>,
+    /// Index of the destination in `filters` this handle changes, or `None`
+    /// for all destinations.
+    destination: Option<usize>,
+}
+
+impl LoggerHandle {
+    pub(crate) fn new(filters: Box<[SharedFilter]>) -> LoggerHandle {
+        LoggerHandle {
+            filters: filters.into(),
+            destination: None,
+        }
+    }
+
+    /// Returns a handle that only changes the destination at `index`, in the
+    /// order the destinations were added using [`Fanout::with`], or `None` if
+    /// there is no such destination.
+    ///
+    /// When not using [`Fanout`] there is a single destination, at index 0.
+    ///
+    /// [`Fanout`]: crate::Fanout
+    /// [`Fanout::with`]: crate::Fanout::with
+    pub fn destination(&self, index: usize) -> Option<LoggerHandle> {
+        if index < self.filters.len() {
+            Some(LoggerHandle {
+                filters: self.filters.clone(),
+                destination: Some(index),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the most verbose severity (log level) that is logged.
+    pub fn level(&self) -> LevelFilter {
+        max_level(self.selected())
+    }
+
+    /// Set the maximum severity (log level) of the messages to log.
+    ///
+    /// This doesn't change the per-target severities set using the `LOG` or
+    /// `LOG_LEVEL` environment variables. When using [`Fanout`] this sets the
+    /// same severity for all destinations, see [`LoggerHandle::destination`]
+    /// to change the severity of a single destination.
+    ///
+    /// [`Fanout`]: crate::Fanout
+    pub fn set_level(&self, level: LevelFilter) {
+        self.update(|filter| filter.level = level);
+    }
+
+    /// Change the severity (log level) of each destination using `change`,
+    /// which is called with the current severity of the destination. Returns
+    /// the most verbose of the new severities.
+    pub(crate) fn change_level<C>(&self, mut change: C) -> LevelFilter
+    where
+        C: FnMut(LevelFilter) -> LevelFilter,
+    {
+        let mut max = LevelFilter::Off;
+        self.update(|filter| {
+            filter.level = change(filter.level);
+            max = max.max(filter.level);
+        });
+        max
+    }
+
+    /// Only log messages with a target that starts with one of the prefixes
+    /// in `targets`. If `targets` is empty all targets are logged.
+    ///
+    /// Note that requests and panics are always logged.
+    pub fn set_targets<I>(&self, targets: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<Box<str>>,
+    {
+        let targets: Box<[Box<str>]> = targets.into_iter().map(Into::into).collect();
+        self.update(|filter| filter.targets.only = targets.clone());
+    }
+
+    /// Never log messages with a target that starts with one of the prefixes
+    /// in `targets`.
+    ///
+    /// Note that requests and panics are always logged.
+    pub fn set_excluded_targets<I>(&self, targets: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<Box<str>>,
+    {
+        let targets: Box<[Box<str>]> = targets.into_iter().map(Into::into).collect();
+        self.update(|filter| filter.targets.exclude = targets.clone());
+    }
+
+    /// Returns the filters of the destinations this handle changes.
+    fn selected(&self) -> &[SharedFilter] {
+        match self.destination {
+            Some(index) => slice::from_ref(&self.filters[index]),
+            None => &self.filters,
+        }
+    }
+
+    /// Apply `change` to the selected filters and update the maximum severity.
+    fn update<C>(&self, mut change: C)
+    where
+        C: FnMut(&mut Filter),
+    {
+        for filter in self.selected() {
+            filter.update(&mut change);
+        }
+        log::set_max_level(max_level(&self.filters));
+    }
+}
+
+/// Returns the most verbose severity of all `filters`.
+fn max_level(filters: &[SharedFilter]) -> LevelFilter {
+    filters
+        .iter()
+        .map(|filter| filter.read().max_level())
+        .max()
+        .unwrap_or(LevelFilter::Off)
+}
@@ -8,13 +8,37 @@
 //!
 //! # Supported Formats
 //!
-//! This crate supports the three following formats:
+//! This crate supports the following formats:
 //!
 //! * Logfmt, following <https://www.brandur.org/logfmt>, use
 //!   [`Config::logfmt`].
 //! * JSON, use [`Config::json`].
 //! * Google Cloud Platform structured logging using JSON, following
 //!   <https://cloud.google.com/logging/docs/structured-logging>, [`Config::gcloud`].
+//! * Elastic Common Schema (ECS) using JSON, following
+//!   <https://www.elastic.co/guide/en/ecs-logging/overview/current/intro.html>,
+//!   [`Config::ecs`].
+//! * Datadog using JSON, following Datadog's attribute naming convention,
+//!   [`Config::datadog`].
+//! * Graylog Extended Log Format (GELF), [`Config::gelf`].
+//! * OpenTelemetry logs using OTLP/JSON, [`Config::opentelemetry`].
+//! * AWS CloudWatch Embedded Metric Format using JSON, [`Config::emf`].
+//! * Binary MessagePack, [`Config::msgpack`].
+//! * systemd-journald's native protocol, following
+//!   <https://systemd.io/JOURNAL_NATIVE_PROTOCOL>, [`Config::journald`].
+//! * Syslog, following RFC 5424 or RFC 3164, [`Config::syslog`] and
+//!   [`Config::syslog_rfc3164`].
+//! * A human readable format with colors for terminals, [`Config::pretty`].
+//!
+//! The format can also be selected at runtime using the `LOG_FORMAT`
+//! environment variable, e.g. `LOG_FORMAT=json`, see [`Config::from_env`].
+//! [`Config::with_pretty_on_tty`] uses the pretty format when the output is a
+//! terminal and the configured format otherwise.
+//!
+//! Other formats can be added by implementing the [`Format`] trait and using
+//! [`Config::custom`].
+//!
+//! [`Format`]: format::Format
 //!
 //! ### Logfmt
 //!
@@ -59,6 +83,12 @@
 //! Note: the timestamp is not printed when the *timestamp* feature is not
 //! enabled, this feature is enabled by default, see [Timestamp feature] below.
 //!
+//! The names of the fields in the JSON and logfmt formats can be changed, or
+//! the fields omitted, using [`Config::with_field_name`] and
+//! [`Config::without_field`]. For JSON the key-values can be logged in a
+//! nested object, e.g. `"fields":{"user_id":123}`, using
+//! [`Config::with_nested_kvs`], preventing collisions with the built-in fields.
+//!
 //! ### Gcloud (GCP JSON)
 //!
 //! Google Cloud Platform structured logging using JSON, or just gcloud for
@@ -84,6 +114,150 @@
 //!    line information to all logs, if the source information is provided.
 //!
 //!
+//! ### ECS (Elastic Common Schema JSON)
+//!
+//! Elastic Common Schema logging using JSON, messages look like the following:
+//!
+//! ```text
+//! {"@timestamp":"YYYY-MM-DDTHH:MM:SS.MICROSZ","log.level":"$log_level","message":"$message","ecs.version":"1.6.0","log.logger":"$target"}
+//!
+//! For example:
+//!
+//! {"@timestamp":"2018-03-24T13:48:28.820588Z","log.level":"error","message":"my error message","ecs.version":"1.6.0","log.logger":"my_module"}
+//! ```
+//!
+//! Panics are logged using the `critical` level, with the message and backtrace
+//! in `error.message` and `error.stack_trace`. When debug logging is enabled
+//! `log.origin.file.name` and `log.origin.file.line` are added.
+//!
+//!
+//! ### Datadog
+//!
+//! Datadog structured logging using JSON, messages look like the following:
+//!
+//! ```text
+//! {"timestamp":"YYYY-MM-DDTHH:MM:SS.MICROSZ","status":"$log_level","message":"$message","logger":{"name":"$target","method_name":"$module"}}
+//!
+//! For example:
+//!
+//! {"timestamp":"2018-03-24T13:48:28.820588Z","status":"error","message":"my error message","logger":{"name":"my_module","method_name":"my_module"},"dd":{"trace_id":"123","span_id":"456"}}
+//! ```
+//!
+//! The `dd.trace_id`, `dd.span_id`, `dd.service`, `dd.env` and `dd.version`
+//! key-values are logged in the `dd` object. Panics are logged using the
+//! `critical` status, with the `error` object holding the message and the
+//! backtrace.
+//!
+//!
+//! ### GELF
+//!
+//! Graylog Extended Log Format version 1.1, messages look like the following:
+//!
+//! ```text
+//! {"version":"1.1","host":"$hostname","short_message":"$message","timestamp":$seconds.$micros,"level":$severity,"_target":"$target","_module":"$module","_$key":"$value"}
+//!
+//! For example:
+//!
+//! {"version":"1.1","host":"my_host","short_message":"my error message","timestamp":1521899308.820588,"level":3,"_target":"my_module","_module":"my_module","_user_id":123}
+//! ```
+//!
+//! The log level is mapped to a syslog severity in the same way as for the
+//! syslog format. Multi-line messages and panics, including their backtrace,
+//! are also logged as `full_message`. Records can be send to Graylog over UDP
+//! using the [`sink::Gelf`] output, which splits large records into chunks.
+//!
+//!
+//! ### OpenTelemetry
+//!
+//! OpenTelemetry logs using OTLP/JSON, each record is logged as an export
+//! request on a single line (formatted over multiple lines here):
+//!
+//! ```text
+//! {"resourceLogs":[{"resource":{"attributes":[{"key":"service.name","value":{"stringValue":"my_binary"}}]},
+//!   "scopeLogs":[{"scope":{"name":"my_module"},"logRecords":[{
+//!     "timeUnixNano":"1521899308820588000","severityNumber":17,"severityText":"ERROR",
+//!     "body":{"stringValue":"my error message"},
+//!     "attributes":[{"key":"user_id","value":{"intValue":"123"}},{"key":"code.namespace","value":{"stringValue":"my_module"}}],
+//!     "traceId":"5b8efff798038103d269b633813fc60c","spanId":"eee19b7ec3c1b174"}]}]}]}
+//! ```
+//!
+//! The `trace_id` and `span_id` key-values are logged as `traceId` and
+//! `spanId`. Panics are logged using the `FATAL` severity. The resource
+//! attributes can be set using [`Config::with_resource`].
+//!
+//!
+//! ### CloudWatch EMF
+//!
+//! AWS CloudWatch Embedded Metric Format, which is the same as the JSON format,
+//! but records with metrics have the `_aws` metadata object added. For
+//! example using `Config::emf("MyApp").with_metrics(["latency_ms"])` (formatted
+//! over multiple lines here):
+//!
+//! ```text
+//! {"timestamp":"2018-03-24T13:48:28.820588Z","level":"INFO","message":"finished request","target":"request","module":"my_module",
+//!   "latency_ms":12,"_aws":{"Timestamp":1521899308820,"CloudWatchMetrics":[{"Namespace":"MyApp","Dimensions":[[]],"Metrics":[{"Name":"latency_ms"}]}]}}
+//! ```
+//!
+//! See [`Config::with_metrics`] and [`Config::with_dimensions`].
+//!
+//!
+//! ### MessagePack
+//!
+//! Binary format for high volume log pipelines. Each record is written as a 32
+//! bit big-endian length followed by a MessagePack map holding the same fields
+//! as the JSON format. Integers, floats and booleans in key-values keep their
+//! type. The [`std-logger-parser`] crate can be used to read these records.
+//!
+//! [`std-logger-parser`]: https://crates.io/crates/std-logger-parser
+//!
+//!
+//! ### Journald
+//!
+//! Using journald's native protocol all messages, including requests, are send
+//! to journald directly. Each record is send with the following fields:
+//! `PRIORITY`, `MESSAGE`, `TARGET`, `CODE_MODULE`, `CODE_FILE`, `CODE_LINE`
+//! and all key-value pairs, with their keys converted to uppercase, e.g.
+//! `user_id` is logged as `USER_ID`.
+//!
+//! Similar to the gcloud format panics are logged with the critical priority.
+//!
+//! ### Syslog
+//!
+//! Syslog following RFC 5424, messages look like the following:
+//!
+//! ```text
+//! <$PRI>1 YYYY-MM-DDTHH:MM:SS.MICROSZ $hostname $app_name $pid $target [kv@32473 $key="$value"] $message
+//!
+//! For example:
+//!
+//! <11>1 2018-03-24T13:48:28.820588Z my_host my_binary 1234 my_module [kv@32473 user_id="123"] my error message
+//! ```
+//!
+//! The log level is mapped to a syslog severity in the same way as for
+//! journald. The older RFC 3164 syslog format is also supported, see
+//! [`Config::syslog_rfc3164`]. Records can be send to a syslog daemon, e.g. at
+//! `/dev/log`, or a collector over UDP or TCP using the [`sink::Syslog`]
+//! output.
+//!
+//! ### Pretty
+//!
+//! Human readable format intended for terminals during development, messages
+//! look like the following:
+//!
+//! ```text
+//! HH:MM:SS.mmm $LEVEL $message $key1=$value1 $target
+//!
+//! For example:
+//!
+//! 13:48:28.820 ERROR my error message user_id=123 my_module
+//! ```
+//!
+//! The timestamp is in local time. The level is colored and the key-value
+//! pairs and target are dimmed, but only if the output is a terminal and the
+//! `NO_COLOR` environment variable is not set. Backtraces of panics are
+//! printed on the lines following the message.
+//!
+//!
 //! # Setting severity
 //!
 //! You can use various environment variables to change the severity (log level)
@@ -122,6 +296,34 @@
 //! If none of these environment variables are found it will default to an
 //! information severity.
 //!
+//! `LOG` and `LOG_LEVEL` also support setting the severity for specific
+//! targets, using `target=severity` directives separated by commas. A target
+//! uses the severity of the directive with the longest prefix that matches the
+//! target, or the global severity if no directive matches.
+//!
+//! ```bash
+//! ## In your shell of your choice:
+//!
+//! ## Log info messages, but trace messages for the `my_crate::db` module.
+//! $ LOG=info,my_crate::db=trace ./my_binary
+//!
+//! ## Without a global severity, the `TRACE` and `DEBUG` variables or the
+//! ## default severity is used.
+//! $ LOG=hyper=warn ./my_binary
+//! ```
+//!
+//! Instead of using the environment the severity and targets can also be set
+//! using [`Config::with_level`], [`Config::with_targets`] and
+//! [`Config::with_excluded_targets`]. To prevent multiple binaries in the same
+//! environment from using the same configuration [`Config::with_env_prefix`]
+//! can be used to use prefixed environment variables, e.g. `MYAPP_LOG`.
+//!
+//! The severity, and the targets to log, can also be changed while the process
+//! is running, using the [`LoggerHandle`] returned by
+//! [`Config::init_with_handle`]. On Unix
+//! [`Config::with_level_signals`] can be used to increase the severity using
+//! `SIGUSR1` and decrease it using `SIGUSR2`.
+//!
 //! In addition to these runtime filters the [log] crate provides [compile time
 //! filters] which allows you to filter log messages at compile time.
 //!
@@ -144,6 +346,40 @@
 //! ```
 //!
 //!
+//! # Outputs
+//!
+//! By default regular messages are written to standard error and requests to
+//! standard out. Both can be changed, using [`Config::with_output`] and
+//! [`Config::with_request_output`] respectively, to any type that implements
+//! the [`Sink`] trait, for example a file. See the [`sink`] module for the
+//! available sinks.
+//!
+//! ```no_run
+//! use std_logger::sink::LogFile;
+//!
+//! # fn main() -> std::io::Result<()> {
+//! std_logger::Config::logfmt()
+//!     .with_output(LogFile::open("/var/log/my_app/error.log")?)
+//!     .with_request_output(LogFile::open("/var/log/my_app/access.log")?)
+//!     .init();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! When using an external tool such as `logrotate(8)` to rotate log files, use
+//! [`reopen`] or [`Config::with_reopen_on_sighup`] to start writing to the new
+//! file after it has been rotated.
+//!
+//! Writing to the outputs is done on the logging thread by default. To not
+//! block the logging thread on slow outputs, use [`Config::with_async`] to
+//! write records on a background thread instead. In that case use
+//! [`Config::init_with_guard`] to make sure all records are written before the
+//! process exits.
+//!
+//! To log to multiple destinations at once, each with its own format, severity
+//! and outputs, use [`Fanout`].
+//!
+//!
 //! # Limiting logging targets
 //!
 //! Sometimes it's useful to only log messages related to a specific target, for
@@ -168,6 +404,21 @@
 //! $ LOG_LEVEL=trace LOG_TARGET=my_crate::my_module ./my_binary
 //! ```
 //!
+//! Targets can also be excluded by prefixing them with `-`. Exclusions are
+//! checked before the targets to log, so they can be used to silence a single
+//! module, or a chatty dependency. Exclusions can also be set using
+//! [`Config::with_excluded_targets`].
+//!
+//! ```bash
+//! ## In your shell of choose:
+//!
+//! ## Log all messages, except for those from the `hyper` and `rustls` crates.
+//! $ LOG_TARGET=-hyper,-rustls ./my_binary
+//!
+//! ## Log messages from your crate, except for the `my_crate::db` module.
+//! $ LOG_TARGET=my_crate,-my_crate::db ./my_binary
+//! ```
+//!
 //! Note that [requests] and panics (with [target="panic"]) are always logged.
 //!
 //! [requests]: index.html#logging-requests
@@ -176,11 +427,12 @@
 //!
 //! # Crate features
 //!
-//! This crate has three features:
+//! This crate has the following features:
 //! * *timestamp*, enabled by default.
 //! * *log-panic*, enabled by default.
 //! * *nightly*, disabled by default.
 //! * *serde1*, disabled by default.
+//! * *gzip*, disabled by default.
 //!
 //!
 //! ## Timestamp feature
@@ -244,8 +496,22 @@
 //! Enables the use of [serde] version 1. Mainly this allows the value of
 //! key-value pairs to be structured.
 //!
+//! The JSON based formats log structured values as JSON objects and arrays.
+//! The logfmt based formats flatten them into multiple key-value pairs using
+//! dotted keys, e.g. `user.id=1 user.name="Thomas"`, using the index as key for
+//! elements of sequences, e.g. `ids.0=1 ids.1=2`. The journald, syslog (RFC
+//! 5424), OpenTelemetry and MessagePack formats don't support structured
+//! values, they log them using their `Display` implementation.
+//!
 //! [serde]: https://crates.io/crates/serde
 //!
+//! ## Gzip feature
+//!
+//! Enables compression of rotated log files using gzip, see
+//! [`Rotation::with_compression`].
+//!
+//! [`Rotation::with_compression`]: sink::Rotation::with_compression
+//!
 //! # Examples
 //!
 //! ```
@@ -295,15 +561,28 @@
 
 use std::cell::RefCell;
 use std::io::{self, IoSlice, Write};
-use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard};
 
 use log::{kv, LevelFilter, Log, Metadata, Record};
 
-mod format;
+pub mod format;
 use format::{Buffer, Format, BUFS_SIZE};
 
 mod config;
-pub use config::Config;
+pub use config::{Config, FlushGuard};
+
+mod handle;
+pub use handle::LoggerHandle;
+
+mod fanout;
+pub use fanout::Fanout;
+
+pub mod sink;
+use sink::Sink;
+
+#[cfg(unix)]
+mod signal;
 
 #[cfg(feature = "timestamp")]
 mod timestamp;
@@ -337,29 +616,154 @@ macro_rules! request {
     )
 }
 
+/// Reopen all log files.
+///
+/// This reopens all [`LogFile`]s opened by path, the next time a record is
+/// written to them. This can be used in combination with external log rotation
+/// tools such as `logrotate(8)`, which move the log file, after which the
+/// application should start writing to a new file. Records are not lost or
+/// interleaved while the file is swapped.
+///
+/// Also see [`Config::with_reopen_on_sighup`] to reopen the files on `SIGHUP`.
+///
+/// [`LogFile`]: sink::LogFile
+pub fn reopen() {
+    sink::file::reopen();
+}
+
 // Not part of the API. Only here for use in the `request!` macro.
 #[doc(hidden)]
 pub use log as _log;
 
 /// Our `Log` implementation.
 struct Logger<F, Kvs> {
-    /// The filter used to determine what messages to log.
-    filter: LevelFilter,
-    /// `add_loc` argument to `Format::format`.
-    add_loc: bool,
-    /// What logging targets to log.
-    targets: Targets,
+    /// The filter used to determine what messages to log, shared with
+    /// [`LoggerHandle`].
+    filter: SharedFilter,
+    /// `add_loc` argument to `Format::format`, if `None` it's enabled if the
+    /// debug (or lower) messages are enabled.
+    add_loc: Option<bool>,
     /// Key-values supplied for all logs.
     kvs: Kvs,
-    format: PhantomData<F>,
+    /// Format used to format the records.
+    format: F,
+    /// Output for regular messages.
+    output: Box<dyn Sink>,
+    /// Output for requests.
+    request_output: Box<dyn Sink>,
+}
+
+/// [`Filter`] shared between the logger and its handles.
+type SharedFilter = Arc<FilterLock>;
+
+/// [`Filter`] behind a lock, with its maximum severity available without
+/// taking the lock.
+#[derive(Debug)]
+struct FilterLock {
+    filter: RwLock<Filter>,
+    /// [`Filter::max_level`] as `usize`, this is checked before taking the
+    /// lock so that messages that are never logged don't have to take it.
+    max_level: AtomicUsize,
+    /// [`Filter::level`] as `usize`, used to determine whether or not to add
+    /// the location of the record.
+    level: AtomicUsize,
+}
+
+impl FilterLock {
+    fn new(filter: Filter) -> FilterLock {
+        FilterLock {
+            max_level: AtomicUsize::new(filter.max_level() as usize),
+            level: AtomicUsize::new(filter.level as usize),
+            filter: RwLock::new(filter),
+        }
+    }
+
+    /// Returns `false` if the record with `metadata` is never logged, without
+    /// taking the lock.
+    fn may_log(&self, metadata: &Metadata) -> bool {
+        metadata.level() as usize <= self.max_level.load(Ordering::Relaxed)
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Filter> {
+        self.filter.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Apply `change` to the filter.
+    fn update<C>(&self, change: C)
+    where
+        C: FnOnce(&mut Filter),
+    {
+        let mut filter = self.filter.write().unwrap_or_else(PoisonError::into_inner);
+        change(&mut filter);
+        self.max_level
+            .store(filter.max_level() as usize, Ordering::Relaxed);
+        self.level.store(filter.level as usize, Ordering::Relaxed);
+    }
+}
+
+/// Severity filter, with optional per-target severities, and the targets to
+/// log.
+#[derive(Debug, Eq, PartialEq)]
+struct Filter {
+    /// Severity for targets without a directive.
+    level: LevelFilter,
+    /// Per-target severities, sorted by target length (longest first).
+    directives: Box<[(Box<str>, LevelFilter)]>,
+    /// What logging targets to log.
+    targets: Targets,
+}
+
+impl Filter {
+    /// Filter that uses `level` for all targets.
+    fn new(level: LevelFilter) -> Filter {
+        Filter::with_directives(level, Vec::new())
+    }
+
+    /// Filter that uses `level` for all targets, except for the targets in
+    /// `directives`.
+    fn with_directives(level: LevelFilter, mut directives: Vec<(Box<str>, LevelFilter)>) -> Filter {
+        // Sort by length so that the first match is the most specific one.
+        directives.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Filter {
+            level,
+            directives: directives.into_boxed_slice(),
+            targets: Targets {
+                only: Box::new([]),
+                exclude: Box::new([]),
+            },
+        }
+    }
+
+    /// Returns `true` if the record with `metadata` should be logged.
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.level(metadata.target()) >= metadata.level()
+            && self.targets.should_log(metadata.target())
+    }
+
+    /// Returns the severity to use for `target`, using the directive with
+    /// the longest matching prefix.
+    fn level(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .find(|(prefix, _)| target.starts_with(&**prefix))
+            .map_or(self.level, |(_, level)| *level)
+    }
+
+    /// Returns the most verbose severity of all targets.
+    fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .fold(self.level, |max, (_, level)| max.max(*level))
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
-enum Targets {
-    /// Log all targets.
-    All,
-    /// Only log certain targets.
-    Only(Box<[Box<str>]>),
+struct Targets {
+    /// Only log targets that start with one these prefixes, or all targets if
+    /// empty.
+    only: Box<[Box<str>]>,
+    /// Never log targets that start with one these prefixes.
+    exclude: Box<[Box<str>]>,
 }
 
 impl Targets {
@@ -369,17 +773,15 @@ impl Targets {
             // Always log requests and panics.
             return true;
         }
-        match self {
-            Targets::All => true, // All targets should be logged.
-            Targets::Only(targets) => {
-                // Log all targets that start with an allowed target. This way
-                // we can just use `LOG_TARGET=my_crate`, rather then
-                // `LOG_TARGET=my_crate::module1,my_crate::module2` etc.
-                targets
-                    .iter()
-                    .any(|log_target| target.starts_with(&**log_target))
-            }
+        // Log all targets that start with an allowed target, and don't start
+        // with an excluded target. This way we can just use
+        // `LOG_TARGET=my_crate`, rather then
+        // `LOG_TARGET=my_crate::module1,my_crate::module2` etc.
+        let starts_with = |prefix: &str| target.starts_with(prefix);
+        if self.exclude.iter().map(|prefix| &**prefix).any(starts_with) {
+            return false;
         }
+        self.only.is_empty() || self.only.iter().map(|prefix| &**prefix).any(starts_with)
     }
 }
 
@@ -389,25 +791,41 @@ where
     Kvs: kv::Source + Sync + Send,
 {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        self.filter >= metadata.level() && self.targets.should_log(metadata.target())
+        self.filter.may_log(metadata) && self.filter.read().enabled(metadata)
     }
 
     fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            log::<F, Kvs>(record, &self.kvs, self.add_loc);
+        if !self.enabled(record.metadata()) {
+            return;
         }
+        let add_loc = self.add_loc.unwrap_or_else(|| {
+            self.filter.level.load(Ordering::Relaxed) >= LevelFilter::Debug as usize
+        });
+        let output = match record.target() {
+            REQUEST_TARGET => &*self.request_output,
+            _ => &*self.output,
+        };
+        log(&self.format, output, record, &self.kvs, add_loc);
     }
 
     fn flush(&self) {
-        // Can't flush standard error/out.
+        // Nothing we can do if flushing fails.
+        let _ = self.output.flush();
+        let _ = self.request_output.flush();
     }
 }
 
 /// The actual logging of a record.
 #[allow(clippy::single_match_else)]
-fn log<F: Format, Kvs: kv::Source>(record: &Record, kvs: &Kvs, add_loc: bool) {
-    // Thread local buffer for logging. This way we only lock standard out/error
-    // for a single writev call and don't create half written logs.
+fn log<F: Format, Kvs: kv::Source>(
+    format: &F,
+    output: &dyn Sink,
+    record: &Record,
+    kvs: &Kvs,
+    add_loc: bool,
+) {
+    // Thread local buffer for logging. This way we only lock the output for a
+    // single write call and don't create half written logs.
     thread_local! {
         static BUF: RefCell<Buffer> = RefCell::new(Buffer::new());
     }
@@ -417,35 +835,28 @@ fn log<F: Format, Kvs: kv::Source>(record: &Record, kvs: &Kvs, add_loc: bool) {
         match buf.try_borrow_mut() {
             Ok(mut buf) => {
                 // NOTE: keep in sync with the `Err` branch below.
-                let bufs = F::format(&mut bufs, &mut buf, record, kvs, add_loc);
-                match record.target() {
-                    REQUEST_TARGET => write_once(stdout(), bufs),
-                    _ => write_once(stderr(), bufs),
-                }
-                .unwrap_or_else(log_failure);
+                let bufs = format.format(&mut bufs, &mut buf, record, kvs, add_loc);
+                output.write_record(bufs).unwrap_or_else(log_failure);
             }
             Err(_) => {
                 // NOTE: We only get to this branch if we're panicking while
-                // calling `F::format`, e.g. when a `fmt::Display` impl in the
+                // calling `Format::format`, e.g. when a `fmt::Display` impl in the
                 // `record` panics, and the `log-panic` feature is enabled which
                 // calls `error!` and in turn this function again, while still
                 // borrowing `BUF`.
                 let mut buf = Buffer::new();
                 // NOTE: keep in sync with the `Ok` branch above.
-                let bufs = F::format(&mut bufs, &mut buf, record, kvs, add_loc);
-                match record.target() {
-                    REQUEST_TARGET => write_once(stdout(), bufs),
-                    _ => write_once(stderr(), bufs),
-                }
-                .unwrap_or_else(log_failure);
+                let bufs = format.format(&mut bufs, &mut buf, record, kvs, add_loc);
+                output.write_record(bufs).unwrap_or_else(log_failure);
             }
         }
     });
 }
 
-/// Write the entire `buf`fer into the `output` or return an error.
+/// Write the entire `buf`fer into the `output` using a single write call or
+/// return an error.
 #[inline]
-fn write_once<W>(mut output: W, bufs: &[IoSlice]) -> io::Result<()>
+pub(crate) fn write_once<W>(mut output: W, bufs: &[IoSlice]) -> io::Result<()>
 where
     W: Write,
 {
@@ -483,9 +894,9 @@ fn log_failure(err: io::Error) {
 // to implement `io::Write`.
 
 #[cfg(test)]
-use self::test_instruments::{stderr, stdout, LOG_OUTPUT};
+pub(crate) use self::test_instruments::{stderr, stdout, LOG_OUTPUT};
 #[cfg(not(test))]
-use std::io::{stderr, stdout};
+pub(crate) use std::io::{stderr, stdout};
 
 // The testing variant of the functions.
 
@@ -514,14 +925,16 @@ mod test_instruments {
         }
 
         fn flush(&mut self) -> io::Result<()> {
-            unreachable!()
+            Ok(())
         }
     }
 
     impl Drop for LogOutput {
         fn drop(&mut self) {
             let buf = take(&mut self.inner);
-            LOG_OUTPUT.lock().unwrap().push(buf);
+            if !buf.is_empty() {
+                LOG_OUTPUT.lock().unwrap().push(buf);
+            }
         }
     }
 
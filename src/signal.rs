@@ -0,0 +1,140 @@
+//! Unix signal handling.
+
+use std::io::{self, Read};
+use std::mem::MaybeUninit;
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::net::UnixStream;
+use std::ptr;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::thread;
+
+use log::LevelFilter;
+
+use crate::LoggerHandle;
+
+/// Install `handler` for `signal`.
+///
+/// The `handler` must be async-signal-safe.
+pub(crate) fn install(signal: libc::c_int, handler: extern "C" fn(libc::c_int)) -> io::Result<()> {
+    // SAFETY: all zeroes is a valid `sigaction`.
+    let mut action: libc::sigaction = unsafe { MaybeUninit::zeroed().assume_init() };
+    action.sa_sigaction = handler as libc::sighandler_t;
+    action.sa_flags = libc::SA_RESTART;
+    // SAFETY: `sa_mask` is a valid pointer.
+    if unsafe { libc::sigemptyset(&mut action.sa_mask) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `action` is fully initialised.
+    if unsafe { libc::sigaction(signal, &action, ptr::null_mut()) } == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Signal handler for `SIGHUP`, see [`crate::reopen`].
+pub(crate) extern "C" fn reopen_handler(_: libc::c_int) {
+    crate::sink::file::reopen();
+}
+
+/// Write end of the pipe used by [`level_handler`], or -1 if not installed.
+static LEVEL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Install the signal handlers for `SIGUSR1` and `SIGUSR2` that change the
+/// severity of `handle`.
+///
+/// As we can't change the severity (or log) from within a signal handler the
+/// handler writes the signal to a pipe, which is read by a separate thread that
+/// changes the severity.
+pub(crate) fn install_level_handlers(handle: LoggerHandle) -> io::Result<()> {
+    let (receiver, sender) = UnixStream::pair()?;
+    // Never block in the signal handler, if the pipe is full the signal is
+    // simply dropped.
+    sender.set_nonblocking(true)?;
+    let _ = thread::Builder::new()
+        .name("std-logger-signals".to_owned())
+        .spawn(move || change_level(&handle, receiver))?;
+    // NOTE: the sender is leaked, it's used for the remainder of the process.
+    LEVEL_PIPE.store(sender.into_raw_fd(), Ordering::Release);
+    install(libc::SIGUSR1, level_handler)?;
+    install(libc::SIGUSR2, level_handler)
+}
+
+/// Signal handler for `SIGUSR1` and `SIGUSR2`.
+extern "C" fn level_handler(signal: libc::c_int) {
+    let fd = LEVEL_PIPE.load(Ordering::Acquire);
+    if fd != -1 {
+        let byte = if signal == libc::SIGUSR1 { b'+' } else { b'-' };
+        // `write(2)` can overwrite `errno`, which the interrupted thread
+        // could be about to read.
+        let errno = errno();
+        // SAFETY: `errno` is valid for the current thread.
+        let saved = unsafe { *errno };
+        // SAFETY: `byte` is valid for a single byte. Nothing we can do if the
+        // write fails.
+        let _ = unsafe { libc::write(fd, ptr::addr_of!(byte).cast(), 1) };
+        // SAFETY: see above.
+        unsafe { *errno = saved };
+    }
+}
+
+/// Returns a pointer to `errno` of the current thread.
+fn errno() -> *mut libc::c_int {
+    // SAFETY: these functions are always safe to call.
+    #[cfg(any(target_os = "linux", target_os = "emscripten", target_os = "redox"))]
+    unsafe {
+        libc::__errno_location()
+    }
+    #[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))]
+    unsafe {
+        libc::__errno()
+    }
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    ))]
+    unsafe {
+        libc::__error()
+    }
+    #[cfg(any(target_os = "solaris", target_os = "illumos"))]
+    unsafe {
+        libc::___errno()
+    }
+}
+
+/// Change the severity of `handle` for each signal received on `receiver`.
+fn change_level(handle: &LoggerHandle, mut receiver: UnixStream) {
+    let mut buf = [0; 16];
+    loop {
+        let n = match receiver.read(&mut buf) {
+            Ok(0) => return,
+            Ok(n) => n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return,
+        };
+        for byte in &buf[..n] {
+            change_level_once(handle, *byte);
+        }
+    }
+}
+
+/// Increase the severity of each destination of `handle` if `byte` is `+`,
+/// decrease it otherwise.
+fn change_level_once(handle: &LoggerHandle, byte: u8) {
+    let new_level = handle.change_level(|level| match (byte, level) {
+        (b'+', LevelFilter::Off) => LevelFilter::Error,
+        (b'+', LevelFilter::Error) => LevelFilter::Warn,
+        (b'+', LevelFilter::Warn) => LevelFilter::Info,
+        (b'+', LevelFilter::Info) => LevelFilter::Debug,
+        (b'+', LevelFilter::Debug | LevelFilter::Trace) => LevelFilter::Trace,
+        (_, LevelFilter::Trace) => LevelFilter::Debug,
+        (_, LevelFilter::Debug) => LevelFilter::Info,
+        (_, LevelFilter::Info) => LevelFilter::Warn,
+        (_, LevelFilter::Warn | LevelFilter::Error | LevelFilter::Off) => LevelFilter::Error,
+    });
+    if let Some(level) = new_level.to_level() {
+        log::log!(level, "changed log level to {new_level}");
+    }
+}
@@ -0,0 +1,308 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::io::{self, IoSlice};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+use crate::sink::{concat, report, Sink};
+
+/// Default maximum number of records in the queue.
+const DEFAULT_CAPACITY: usize = 1024;
+
+thread_local! {
+    /// Set on the writer threads of [`Background`] sinks.
+    static WRITER_THREAD: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Returns `true` if this is the writer thread of a [`Background`] sink.
+#[cfg(feature = "log-panic")]
+pub(crate) fn is_writer_thread() -> bool {
+    WRITER_THREAD.with(Cell::get)
+}
+
+/// Sink that writes records to another sink on a background thread.
+///
+/// Records are formatted on the calling thread and copied into the queue, a
+/// dedicated writer thread writes them to the underlying sink. The queue is
+/// bounded, what happens when it's full is determined by the [`Overflow`]
+/// policy.
+///
+/// When records are dropped, either because the queue is full or because
+/// writing them failed, the writer thread reports the number of dropped
+/// records on standard error. This is written directly, rather than logged, so
+/// that it's not filtered out by the severity. The total number of dropped
+/// records can be retrieved using [`Background::dropped`].
+///
+/// Calling [`flush`] waits until all queued records are written and then
+/// flushes the underlying sink. When called on the writer thread itself it
+/// only flushes the underlying sink.
+///
+/// If the underlying sink panics the writer thread stops, all records in the
+/// queue and those logged afterwards are dropped.
+///
+/// See [`Config::with_async`] to use it for both regular messages and requests.
+///
+/// [`flush`]: Sink::flush
+/// [`Config::with_async`]: crate::Config::with_async
+#[derive(Debug)]
+pub struct Background {
+    shared: Arc<Shared>,
+    thread: Option<thread::JoinHandle<()>>,
+    thread_id: thread::ThreadId,
+}
+
+/// What to do when the queue of a [`Background`] sink is full.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Overflow {
+    /// Block the logging thread until there is space in the queue.
+    #[default]
+    Block,
+    /// Drop the record that is being logged.
+    DropNewest,
+    /// Drop the oldest record in the queue to make space for the record that
+    /// is being logged.
+    DropOldest,
+}
+
+#[derive(Debug)]
+struct Shared {
+    sink: Box<dyn Sink>,
+    capacity: usize,
+    overflow: Overflow,
+    queue: Mutex<Queue>,
+    /// Signalled when records are added to the queue, or on shutdown.
+    not_empty: Condvar,
+    /// Signalled when the writer thread has taken records from the queue.
+    not_full: Condvar,
+    /// Signalled when the writer thread is done writing its records.
+    idle: Condvar,
+    /// Total number of dropped records.
+    dropped: AtomicU64,
+}
+
+#[derive(Debug)]
+struct Queue {
+    records: VecDeque<Box<[u8]>>,
+    /// Number of records dropped since the last report.
+    dropped: u64,
+    /// Whether or not the writer thread is writing records.
+    writing: bool,
+    shutdown: bool,
+    /// Set when the writer thread stopped, because the sink panicked.
+    stopped: bool,
+}
+
+impl Background {
+    /// Write records to `sink` on a background thread, using a queue of 1024
+    /// records that blocks when full.
+    pub fn new<S>(sink: S) -> Background
+    where
+        S: Sink + 'static,
+    {
+        Background::with_capacity(sink, DEFAULT_CAPACITY, Overflow::Block)
+    }
+
+    /// Write records to `sink` on a background thread, using a queue of
+    /// `capacity` records and the `overflow` policy.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `capacity` is zero or if the writer thread can't be
+    /// spawned.
+    pub fn with_capacity<S>(sink: S, capacity: usize, overflow: Overflow) -> Background
+    where
+        S: Sink + 'static,
+    {
+        assert!(capacity != 0, "queue capacity must be larger than zero");
+        let shared = Arc::new(Shared {
+            sink: Box::new(sink),
+            capacity,
+            overflow,
+            queue: Mutex::new(Queue {
+                records: VecDeque::with_capacity(capacity),
+                dropped: 0,
+                writing: false,
+                shutdown: false,
+                stopped: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            idle: Condvar::new(),
+            dropped: AtomicU64::new(0),
+        });
+        let s = shared.clone();
+        let thread = thread::Builder::new()
+            .name("std-logger".to_owned())
+            .spawn(move || writer(&s))
+            .expect("failed to spawn log writer thread");
+        Background {
+            shared,
+            thread_id: thread.thread().id(),
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns the total number of dropped records.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Sink for Background {
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        let record = Box::from(&*concat(bufs));
+        let shared = &*self.shared;
+        let mut queue = shared.lock();
+        if queue.records.len() >= shared.capacity {
+            match shared.overflow {
+                // The underlying sink could log on the writer thread, which
+                // can't wait on itself.
+                Overflow::Block if thread::current().id() == self.thread_id => {}
+                Overflow::Block => {
+                    while queue.records.len() >= shared.capacity {
+                        queue = shared
+                            .not_full
+                            .wait(queue)
+                            .unwrap_or_else(PoisonError::into_inner);
+                    }
+                }
+                Overflow::DropNewest => {
+                    queue.drop_record(shared);
+                    return Ok(());
+                }
+                Overflow::DropOldest => {
+                    let _ = queue.records.pop_front();
+                    queue.drop_record(shared);
+                }
+            }
+        }
+        if queue.stopped {
+            queue.drop_record(shared);
+            return Ok(());
+        }
+        queue.records.push_back(record);
+        drop(queue);
+        shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let shared = &*self.shared;
+        // The writer thread can't wait on itself, e.g. when the sink panics
+        // and the panic hook flushes the logger.
+        if thread::current().id() != self.thread_id {
+            let mut queue = shared.lock();
+            while !queue.records.is_empty() || queue.writing {
+                queue = shared
+                    .idle
+                    .wait(queue)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+        shared.sink.flush()
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.shared.sink.is_terminal()
+    }
+}
+
+impl Drop for Background {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.not_empty.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Queue {
+    fn drop_record(&mut self, shared: &Shared) {
+        self.dropped += 1;
+        let _ = shared.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Writer thread, writes all records in the queue to the sink until the
+/// [`Background`] sink is dropped.
+fn writer(shared: &Shared) {
+    WRITER_THREAD.with(|writer| writer.set(true));
+    let mut writing = Writing {
+        shared,
+        records: VecDeque::new(),
+    };
+    loop {
+        let mut queue = shared.lock();
+        queue.writing = false;
+        shared.idle.notify_all();
+        while queue.records.is_empty() && !queue.shutdown {
+            queue = shared
+                .not_empty
+                .wait(queue)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        if queue.records.is_empty() {
+            // Shutting down and all records are written.
+            return;
+        }
+        writing.records.extend(queue.records.drain(..));
+        queue.writing = true;
+        drop(queue);
+        shared.not_full.notify_all();
+
+        let mut failed = 0;
+        // NOTE: the record is only removed after it's written, so that it's
+        // counted as dropped if the sink panics.
+        while let Some(record) = writing.records.front() {
+            if shared.sink.write_record(&[IoSlice::new(record)]).is_err() {
+                failed += 1;
+            }
+            let _ = writing.records.pop_front();
+        }
+
+        let mut queue = shared.lock();
+        if failed != 0 {
+            let _ = shared.dropped.fetch_add(failed, Ordering::Relaxed);
+        }
+        let dropped = queue.dropped + failed;
+        queue.dropped = 0;
+        drop(queue);
+        if dropped != 0 {
+            report(format_args!("dropped {dropped} log records"));
+        }
+    }
+}
+
+/// Records taken from the queue by the writer thread.
+///
+/// When dropped, i.e. when the writer thread returns or the sink panics, this
+/// marks the writer thread as stopped and drops all records that are not yet
+/// written.
+struct Writing<'a> {
+    shared: &'a Shared,
+    records: VecDeque<Box<[u8]>>,
+}
+
+impl Drop for Writing<'_> {
+    fn drop(&mut self) {
+        let shared = self.shared;
+        let mut queue = shared.lock();
+        queue.stopped = true;
+        queue.writing = false;
+        let dropped = (self.records.len() + queue.records.len()) as u64;
+        queue.records.clear();
+        let _ = shared.dropped.fetch_add(dropped, Ordering::Relaxed);
+        drop(queue);
+        // Wake up any threads waiting on the writer thread.
+        shared.not_full.notify_all();
+        shared.idle.notify_all();
+    }
+}
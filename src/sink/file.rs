@@ -0,0 +1,128 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, IoSlice, IsTerminal};
+#[cfg(unix)]
+use std::os::unix::io::OwnedFd;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{PoisonError, RwLock};
+
+use crate::sink::{report_once, Sink};
+use crate::write_once;
+
+/// Incremented every time the log files should be reopened, see [`reopen`].
+static REOPEN_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Reopen all log files opened by path.
+///
+/// See [`crate::reopen`].
+pub(crate) fn reopen() {
+    // NOTE: this is called from a signal handler, so it must be
+    // async-signal-safe.
+    let _ = REOPEN_GENERATION.fetch_add(1, Ordering::AcqRel);
+}
+
+/// Sink that writes to a file.
+///
+/// Each record is written using a single `writev(2)` call. When the file is
+/// opened in append mode, which [`LogFile::open`] does, this means that records
+/// are not interleaved, even if multiple processes write to the same file.
+///
+/// Files opened using [`LogFile::open`] are reopened after a call to
+/// [`reopen`], which makes it possible to use external log rotation tools such
+/// as `logrotate(8)`. Also see [`Config::with_reopen_on_sighup`]. If reopening
+/// the file fails the sink keeps writing to the old file and tries again on the
+/// next record, the first failure is reported on standard error.
+///
+/// [`reopen`]: crate::reopen
+/// [`Config::with_reopen_on_sighup`]: crate::Config::with_reopen_on_sighup
+#[derive(Debug)]
+pub struct LogFile {
+    file: RwLock<File>,
+    /// Path used to reopen the file, `None` if the file wasn't opened by path.
+    path: Option<PathBuf>,
+    /// Value of [`REOPEN_GENERATION`] when `file` was opened.
+    generation: AtomicUsize,
+    /// Whether or not a failure to reopen the file was reported.
+    reported: AtomicBool,
+}
+
+impl LogFile {
+    /// Open the file at `path` for appending, creating it if it doesn't exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<LogFile> {
+        let path = path.as_ref().to_path_buf();
+        let generation = REOPEN_GENERATION.load(Ordering::Acquire);
+        let file = open(&path)?;
+        Ok(LogFile {
+            file: RwLock::new(file),
+            path: Some(path),
+            generation: AtomicUsize::new(generation),
+            reported: AtomicBool::new(false),
+        })
+    }
+
+    /// Reopen the file if [`reopen`] was called since we last opened it.
+    ///
+    /// If opening the new file fails we keep the old file and leave the
+    /// generation as is, so that we try again on the next call.
+    fn maybe_reopen(&self, path: &Path) {
+        let generation = REOPEN_GENERATION.load(Ordering::Acquire);
+        if self.generation.load(Ordering::Acquire) == generation {
+            return;
+        }
+
+        // Holding the write lock ensures no other thread is writing to the old
+        // file while we swap it.
+        let mut file = self.file.write().unwrap_or_else(PoisonError::into_inner);
+        // Another thread could have reopened the file while we were waiting on
+        // the lock.
+        if self.generation.load(Ordering::Acquire) != generation {
+            match open(path) {
+                Ok(f) => {
+                    *file = f;
+                    self.generation.store(generation, Ordering::Release);
+                }
+                Err(err) => report_once(
+                    &self.reported,
+                    format_args!("failed to reopen log file '{}': {err}", path.display()),
+                ),
+            }
+        }
+    }
+}
+
+impl From<File> for LogFile {
+    fn from(file: File) -> LogFile {
+        LogFile {
+            file: RwLock::new(file),
+            path: None,
+            generation: AtomicUsize::new(0),
+            reported: AtomicBool::new(false),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<OwnedFd> for LogFile {
+    fn from(fd: OwnedFd) -> LogFile {
+        LogFile::from(File::from(fd))
+    }
+}
+
+impl Sink for LogFile {
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        if let Some(path) = &self.path {
+            self.maybe_reopen(path);
+        }
+        let file = self.file.read().unwrap_or_else(PoisonError::into_inner);
+        write_once(&*file, bufs)
+    }
+
+    fn is_terminal(&self) -> bool {
+        let file = self.file.read().unwrap_or_else(PoisonError::into_inner);
+        file.is_terminal()
+    }
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
@@ -0,0 +1,131 @@
+use std::io::{self, IoSlice};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::sink::syslog::resolve;
+use crate::sink::{concat, Dropped, Sink};
+
+/// Default maximum size of a single datagram, recommended for sending over the
+/// internet.
+const DEFAULT_CHUNK_SIZE: usize = 1420;
+
+/// Size of the header of a chunk: magic bytes (2), message id (8), sequence
+/// number (1) and sequence count (1).
+const CHUNK_HEADER_SIZE: usize = 12;
+
+/// Maximum number of chunks per message.
+const MAX_CHUNKS: usize = 128;
+
+/// Sink that sends records to Graylog using GELF over UDP.
+///
+/// This should be used in combination with the [`Gelf`] format, see
+/// [`Config::gelf`].
+///
+/// Each record is send as a single datagram, without the trailing new line. If
+/// a record is larger than the chunk size it's split into multiple chunks,
+/// following the GELF chunking protocol.
+///
+/// Records that require more than 128 chunks, or that can't be send, e.g.
+/// because Graylog is down, are dropped. The first failure is reported on
+/// standard error, the total number of dropped records can be retrieved using
+/// [`Gelf::dropped`].
+///
+/// [`Gelf`]: crate::format::Gelf
+/// [`Config::gelf`]: crate::Config::gelf
+#[derive(Debug)]
+pub struct Gelf {
+    socket: UdpSocket,
+    chunk_size: usize,
+    /// Used to create unique message ids for chunked messages.
+    message_id: AtomicU64,
+    dropped: Dropped,
+}
+
+impl Gelf {
+    /// Send records to `address` using UDP.
+    pub fn udp<A: ToSocketAddrs>(address: A) -> io::Result<Gelf> {
+        let address = resolve(address)?;
+        let local: SocketAddr = if address.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(address)?;
+        // Start the message ids at a value unique to this process, so that
+        // chunks of different processes don't get mixed up.
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Ok(Gelf {
+            socket,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            message_id: AtomicU64::new(now ^ (u64::from(process::id()) << 32)),
+            dropped: Dropped::default(),
+        })
+    }
+
+    /// Set the maximum size of a single datagram, defaults to 1420 bytes.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `size` is not larger than the 12 byte chunk header.
+    pub fn with_chunk_size(self, size: usize) -> Gelf {
+        assert!(
+            size > CHUNK_HEADER_SIZE,
+            "chunk size must be larger than the chunk header"
+        );
+        Gelf {
+            chunk_size: size,
+            ..self
+        }
+    }
+
+    /// Returns the total number of dropped records.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.count()
+    }
+
+    fn send(&self, record: &[u8]) -> io::Result<()> {
+        if record.len() <= self.chunk_size {
+            return self.socket.send(record).map(|_| ());
+        }
+
+        let chunks = record.chunks(self.chunk_size - CHUNK_HEADER_SIZE);
+        let count = chunks.len();
+        if count > MAX_CHUNKS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "GELF message too large",
+            ));
+        }
+        let id = self
+            .message_id
+            .fetch_add(1, Ordering::Relaxed)
+            .to_be_bytes();
+        let mut datagram = Vec::with_capacity(self.chunk_size);
+        for (n, chunk) in chunks.enumerate() {
+            datagram.clear();
+            datagram.extend_from_slice(&[0x1e, 0x0f]);
+            datagram.extend_from_slice(&id);
+            #[allow(clippy::cast_possible_truncation)] // `MAX_CHUNKS` fits in u8.
+            datagram.extend_from_slice(&[n as u8, count as u8]);
+            datagram.extend_from_slice(chunk);
+            let _ = self.socket.send(&datagram)?;
+        }
+        Ok(())
+    }
+}
+
+impl Sink for Gelf {
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        let record = concat(bufs);
+        let record = record.strip_suffix(b"\n").unwrap_or(&record);
+        if let Err(err) = self.send(record) {
+            self.dropped.record("Graylog", &err);
+        }
+        Ok(())
+    }
+}
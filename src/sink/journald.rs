@@ -0,0 +1,143 @@
+use std::io::{self, IoSlice};
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::sink::{concat, Dropped, Sink};
+
+/// Path to the journald socket.
+const JOURNALD_PATH: &str = "/run/systemd/journal/socket";
+
+/// Sink that sends records to systemd-journald, using its native protocol.
+///
+/// This should be used in combination with the [`Journald`] format, see
+/// [`Config::journald`].
+///
+/// Each record is send as a single datagram. On Linux records that are too
+/// large for a single datagram are written to a sealed memfd, which is send to
+/// journald instead, the same way `sd_journal_send(3)` does.
+///
+/// If a record can't be send, e.g. because journald isn't running, the record
+/// is dropped. The first failure is reported on standard error, the total
+/// number of dropped records can be retrieved using [`Journald::dropped`].
+///
+/// [`Journald`]: crate::format::Journald
+/// [`Config::journald`]: crate::Config::journald
+#[derive(Debug)]
+pub struct Journald {
+    path: PathBuf,
+    /// Created on first use.
+    socket: OnceLock<io::Result<UnixDatagram>>,
+    dropped: Dropped,
+}
+
+impl Journald {
+    /// Send records to journald's default socket
+    /// (`/run/systemd/journal/socket`).
+    pub fn new() -> Journald {
+        Journald::with_path(JOURNALD_PATH)
+    }
+
+    /// Send records to the socket at `path`.
+    pub fn with_path<P: AsRef<Path>>(path: P) -> Journald {
+        Journald {
+            path: path.as_ref().to_path_buf(),
+            socket: OnceLock::new(),
+            dropped: Dropped::default(),
+        }
+    }
+
+    /// Returns the total number of dropped records.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.count()
+    }
+
+    fn send(&self, record: &[u8]) -> io::Result<()> {
+        let socket = match self.socket.get_or_init(UnixDatagram::unbound) {
+            Ok(socket) => socket,
+            Err(err) => return Err(io::Error::new(err.kind(), err.to_string())),
+        };
+        match socket.send_to(record, &self.path) {
+            Ok(_) => Ok(()),
+            #[cfg(target_os = "linux")]
+            Err(ref err) if matches!(err.raw_os_error(), Some(libc::EMSGSIZE | libc::ENOBUFS)) => {
+                send_memfd(&self.path, record)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl Default for Journald {
+    fn default() -> Journald {
+        Journald::new()
+    }
+}
+
+impl Sink for Journald {
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        if let Err(err) = self.send(&concat(bufs)) {
+            self.dropped.record("journald", &err);
+        }
+        Ok(())
+    }
+}
+
+/// Send `record` to the journald socket at `path` by writing it to a sealed
+/// memfd and sending the file descriptor.
+#[cfg(target_os = "linux")]
+fn send_memfd(path: &Path, record: &[u8]) -> io::Result<()> {
+    use std::fs::File;
+    use std::io::Write;
+    use std::mem::{size_of, MaybeUninit};
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::ptr;
+
+    let flags = libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING;
+    // SAFETY: the name is a valid C string.
+    #[allow(clippy::manual_c_str_literals)] // `c""` literals require Rust 1.77.
+    let fd = unsafe { libc::memfd_create(b"journal-data\0".as_ptr().cast(), flags) };
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: we just created `fd`, so we own it.
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    file.write_all(record)?;
+    // Journald only accepts sealed memfds.
+    let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+    // SAFETY: `fd` is a valid memfd.
+    if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let socket = UnixDatagram::unbound()?;
+    socket.connect(path)?;
+    // Buffer for a single control message holding one file descriptor, using
+    // `u64` for alignment.
+    let mut control = [0u64; 4];
+    // SAFETY: `CMSG_SPACE` and `CMSG_LEN` are simple calculations.
+    let (space, len) = unsafe {
+        let size = size_of::<libc::c_int>() as libc::c_uint;
+        (libc::CMSG_SPACE(size), libc::CMSG_LEN(size))
+    };
+    debug_assert!(space as usize <= size_of::<[u64; 4]>());
+    // SAFETY: all zeroes is a valid `msghdr`.
+    let mut msg: libc::msghdr = unsafe { MaybeUninit::zeroed().assume_init() };
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = space as _;
+    // SAFETY: `msg` has room for a single control message with a file
+    // descriptor, which we fill here.
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = len as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast(), file.as_raw_fd());
+    }
+    // SAFETY: `msg` is fully initialised.
+    if unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) } == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
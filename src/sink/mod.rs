@@ -0,0 +1,297 @@
+//! Outputs, or sinks, to write log records to.
+//!
+//! By default regular messages are written to [`Stderr`] and requests to
+//! [`Stdout`]. This can be changed using [`Config::with_output`] and
+//! [`Config::with_request_output`] respectively.
+//!
+//! The following sinks are provided:
+//!  * [`Stderr`] and [`Stdout`]: standard error and out.
+//!  * [`LogFile`]: a file, opened by path or from a file descriptor.
+//!  * [`RotatingFile`]: a file that is rotated based on size and/or time.
+//!  * [`Journald`]: systemd-journald, using its native protocol.
+//!  * [`Syslog`]: a syslog daemon or collector, over a unix socket, UDP or TCP.
+//!  * [`Gelf`]: Graylog, over UDP.
+//!  * [`Writer`]: any type that implements [`io::Write`].
+//!
+//! Any sink can be wrapped in [`Background`] to write records on a background
+//! thread, see [`Config::with_async`].
+//!
+//! [`Config::with_output`]: crate::Config::with_output
+//! [`Config::with_request_output`]: crate::Config::with_request_output
+//! [`Config::with_async`]: crate::Config::with_async
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, IoSlice, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::format::BUFS_SIZE;
+use crate::{stderr, stdout, write_once};
+
+pub(crate) mod file;
+pub use file::LogFile;
+
+mod rotating;
+pub use rotating::{Interval, RotatingFile, Rotation};
+
+#[cfg(unix)]
+mod journald;
+#[cfg(unix)]
+pub use journald::Journald;
+
+mod syslog;
+pub use syslog::Syslog;
+
+mod gelf;
+pub use gelf::Gelf;
+
+pub(crate) mod background;
+pub use background::{Background, Overflow};
+
+/// Trait that defines where formatted log records are written to.
+pub trait Sink: Send + Sync {
+    /// Write a single formatted record, made up of `bufs`.
+    ///
+    /// The record should be written as a whole, i.e. it should not be
+    /// interleaved with other records.
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()>;
+
+    /// Flush any buffered records.
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Returns `true` if the sink writes to a terminal.
+    ///
+    /// This is used by [`Pretty`] to decide whether or not to use colors.
+    /// Defaults to `false`.
+    ///
+    /// [`Pretty`]: crate::format::Pretty
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Debug for dyn Sink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Sink")
+    }
+}
+
+impl<S: Sink + ?Sized> Sink for Box<S> {
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        (**self).write_record(bufs)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        (**self).flush()
+    }
+
+    fn is_terminal(&self) -> bool {
+        (**self).is_terminal()
+    }
+}
+
+impl<S: Sink + ?Sized> Sink for Arc<S> {
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        (**self).write_record(bufs)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        (**self).flush()
+    }
+
+    fn is_terminal(&self) -> bool {
+        (**self).is_terminal()
+    }
+}
+
+/// Standard error.
+///
+/// Each record is written using a single `writev(2)` call.
+#[derive(Debug)]
+pub struct Stderr;
+
+impl Sink for Stderr {
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        write_once(stderr(), bufs)
+    }
+
+    fn is_terminal(&self) -> bool {
+        io::stderr().is_terminal()
+    }
+}
+
+/// Standard out.
+///
+/// Each record is written using a single `writev(2)` call.
+#[derive(Debug)]
+pub struct Stdout;
+
+impl Sink for Stdout {
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        write_once(stdout(), bufs)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        stdout().flush()
+    }
+
+    fn is_terminal(&self) -> bool {
+        io::stdout().is_terminal()
+    }
+}
+
+/// Sink for any type that implements [`io::Write`].
+///
+/// The writer is protected by a lock, which is held while writing a record, to
+/// ensure records are not interleaved. Unlike the other sinks a record may be
+/// written using multiple calls to [`Write::write_vectored`].
+pub struct Writer<W> {
+    writer: Mutex<W>,
+}
+
+impl<W> Writer<W> {
+    /// Create a new `Writer` sink.
+    pub const fn new(writer: W) -> Writer<W> {
+        Writer {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<W> Sink for Writer<W>
+where
+    W: Write + Send,
+{
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        write_all_vectored(&mut *writer, bufs)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        writer.flush()
+    }
+}
+
+impl<W> fmt::Debug for Writer<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Writer").finish_non_exhaustive()
+    }
+}
+
+/// Returns `bufs` as a single buffer, only allocating if `bufs` holds more
+/// than one non-empty buffer.
+fn concat<'a>(bufs: &'a [IoSlice<'a>]) -> Cow<'a, [u8]> {
+    let mut non_empty = bufs.iter().filter(|buf| !buf.is_empty());
+    match (non_empty.next(), non_empty.next()) {
+        (None, _) => Cow::Borrowed(&[]),
+        (Some(buf), None) => Cow::Borrowed(buf),
+        (Some(_), Some(_)) => Cow::Owned(bufs.iter().flat_map(|buf| buf.iter()).copied().collect()),
+    }
+}
+
+/// Report a failure of a sink on standard error, only if `reported` isn't set
+/// yet.
+///
+/// This is used for failures that sinks handle themselves, rather than
+/// returning an error (which panics). It's written to standard error directly,
+/// as logging it could end up in the failing sink again.
+fn report_once(reported: &AtomicBool, args: fmt::Arguments<'_>) {
+    if !reported.swap(true, Ordering::Relaxed) {
+        report(args);
+    }
+}
+
+/// Report a problem of a sink on standard error, see [`report_once`].
+fn report(args: fmt::Arguments<'_>) {
+    let _ = writeln!(io::stderr(), "std-logger: {args}");
+}
+
+/// Records dropped by a sink, because they couldn't be send.
+#[derive(Debug, Default)]
+struct Dropped {
+    /// Total number of dropped records.
+    count: AtomicU64,
+    /// Whether or not a failure was reported, see [`report_once`].
+    reported: AtomicBool,
+}
+
+impl Dropped {
+    /// Drop a record that failed to send to `sink` because of `err`.
+    fn record(&self, sink: &str, err: &io::Error) {
+        let _ = self.count.fetch_add(1, Ordering::Relaxed);
+        report_once(
+            &self.reported,
+            format_args!("failed to send log record to {sink}, dropping records: {err}"),
+        );
+    }
+
+    fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+/// Write all `bufs` into `output`, using as few calls to
+/// [`Write::write_vectored`] as possible.
+fn write_all_vectored<W>(output: &mut W, bufs: &[IoSlice<'_>]) -> io::Result<()>
+where
+    W: Write + ?Sized,
+{
+    if bufs.len() > BUFS_SIZE {
+        // Should never happen for the formats provided by this crate.
+        for buf in bufs {
+            output.write_all(buf)?;
+        }
+        return Ok(());
+    }
+
+    // Skip any empty buffers.
+    let mut slices: [&[u8]; BUFS_SIZE] = [&[]; BUFS_SIZE];
+    let mut len = 0;
+    for buf in bufs.iter().filter(|buf| !buf.is_empty()) {
+        slices[len] = buf;
+        len += 1;
+    }
+
+    let mut iovecs = [IoSlice::new(&[]); BUFS_SIZE];
+    let mut start = 0;
+    while start < len {
+        for (iovec, slice) in iovecs.iter_mut().zip(&slices[start..len]) {
+            *iovec = IoSlice::new(slice);
+        }
+        match output.write_vectored(&iovecs[..len - start]) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write entire log message",
+                ))
+            }
+            Ok(mut n) => {
+                // Remove the written bytes.
+                while n != 0 {
+                    let slice = slices[start];
+                    if slice.len() > n {
+                        slices[start] = &slice[n..];
+                        n = 0;
+                    } else {
+                        n -= slice.len();
+                        start += 1;
+                    }
+                }
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,296 @@
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, IoSlice};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+
+use crate::sink::Sink;
+use crate::write_once;
+
+/// Sink that writes to a file, rotating it based on its size and/or time.
+///
+/// When the file is rotated the current file, e.g. `app.log`, is renamed to
+/// `app.log.1`, the previously rotated `app.log.1` to `app.log.2`, etc. Only
+/// [`Rotation::with_max_files`] rotated files are kept, older files are
+/// removed. If compression is enabled the rotated files are compressed using
+/// gzip, using a `.gz` extension, e.g. `app.log.1.gz`. The thread that rotates
+/// the file also compresses it, other threads can continue logging while it
+/// does.
+///
+/// Like [`LogFile`] each record is written using a single `writev(2)` call.
+///
+/// [`LogFile`]: crate::sink::LogFile
+///
+/// # Examples
+///
+/// ```no_run
+/// use std_logger::sink::{Interval, RotatingFile, Rotation};
+///
+/// # fn main() -> std::io::Result<()> {
+/// // Rotate the file daily or when it's larger then 100 MB, whichever comes
+/// // first, keeping the logs of the last week.
+/// let rotation = Rotation::new()
+///     .with_max_size(100 * 1024 * 1024)
+///     .with_interval(Interval::Daily)
+///     .with_max_files(7);
+/// let output = RotatingFile::open("/var/log/my_app/error.log", rotation)?;
+/// std_logger::Config::logfmt().with_output(output).init();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: Rotation,
+    state: Mutex<State>,
+    /// Held while compressing a rotated file, which is done without holding
+    /// the `state` lock.
+    compressing: Mutex<()>,
+}
+
+#[derive(Debug)]
+struct State {
+    file: File,
+    /// Size of `file` in bytes.
+    size: u64,
+    /// Time at which to rotate the file, as seconds since the Unix epoch.
+    rotate_at: Option<u64>,
+}
+
+/// When to rotate a [`RotatingFile`].
+///
+/// By default files are never rotated and five rotated files are kept.
+#[derive(Clone, Debug)]
+#[must_use = "the rotation must be used in `RotatingFile::open`"]
+pub struct Rotation {
+    max_size: Option<u64>,
+    interval: Option<Interval>,
+    max_files: usize,
+    compress: bool,
+}
+
+/// Time interval at which to rotate a [`RotatingFile`].
+///
+/// All intervals are based on wall-clock time in UTC.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Interval {
+    /// Rotate at the start of every hour.
+    Hourly,
+    /// Rotate at the start of every day (midnight UTC).
+    Daily,
+}
+
+impl Interval {
+    /// Returns the length of the interval in seconds.
+    const fn secs(self) -> u64 {
+        match self {
+            Interval::Hourly => 60 * 60,
+            Interval::Daily => 24 * 60 * 60,
+        }
+    }
+
+    /// Returns the start of the next interval after `time`, as seconds since
+    /// the Unix epoch.
+    pub(crate) fn next(self, time: SystemTime) -> u64 {
+        let secs = unix_secs(time);
+        (secs / self.secs() + 1) * self.secs()
+    }
+}
+
+impl Rotation {
+    /// Create a new `Rotation`, which never rotates.
+    pub const fn new() -> Rotation {
+        Rotation {
+            max_size: None,
+            interval: None,
+            max_files: 5,
+            compress: false,
+        }
+    }
+
+    /// Rotate the file once writing a record would make it larger then
+    /// `max_size` bytes.
+    pub const fn with_max_size(self, max_size: u64) -> Rotation {
+        Rotation {
+            max_size: Some(max_size),
+            ..self
+        }
+    }
+
+    /// Rotate the file at every `interval`.
+    pub const fn with_interval(self, interval: Interval) -> Rotation {
+        Rotation {
+            interval: Some(interval),
+            ..self
+        }
+    }
+
+    /// Maximum number of rotated files to keep, defaults to five.
+    pub const fn with_max_files(self, max_files: usize) -> Rotation {
+        Rotation { max_files, ..self }
+    }
+
+    /// Compress rotated files using gzip.
+    #[cfg(feature = "gzip")]
+    pub const fn with_compression(self, enable: bool) -> Rotation {
+        Rotation {
+            compress: enable,
+            ..self
+        }
+    }
+}
+
+impl Default for Rotation {
+    fn default() -> Rotation {
+        Rotation::new()
+    }
+}
+
+impl RotatingFile {
+    /// Open the file at `path` for appending, creating it if it doesn't exist,
+    /// rotating it based on `rotation`.
+    pub fn open<P: AsRef<Path>>(path: P, rotation: Rotation) -> io::Result<RotatingFile> {
+        let path = path.as_ref().to_path_buf();
+        let file = open(&path)?;
+        let metadata = file.metadata()?;
+        // Use the modification time of an existing file to determine when to
+        // rotate, this way we rotate the file from a previous run.
+        let modified = if metadata.len() == 0 {
+            SystemTime::now()
+        } else {
+            metadata.modified().unwrap_or_else(|_| SystemTime::now())
+        };
+        let state = State {
+            file,
+            size: metadata.len(),
+            rotate_at: rotation.interval.map(|interval| interval.next(modified)),
+        };
+        Ok(RotatingFile {
+            path,
+            rotation,
+            state: Mutex::new(state),
+            compressing: Mutex::new(()),
+        })
+    }
+
+    /// Returns `true` if the file should be rotated before writing `len` bytes.
+    fn should_rotate(&self, state: &State, len: u64) -> bool {
+        if let Some(max_size) = self.rotation.max_size {
+            // Never rotate an empty file, even if the record is larger than
+            // the maximum size.
+            if state.size != 0 && state.size + len > max_size {
+                return true;
+            }
+        }
+        if let Some(rotate_at) = state.rotate_at {
+            if unix_secs(SystemTime::now()) >= rotate_at {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Rotate the file.
+    fn rotate(&self, state: &mut State) -> io::Result<()> {
+        let max_files = self.rotation.max_files;
+        if max_files == 0 {
+            remove_if_exists(&self.path)?;
+        } else {
+            let ext = if self.rotation.compress { ".gz" } else { "" };
+            remove_if_exists(&rotated_path(&self.path, max_files, ext))?;
+            for n in (1..max_files).rev() {
+                let from = rotated_path(&self.path, n, ext);
+                match fs::rename(&from, rotated_path(&self.path, n + 1, ext)) {
+                    Ok(()) => {}
+                    Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            // NOTE: the file is compressed by the caller, after releasing the
+            // lock.
+            fs::rename(&self.path, rotated_path(&self.path, 1, ""))?;
+        }
+
+        state.file = open(&self.path)?;
+        state.size = 0;
+        if let Some(interval) = self.rotation.interval {
+            state.rotate_at = Some(interval.next(SystemTime::now()));
+        }
+        Ok(())
+    }
+}
+
+impl Sink for RotatingFile {
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        let len = bufs.iter().map(|b| b.len() as u64).sum();
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut compressing = None;
+        if self.should_rotate(&state, len) {
+            // Wait for the previous rotated file to be compressed, before we
+            // rename it.
+            let guard = self
+                .compressing
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            self.rotate(&mut state)?;
+            if self.rotation.compress && self.rotation.max_files != 0 {
+                compressing = Some(guard);
+            }
+        }
+        write_once(&state.file, bufs)?;
+        state.size += len;
+        drop(state);
+
+        // Compress the rotated file without holding the lock, so that other
+        // threads can continue logging.
+        if let Some(guard) = compressing {
+            let rotated = rotated_path(&self.path, 1, "");
+            compress(&rotated, &rotated_path(&self.path, 1, ".gz"))?;
+            drop(guard);
+        }
+        Ok(())
+    }
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Returns `{path}.{n}{ext}`.
+fn rotated_path(path: &Path, n: usize, ext: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(format!(".{n}{ext}"));
+    PathBuf::from(path)
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Compress the file at `from` using gzip into `to`, removing `from`.
+#[cfg(feature = "gzip")]
+fn compress(from: &Path, to: &Path) -> io::Result<()> {
+    let mut input = File::open(from)?;
+    let output = File::create(to)?;
+    let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+    let _ = io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::remove_file(from)
+}
+
+#[cfg(not(feature = "gzip"))]
+fn compress(_: &Path, _: &Path) -> io::Result<()> {
+    // `Rotation::compress` can't be set without the gzip feature.
+    unreachable!()
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
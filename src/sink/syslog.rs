@@ -0,0 +1,235 @@
+use std::io::{self, IoSlice, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::sync::OnceLock;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::sink::{concat, Dropped, Sink};
+
+/// Path to the local syslog socket.
+#[cfg(unix)]
+const SYSLOG_PATH: &str = "/dev/log";
+
+/// Timeout for connecting to, and writing to, a TCP collector.
+const TCP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Minimum and maximum time to wait before reconnecting to a TCP collector.
+const MIN_BACKOFF: Duration = TCP_TIMEOUT;
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Sink that sends records to a syslog daemon or collector.
+///
+/// This should be used in combination with the [`Syslog`] or [`SyslogRfc3164`]
+/// format, see [`Config::syslog`].
+///
+/// Records can be send over a unix datagram socket ([`Syslog::local`] or
+/// [`Syslog::unix`]), UDP ([`Syslog::udp`]) or TCP ([`Syslog::tcp`]). When
+/// using datagrams each record is send as a single datagram, the trailing new
+/// line is not send. When using TCP records are framed using octet counting,
+/// following RFC 6587.
+///
+/// If a record can't be send, e.g. because the collector is down, the record is
+/// dropped. The first failure is reported on standard error, the total number
+/// of dropped records can be retrieved using [`Syslog::dropped`]. When the TCP
+/// connection is lost the sink reconnects, using an exponential backoff between
+/// attempts, records logged while disconnected are dropped.
+///
+/// [`Syslog`]: crate::format::Syslog
+/// [`SyslogRfc3164`]: crate::format::SyslogRfc3164
+/// [`Config::syslog`]: crate::Config::syslog
+#[derive(Debug)]
+pub struct Syslog {
+    transport: Transport,
+    dropped: Dropped,
+}
+
+#[derive(Debug)]
+enum Transport {
+    #[cfg(unix)]
+    Unix {
+        path: PathBuf,
+        /// Created on first use.
+        socket: OnceLock<io::Result<UnixDatagram>>,
+    },
+    Udp(UdpSocket),
+    Tcp {
+        address: SocketAddr,
+        connection: Mutex<Connection>,
+    },
+}
+
+#[derive(Debug)]
+struct Connection {
+    /// `None` if the previous write failed, in which case we reconnect.
+    stream: Option<TcpStream>,
+    /// Don't reconnect before this time.
+    retry_at: Option<Instant>,
+    /// Time to wait after the next failed attempt to connect.
+    backoff: Duration,
+}
+
+impl Syslog {
+    /// Send records to the local syslog daemon at `/dev/log`.
+    #[cfg(unix)]
+    pub fn local() -> Syslog {
+        Syslog::unix(SYSLOG_PATH)
+    }
+
+    /// Send records to the unix datagram socket at `path`.
+    #[cfg(unix)]
+    pub fn unix<P: AsRef<Path>>(path: P) -> Syslog {
+        Syslog {
+            transport: Transport::Unix {
+                path: path.as_ref().to_path_buf(),
+                socket: OnceLock::new(),
+            },
+            dropped: Dropped::default(),
+        }
+    }
+
+    /// Send records to `address` using UDP.
+    pub fn udp<A: ToSocketAddrs>(address: A) -> io::Result<Syslog> {
+        let address = resolve(address)?;
+        let local: SocketAddr = if address.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(address)?;
+        Ok(Syslog {
+            transport: Transport::Udp(socket),
+            dropped: Dropped::default(),
+        })
+    }
+
+    /// Send records to `address` using TCP.
+    ///
+    /// If writing to the connection fails the sink reconnects right away and
+    /// tries again. If that fails as well the record is dropped and the sink
+    /// waits before reconnecting, starting at one second and doubling after
+    /// each failed attempt up to a minute. Records logged in the meantime are
+    /// dropped.
+    ///
+    /// Records are written while holding a lock on the connection, with a
+    /// write timeout of one second. This means a stalled collector blocks all
+    /// threads that log, for up to a second per record. To avoid this use
+    /// [`Config::with_async`] to write the records on a background thread.
+    ///
+    /// [`Config::with_async`]: crate::Config::with_async
+    pub fn tcp<A: ToSocketAddrs>(address: A) -> io::Result<Syslog> {
+        let address = resolve(address)?;
+        let stream = connect(address)?;
+        Ok(Syslog {
+            transport: Transport::Tcp {
+                address,
+                connection: Mutex::new(Connection {
+                    stream: Some(stream),
+                    retry_at: None,
+                    backoff: MIN_BACKOFF,
+                }),
+            },
+            dropped: Dropped::default(),
+        })
+    }
+
+    /// Returns the total number of dropped records.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.count()
+    }
+
+    fn send(&self, record: &[u8]) -> io::Result<()> {
+        match &self.transport {
+            #[cfg(unix)]
+            Transport::Unix { path, socket } => {
+                let socket = match socket.get_or_init(UnixDatagram::unbound) {
+                    Ok(socket) => socket,
+                    Err(err) => return Err(io::Error::new(err.kind(), err.to_string())),
+                };
+                socket.send_to(record, path).map(|_| ())
+            }
+            Transport::Udp(socket) => socket.send(record).map(|_| ()),
+            Transport::Tcp {
+                address,
+                connection,
+            } => {
+                // Octet counting: `MSG-LEN SP SYSLOG-MSG`.
+                let mut itoa = itoa::Buffer::new();
+                let len = itoa.format(record.len()).as_bytes();
+                let mut frame = Vec::with_capacity(len.len() + 1 + record.len());
+                frame.extend_from_slice(len);
+                frame.push(b' ');
+                frame.extend_from_slice(record);
+
+                send_tcp(*address, connection, &frame)
+            }
+        }
+    }
+}
+
+/// Send `frame` over the TCP `connection`, (re)connecting to `address` if
+/// needed.
+///
+/// The lock isn't held while connecting, other threads drop their records in
+/// the meantime.
+fn send_tcp(address: SocketAddr, connection: &Mutex<Connection>, frame: &[u8]) -> io::Result<()> {
+    let mut conn = connection.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(stream) = &mut conn.stream {
+        if stream.write_all(frame).is_ok() {
+            return Ok(());
+        }
+        // The write failed, reconnect once right away.
+        conn.stream = None;
+    } else if conn.retry_at.is_some_and(|at| Instant::now() < at) {
+        return Err(io::Error::new(
+            io::ErrorKind::NotConnected,
+            "not connected to syslog collector",
+        ));
+    }
+    // Set the time of the next attempt before connecting, so that other
+    // threads don't try to connect at the same time.
+    conn.retry_at = Some(Instant::now() + conn.backoff);
+    conn.backoff = (conn.backoff * 2).min(MAX_BACKOFF);
+    drop(conn);
+
+    let mut stream = connect(address)?;
+    stream.write_all(frame)?;
+    let mut conn = connection.lock().unwrap_or_else(PoisonError::into_inner);
+    conn.stream = Some(stream);
+    conn.retry_at = None;
+    conn.backoff = MIN_BACKOFF;
+    Ok(())
+}
+
+/// Connect to `address`, using [`TCP_TIMEOUT`] for connecting and writing.
+fn connect(address: SocketAddr) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect_timeout(&address, TCP_TIMEOUT)?;
+    stream.set_write_timeout(Some(TCP_TIMEOUT))?;
+    Ok(stream)
+}
+
+/// Returns the first address of `address`.
+pub(super) fn resolve<A: ToSocketAddrs>(address: A) -> io::Result<SocketAddr> {
+    address.to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no address to send log records to",
+        )
+    })
+}
+
+impl Sink for Syslog {
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        let record = concat(bufs);
+        let record = record.strip_suffix(b"\n").unwrap_or(&record);
+        if let Err(err) = self.send(record) {
+            self.dropped.record("syslog", &err);
+        }
+        Ok(())
+    }
+}
@@ -1,14 +1,19 @@
-use std::io::{IoSlice, Write};
+use std::io::{self, IoSlice, Write};
 use std::mem::take;
-use std::sync::Mutex;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
-use std::{env, fmt, panic, str};
+use std::{env, fmt, fs, panic, str};
 
 use log::{debug, error, info, kv, trace, warn, Level, LevelFilter, Record};
 
-use crate::config::{get_log_targets, get_max_level, NoKvs};
-use crate::format::{self, Format, Gcloud, Json, LogFmt};
-use crate::{request, Targets, BUFS_SIZE, LOG_OUTPUT, PANIC_TARGET, REQUEST_TARGET};
+use crate::config::{get_log_filter, get_log_targets, NoKvs};
+use crate::format::{
+    self, Datadog, Dynamic, Ecs, Emf, Field, Format, Gcloud, Gelf, Journald, Json, KeyCollision,
+    LogFmt, MessagePack, OpenTelemetry, Pretty, Syslog, SyslogRfc3164,
+};
+use crate::sink::{Background, Interval, LogFile, Overflow, RotatingFile, Rotation, Sink, Writer};
+use crate::{request, Filter, Targets, BUFS_SIZE, LOG_OUTPUT, PANIC_TARGET, REQUEST_TARGET};
 
 /// Macro to create a group of sequential tests.
 macro_rules! sequential_tests {
@@ -44,8 +49,8 @@ sequential_tests! {
         for (env_var, env_val, want) in tests {
             env::set_var(env_var, env_val);
 
-            let got = get_max_level();
-            assert_eq!(*want, got);
+            let got = get_log_filter("");
+            assert_eq!(Filter::new(*want), got);
 
             env::remove_var(env_var);
         }
@@ -55,26 +60,117 @@ sequential_tests! {
         env::remove_var("DEBUG");
         env::remove_var("LOG");
         env::remove_var("LOG_LEVEL");
-        assert_eq!(get_max_level(), LevelFilter::Info);
+        assert_eq!(get_log_filter(""), Filter::new(LevelFilter::Info));
+    }
+
+    fn should_get_log_level_directives_from_env() {
+        env::set_var("LOG", "warn,my_crate=debug, my_crate::db=trace,invalid");
+        let filter = get_log_filter("");
+        assert_eq!(filter.level("other_crate"), LevelFilter::Warn);
+        assert_eq!(filter.level("my_crate"), LevelFilter::Debug);
+        assert_eq!(filter.level("my_crate::http"), LevelFilter::Debug);
+        assert_eq!(filter.level("my_crate::db"), LevelFilter::Trace);
+        assert_eq!(filter.level("my_crate::db::pool"), LevelFilter::Trace);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+
+        // Without a global level.
+        env::set_var("LOG", "my_crate::db=error");
+        let filter = get_log_filter("");
+        assert_eq!(filter.level("other_crate"), LevelFilter::Info);
+        assert_eq!(filter.level("my_crate::db"), LevelFilter::Error);
+        assert_eq!(filter.max_level(), LevelFilter::Info);
+        env::remove_var("LOG");
     }
 
     fn should_get_correct_log_targets() {
         let tests = &[
-            ("", Targets::All),
-            ("crate1", Targets::Only(vec!["crate1".into()].into_boxed_slice())),
-            ("crate1::mod1", Targets::Only(vec!["crate1::mod1".into()].into_boxed_slice())),
-            ("crate1,crate2", Targets::Only(vec!["crate1".into(), "crate2".into()].into_boxed_slice())),
+            ("", targets(&[], &[])),
+            ("crate1", targets(&["crate1"], &[])),
+            ("crate1::mod1", targets(&["crate1::mod1"], &[])),
+            ("crate1,crate2", targets(&["crate1", "crate2"], &[])),
+            ("-crate1,-crate2", targets(&[], &["crate1", "crate2"])),
+            ("crate1,-crate1::mod1", targets(&["crate1"], &["crate1::mod1"])),
         ];
 
         for (env_val, want) in tests {
             env::set_var("LOG_TARGET", env_val);
 
-            let got = get_log_targets();
+            let got = get_log_targets("");
             assert_eq!(*want, got);
         }
 
         env::remove_var("LOG_TARGET");
-        assert_eq!(get_log_targets(), Targets::All);
+        assert_eq!(get_log_targets(""), targets(&[], &[]));
+    }
+
+    fn should_use_env_prefix() {
+        env::set_var("STD_LOGGER_TEST_LOG", "warn,my_crate=debug");
+        env::set_var("STD_LOGGER_TEST_LOG_TARGET", "my_crate,-my_crate::db");
+        let (_, filter, _) = crate::Config::logfmt()
+            .with_env_prefix("STD_LOGGER_TEST_")
+            .into_logger();
+        env::remove_var("STD_LOGGER_TEST_LOG");
+        env::remove_var("STD_LOGGER_TEST_LOG_TARGET");
+
+        let filter = filter.read();
+        assert_eq!(filter.level, LevelFilter::Warn);
+        assert_eq!(filter.level("my_crate"), LevelFilter::Debug);
+        assert_eq!(filter.targets, targets(&["my_crate"], &["my_crate::db"]));
+    }
+
+    fn should_keep_filter_set_in_code_with_env_prefix() {
+        env::set_var("STD_LOGGER_TEST_LOG", "warn,my_crate=debug");
+        env::set_var("STD_LOGGER_TEST_LOG_TARGET", "my_crate,-my_crate::db");
+        let (_, filter, _) = crate::Config::logfmt()
+            .with_level(LevelFilter::Error)
+            .with_excluded_targets(["other_crate"])
+            .with_env_prefix("STD_LOGGER_TEST_")
+            .into_logger();
+        env::remove_var("STD_LOGGER_TEST_LOG");
+        env::remove_var("STD_LOGGER_TEST_LOG_TARGET");
+
+        let filter = filter.read();
+        assert_eq!(filter.level, LevelFilter::Error);
+        assert_eq!(filter.level("my_crate"), LevelFilter::Debug);
+        assert_eq!(filter.targets, targets(&["my_crate"], &["other_crate"]));
+    }
+
+    fn should_add_location_based_on_default_level() {
+        env::set_var("LOG", "info,x=trace");
+        let output = Arc::new(Writer::new(Vec::new()));
+        let (logger, _, _) = crate::Config::logfmt()
+            .with_output(output.clone())
+            .into_logger();
+        env::remove_var("LOG");
+
+        let record = Record::builder()
+            .args(format_args!("some message"))
+            .level(Level::Info)
+            .target("some_target")
+            .file_static(Some("file.rs"))
+            .line(Some(10))
+            .build();
+        logger.log(&record);
+        drop(logger);
+        let got = Arc::try_unwrap(output).unwrap().into_inner();
+        let got = String::from_utf8(got).unwrap();
+        assert!(got.contains("msg=\"some message\""), "{got}");
+        assert!(!got.contains("file="), "{got}");
+    }
+
+    fn should_get_format_from_env() {
+        env::set_var("STD_LOGGER_TEST_LOG_FORMAT", "gcloud");
+        env::set_var("STD_LOGGER_TEST_LOG", "debug");
+        let config = crate::Config::from_env_prefix("STD_LOGGER_TEST_");
+        env::remove_var("STD_LOGGER_TEST_LOG_FORMAT");
+        env::remove_var("STD_LOGGER_TEST_LOG");
+        let got = format!("{config:?}");
+        assert!(got.contains("format: Gcloud("), "{got}");
+        assert!(got.contains("level: Debug"), "{got}");
+
+        // Defaults to logfmt.
+        let got = format!("{:?}", crate::Config::from_env_prefix("STD_LOGGER_TEST_"));
+        assert!(got.contains("format: LogFmt("), "{got}");
     }
 
     fn log_output() {
@@ -85,13 +181,13 @@ sequential_tests! {
         env::remove_var("LOG_LEVEL");
 
         let want = &[
-            "lvl=\"TRACE\" msg=\"trace message\" target=\"std_logger::tests\" module=\"std_logger::tests\" file=\"src/tests.rs:100\"\n",
-            "lvl=\"DEBUG\" msg=\"debug message\" target=\"std_logger::tests\" module=\"std_logger::tests\" file=\"src/tests.rs:101\"\n",
-            "lvl=\"INFO\" msg=\"info message\" target=\"std_logger::tests\" module=\"std_logger::tests\" file=\"src/tests.rs:102\"\n",
-            "lvl=\"WARN\" msg=\"warn message\" target=\"std_logger::tests\" module=\"std_logger::tests\" file=\"src/tests.rs:103\"\n",
-            "lvl=\"ERROR\" msg=\"error message\" target=\"std_logger::tests\" module=\"std_logger::tests\" file=\"src/tests.rs:104\"\n",
-            "lvl=\"INFO\" msg=\"request message1\" target=\"request\" module=\"std_logger::tests\" file=\"src/tests.rs:105\"\n",
-            "lvl=\"INFO\" msg=\"request message2\" target=\"request\" module=\"std_logger::tests\" file=\"src/tests.rs:106\"\n",
+            "lvl=\"TRACE\" msg=\"trace message\" target=\"std_logger::tests\" module=\"std_logger::tests\" file=\"src/tests.rs:196\"\n",
+            "lvl=\"DEBUG\" msg=\"debug message\" target=\"std_logger::tests\" module=\"std_logger::tests\" file=\"src/tests.rs:197\"\n",
+            "lvl=\"INFO\" msg=\"info message\" target=\"std_logger::tests\" module=\"std_logger::tests\" file=\"src/tests.rs:198\"\n",
+            "lvl=\"WARN\" msg=\"warn message\" target=\"std_logger::tests\" module=\"std_logger::tests\" file=\"src/tests.rs:199\"\n",
+            "lvl=\"ERROR\" msg=\"error message\" target=\"std_logger::tests\" module=\"std_logger::tests\" file=\"src/tests.rs:200\"\n",
+            "lvl=\"INFO\" msg=\"request message1\" target=\"request\" module=\"std_logger::tests\" file=\"src/tests.rs:201\"\n",
+            "lvl=\"INFO\" msg=\"request message2\" target=\"request\" module=\"std_logger::tests\" file=\"src/tests.rs:202\"\n",
         ];
 
         #[cfg(feature = "timestamp")]
@@ -154,25 +250,42 @@ fn add_timestamp(message: String, timestamp: SystemTime, got: &str) -> String {
     format!("{timestamp} {message}")
 }
 
+#[test]
+fn config_builders() {
+    let (_, filter, _) = crate::Config::logfmt()
+        .with_level(LevelFilter::Trace)
+        .with_targets(["crate1", "crate2"])
+        .with_excluded_targets(["crate1::mod1"])
+        .into_logger();
+    let filter = filter.read();
+    assert_eq!(filter.level, LevelFilter::Trace);
+    assert_eq!(
+        filter.targets,
+        targets(&["crate1", "crate2"], &["crate1::mod1"])
+    );
+}
+
 #[test]
 fn targets_should_log() {
     let targets = &[
-        Targets::All,
-        Targets::Only(vec!["crate1".into()].into_boxed_slice()),
-        Targets::Only(vec!["crate1::mod1".into()].into_boxed_slice()),
-        Targets::Only(vec!["crate1".into(), "crate2".into()].into_boxed_slice()),
+        targets(&[], &[]),
+        targets(&["crate1"], &[]),
+        targets(&["crate1::mod1"], &[]),
+        targets(&["crate1", "crate2"], &[]),
+        targets(&[], &["crate2"]),
+        targets(&["crate1"], &["crate1::mod1"]),
     ];
 
     let tests = vec![
-        ("", vec![true, false, false, false]),
-        ("crate1", vec![true, true, false, true]),
-        ("crate1::mod1", vec![true, true, true, true]),
-        ("crate2", vec![true, false, false, true]),
-        ("crate2::mod2", vec![true, false, false, true]),
+        ("", vec![true, false, false, false, true, false]),
+        ("crate1", vec![true, true, false, true, true, true]),
+        ("crate1::mod1", vec![true, true, true, true, true, false]),
+        ("crate2", vec![true, false, false, true, false, false]),
+        ("crate2::mod2", vec![true, false, false, true, false, false]),
         // Requests should always be logged.
-        (REQUEST_TARGET, vec![true, true, true, true]),
+        (REQUEST_TARGET, vec![true, true, true, true, true, true]),
         // Panics should always be logged.
-        (PANIC_TARGET, vec![true, true, true, true]),
+        (PANIC_TARGET, vec![true, true, true, true, true, true]),
     ];
 
     for (test_target, wanted) in tests {
@@ -186,6 +299,13 @@ fn targets_should_log() {
     }
 }
 
+fn targets(only: &[&str], exclude: &[&str]) -> Targets {
+    Targets {
+        only: only.iter().map(|&target| target.into()).collect(),
+        exclude: exclude.iter().map(|&target| target.into()).collect(),
+    }
+}
+
 struct MyDisplay;
 
 impl fmt::Display for MyDisplay {
@@ -196,20 +316,76 @@ impl fmt::Display for MyDisplay {
 
 #[test]
 fn format_logfmt() {
-    format_test::<LogFmt, _>(&[
+    let want = [
         "lvl=\"INFO\" msg=\"some\\r\\n\\t\\nmessage\" key1=\"value1\" target=\"some_target1\" module=\"module_path1\" file=\"file1:123\"\n",
         "lvl=\"INFO\" msg=\"some\\r\\n\\t\\nmessage\" key1=\"value1\" target=\"some_target1\" module=\"module_path1\"\n",
         #[cfg(not(feature = "serde1"))]
         "lvl=\"WARN\" msg=\"arguments2 with \\\"quotes\\\"\" key2a=\"value2\" key2b=123 key3c=-123 key3d=123.0 key2e=true key2f=false key2g=\"c\" key2\\\"g=\"MyDisplay\" null_key=null target=\"second_target\" module=\"module_path1\" file=\"file2:111\"\n",
         #[cfg(feature = "serde1")]
-        "lvl=\"WARN\" msg=\"arguments2 with \\\"quotes\\\"\" key2a=\"value2\" key2b=123 key3c=-123 key3d=123.0 key2e=true key2f=false key2g=\"c\" key2\\\"g=\"MyDisplay\" null_key=null serde_map=\"MyValue { a: 1, b: \\\"2\\\", c: MyValue2 { d: 3.0 } }\" serde_array=\"[1, 2, 3]\" serde_tuple=\"(1, 2.0, \\\"3\\\")\" target=\"second_target\" module=\"module_path1\" file=\"file2:111\"\n",
+        "lvl=\"WARN\" msg=\"arguments2 with \\\"quotes\\\"\" key2a=\"value2\" key2b=123 key3c=-123 key3d=123.0 key2e=true key2f=false key2g=\"c\" key2\\\"g=\"MyDisplay\" null_key=null serde_map.a=1 serde_map.b=\"2\" serde_map.c.d=3.0 serde_array.0=1 serde_array.1=2 serde_array.2=3 serde_tuple.0=1 serde_tuple.1=2.0 serde_tuple.2=\"3\" target=\"second_target\" module=\"module_path1\" file=\"file2:111\"\n",
         "lvl=\"ERROR\" msg=\"panicking!\" target=\"panic\" module=\"\" file=\"??:0\"\n",
-    ], add_timestamp);
+    ];
+    format_test(&LogFmt::new(), &want, add_timestamp);
+
+    // Configuring the field names, using the default names, should produce
+    // the same output.
+    let mut format = LogFmt::new();
+    format.set_field_name(Field::Level, Some("lvl"));
+    format_test(&format, &want, add_timestamp);
+}
+
+#[test]
+#[cfg(feature = "serde1")]
+fn format_logfmt_serde() {
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize)]
+    enum Role {
+        Admin,
+        Guest(u8),
+        Custom(&'static str, bool),
+        Named {
+            level: u8,
+            scopes: Vec<&'static str>,
+        },
+    }
+
+    #[derive(serde::Serialize)]
+    struct User {
+        id: u64,
+        roles: Vec<Role>,
+        tags: Vec<&'static str>,
+        limits: BTreeMap<u8, Option<f64>>,
+        names: BTreeMap<&'static str, &'static str>,
+    }
+
+    let user = User {
+        id: 1,
+        roles: vec![
+            Role::Admin,
+            Role::Guest(2),
+            Role::Custom("x\"y", true),
+            Role::Named {
+                level: 3,
+                scopes: Vec::new(),
+            },
+        ],
+        tags: Vec::new(),
+        limits: BTreeMap::from([(1, Some(1.5)), (2, None)]),
+        names: BTreeMap::from([("first name", "Thomas"), ("a=\"b\"\n", "c")]),
+    };
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[("user", &kv::Value::from_serde(&user))];
+    let kvs: &dyn kv::Source = &kvs;
+    let mut buf = format::Buffer::new();
+    buf.write_logfmt_key_values(kvs);
+    let got = str::from_utf8(buf.as_bytes()).unwrap();
+    let want = " user.id=1 user.roles.0=\"Admin\" user.roles.1.Guest=2 user.roles.2.Custom.0=\"x\\\"y\" user.roles.2.Custom.1=true user.roles.3.Named.level=3 user.roles.3.Named.scopes=[] user.tags=[] user.limits.1=1.5 user.limits.2=null user.names.a__b__=\"c\" user.names.first_name=\"Thomas\"";
+    assert_eq!(got, want);
 }
 
 #[test]
 fn format_json() {
-    format_test::<Json, _>(&[
+    let want = [
         "{\"level\":\"INFO\",\"message\":\"some\\r\\n\\t\\nmessage\",\"target\":\"some_target1\",\"module\":\"module_path1\",\"key1\":\"value1\",\"file\":\"file1\",\"line\":\"123\"}\n",
         "{\"level\":\"INFO\",\"message\":\"some\\r\\n\\t\\nmessage\",\"target\":\"some_target1\",\"module\":\"module_path1\",\"key1\":\"value1\"}\n",
         #[cfg(not(feature = "serde1"))]
@@ -217,12 +393,203 @@ fn format_json() {
         #[cfg(feature = "serde1")]
         "{\"level\":\"WARN\",\"message\":\"arguments2 with \\\"quotes\\\"\",\"target\":\"second_target\",\"module\":\"module_path1\",\"key2a\":\"value2\",\"key2b\":123,\"key3c\":-123,\"key3d\":123.0,\"key2e\":true,\"key2f\":false,\"key2g\":\"c\",\"key2\\\"g\":\"MyDisplay\",\"null_key\":null,\"serde_map\":{\"a\":1,\"b\":\"2\",\"c\":{\"d\":3.0}},\"serde_array\":[1,2,3],\"serde_tuple\":[1,2.0,\"3\"],\"file\":\"file2\",\"line\":\"111\"}\n",
         "{\"level\":\"ERROR\",\"message\":\"panicking!\",\"target\":\"panic\",\"module\":\"\",\"file\":\"??\",\"line\":\"0\"}\n",
-    ], add_timestamp_json);
+    ];
+    format_test(&Json::new(), &want, add_timestamp_json);
+
+    // Configuring the field names, using the default names, should produce
+    // the same output.
+    let mut format = Json::new();
+    format.set_field_name(Field::Level, Some("level"));
+    format_test(&format, &want, add_timestamp_json);
+}
+
+#[test]
+fn format_field_names() {
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Info)
+        .target("some_target")
+        .module_path_static(Some("module_path"))
+        .file_static(Some("file"))
+        .line(Some(123))
+        .key_values(&("key1", "value1"))
+        .build();
+
+    let mut format = Json::new();
+    format.set_field_name(Field::Timestamp, Some("ts"));
+    format.set_field_name(Field::Level, Some("severity"));
+    format.set_field_name(Field::Message, Some("msg"));
+    format.set_field_name(Field::Target, Some("logger"));
+    format.set_field_name(Field::Module, None);
+    format.set_field_name(Field::Line, Some("line\"nr"));
+    let got = format_record(&format, &record, true);
+    #[cfg(feature = "timestamp")]
+    let got = {
+        assert!(got.starts_with("{\"ts\":\""), "{got}");
+        format!("{{{}", &got[36..])
+    };
+    let want = "{\"severity\":\"INFO\",\"msg\":\"some message\",\"logger\":\"some_target\",\"key1\":\"value1\",\"file\":\"file\",\"line\\\"nr\":\"123\"}\n";
+    assert_eq!(got, want);
+
+    // Omitting all fields.
+    let mut format = Json::new();
+    for field in [
+        Field::Timestamp,
+        Field::Level,
+        Field::Message,
+        Field::Target,
+        Field::Module,
+        Field::File,
+        Field::Line,
+    ] {
+        format.set_field_name(field, None);
+    }
+    assert_eq!(
+        format_record(&format, &record, true),
+        "{\"key1\":\"value1\"}\n"
+    );
+    let record = Record::builder().args(format_args!("msg")).build();
+    assert_eq!(format_record(&format, &record, true), "{}\n");
+
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Info)
+        .target("some_target")
+        .module_path_static(Some("module_path"))
+        .file_static(Some("file"))
+        .line(Some(123))
+        .key_values(&("key1", "value1"))
+        .build();
+    let mut format = LogFmt::new();
+    format.set_field_name(Field::Timestamp, Some("time"));
+    format.set_field_name(Field::Level, Some("severity"));
+    format.set_field_name(Field::Module, None);
+    let got = format_record(&format, &record, true);
+    #[cfg(feature = "timestamp")]
+    let got = {
+        assert!(got.starts_with("time=\""), "{got}");
+        got[35..].to_owned()
+    };
+    let want = "severity=\"INFO\" msg=\"some message\" key1=\"value1\" target=\"some_target\" file=\"file:123\"\n";
+    assert_eq!(got, want);
+
+    // The line is only logged separately if the file is omitted.
+    format.set_field_name(Field::Timestamp, None);
+    format.set_field_name(Field::File, None);
+    format.set_field_name(Field::Line, Some("lineno"));
+    let got = format_record(&format, &record, true);
+    let want = "severity=\"INFO\" msg=\"some message\" key1=\"value1\" target=\"some_target\" lineno=\"123\"\n";
+    assert_eq!(got, want);
+    format.set_field_name(Field::File, Some("file"));
+    format.set_field_name(Field::Line, None);
+    let got = format_record(&format, &record, true);
+    let want = "severity=\"INFO\" msg=\"some message\" key1=\"value1\" target=\"some_target\" file=\"file\"\n";
+    assert_eq!(got, want);
+}
+
+#[test]
+fn format_json_nested_kvs() {
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[("level", &"user level"), ("user_id", &123u64)];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Info)
+        .target("some_target")
+        .file_static(Some("file"))
+        .line(Some(123))
+        .key_values(kvs)
+        .build();
+
+    let mut format = Json::new();
+    format.set_kvs_key("fields");
+    let mut bufs = [IoSlice::new(&[]); BUFS_SIZE];
+    let mut buf = format::Buffer::new();
+    let bufs = format.format(&mut bufs, &mut buf, &record, &("app", "test"), false);
+    let mut got = Vec::new();
+    let _ = got.write_vectored(bufs).unwrap();
+    let got = strip_json_timestamp(String::from_utf8(got).unwrap());
+    let want = "{\"level\":\"INFO\",\"message\":\"some message\",\"target\":\"some_target\",\"module\":\"\",\"fields\":{\"level\":\"user level\",\"user_id\":123,\"app\":\"test\"}}\n";
+    assert_eq!(got, want);
+
+    // No key-values, no object.
+    let record = Record::builder().args(format_args!("msg")).build();
+    let got = strip_json_timestamp(format_record(&format, &record, false));
+    let want = "{\"level\":\"INFO\",\"message\":\"msg\",\"target\":\"\",\"module\":\"\"}\n";
+    assert_eq!(got, want);
+}
+
+#[test]
+fn format_json_key_collision() {
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[
+        ("level", &"user level"),
+        ("file", &"user file"),
+        ("user_id", &123u64),
+    ];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Info)
+        .target("some_target")
+        .key_values(kvs)
+        .build();
+
+    let tests = [
+        (
+            KeyCollision::Duplicate,
+            "{\"level\":\"INFO\",\"message\":\"some message\",\"target\":\"some_target\",\"module\":\"\",\"level\":\"user level\",\"file\":\"user file\",\"user_id\":123}\n",
+        ),
+        (
+            KeyCollision::Prefix,
+            "{\"level\":\"INFO\",\"message\":\"some message\",\"target\":\"some_target\",\"module\":\"\",\"fields.level\":\"user level\",\"file\":\"user file\",\"user_id\":123}\n",
+        ),
+        (
+            KeyCollision::Overwrite,
+            "{\"message\":\"some message\",\"target\":\"some_target\",\"module\":\"\",\"level\":\"user level\",\"file\":\"user file\",\"user_id\":123}\n",
+        ),
+        (
+            KeyCollision::Drop,
+            "{\"level\":\"INFO\",\"message\":\"some message\",\"target\":\"some_target\",\"module\":\"\",\"file\":\"user file\",\"user_id\":123}\n",
+        ),
+    ];
+    for (collision, want) in tests {
+        let mut format = Json::new();
+        format.set_key_collision(collision);
+        // The file is not logged, so it doesn't collide.
+        let got = strip_json_timestamp(format_record(&format, &record, false));
+        assert_eq!(got, want, "{collision:?}");
+    }
+
+    let mut format = Json::new();
+    format.set_key_collision(KeyCollision::Prefix);
+    format.set_collision_prefix("user_");
+    let got = strip_json_timestamp(format_record(&format, &record, false));
+    let want = "{\"level\":\"INFO\",\"message\":\"some message\",\"target\":\"some_target\",\"module\":\"\",\"user_level\":\"user level\",\"file\":\"user file\",\"user_id\":123}\n";
+    assert_eq!(got, want);
+
+    // Collisions are based on the configured name.
+    let mut format = Json::new();
+    format.set_key_collision(KeyCollision::Drop);
+    format.set_field_name(Field::Level, Some("severity"));
+    format.set_field_name(Field::Module, None);
+    let got = strip_json_timestamp(format_record(&format, &record, true));
+    let want = "{\"severity\":\"INFO\",\"message\":\"some message\",\"target\":\"some_target\",\"level\":\"user level\",\"user_id\":123,\"file\":\"??\",\"line\":\"0\"}\n";
+    assert_eq!(got, want);
+}
+
+/// Removes the `timestamp` field from the JSON object in `got`.
+fn strip_json_timestamp(got: String) -> String {
+    #[cfg(feature = "timestamp")]
+    {
+        assert!(got.starts_with("{\"timestamp\":\""), "{got}");
+        format!("{{{}", &got[43..])
+    }
+    #[cfg(not(feature = "timestamp"))]
+    got
 }
 
 #[test]
 fn format_gcloud() {
-    format_test::<Gcloud, _>(&[
+    format_test(&Gcloud(()), &[
         "{\"severity\":\"INFO\",\"message\":\"some\\r\\n\\t\\nmessage\",\"target\":\"some_target1\",\"module\":\"module_path1\",\"key1\":\"value1\",\"sourceLocation\":{\"file\":\"file1\",\"line\":\"123\"}}\n",
         "{\"severity\":\"INFO\",\"message\":\"some\\r\\n\\t\\nmessage\",\"target\":\"some_target1\",\"module\":\"module_path1\",\"key1\":\"value1\"}\n",
         #[cfg(not(feature = "serde1"))]
@@ -233,6 +600,279 @@ fn format_gcloud() {
     ], add_timestamp_json);
 }
 
+/// Format using the public [`format::Buffer`] helpers.
+struct Custom;
+
+impl Format for Custom {
+    fn format<'b, Kvs: kv::Source>(
+        &'b self,
+        bufs: &'b mut [IoSlice<'b>; BUFS_SIZE],
+        buf: &'b mut format::Buffer,
+        record: &'b Record,
+        kvs: &Kvs,
+        _: bool,
+    ) -> &'b [IoSlice<'b>] {
+        buf.clear();
+        buf.extend_from_slice(b"{\"msg\":\"");
+        buf.write_json_escaped(*record.args());
+        buf.extend_from_slice(b"\"");
+        buf.write_json_key_values(record.key_values());
+        buf.write_json_key_values(kvs);
+        buf.extend_from_slice(b"} ");
+        let json_end = buf.len();
+        buf.write_logfmt_escaped(*record.args());
+        buf.write_logfmt_key_values(record.key_values());
+        let (json, logfmt) = buf.as_bytes().split_at(json_end);
+        bufs[0] = IoSlice::new(json);
+        bufs[1] = IoSlice::new(logfmt);
+        bufs[2] = IoSlice::new(b"\n");
+        &bufs[..3]
+    }
+}
+
+#[test]
+fn format_ecs() {
+    format_test(&Ecs(()), &[
+        "{\"log.level\":\"info\",\"message\":\"some\\r\\n\\t\\nmessage\",\"ecs.version\":\"1.6.0\",\"log.logger\":\"some_target1\",\"key1\":\"value1\",\"log.origin.file.name\":\"file1\",\"log.origin.file.line\":123}\n",
+        "{\"log.level\":\"info\",\"message\":\"some\\r\\n\\t\\nmessage\",\"ecs.version\":\"1.6.0\",\"log.logger\":\"some_target1\",\"key1\":\"value1\"}\n",
+        #[cfg(not(feature = "serde1"))]
+        "{\"log.level\":\"warn\",\"message\":\"arguments2 with \\\"quotes\\\"\",\"ecs.version\":\"1.6.0\",\"log.logger\":\"second_target\",\"key2a\":\"value2\",\"key2b\":123,\"key3c\":-123,\"key3d\":123.0,\"key2e\":true,\"key2f\":false,\"key2g\":\"c\",\"key2\\\"g\":\"MyDisplay\",\"null_key\":null,\"log.origin.file.name\":\"file2\",\"log.origin.file.line\":111}\n",
+        #[cfg(feature = "serde1")]
+        "{\"log.level\":\"warn\",\"message\":\"arguments2 with \\\"quotes\\\"\",\"ecs.version\":\"1.6.0\",\"log.logger\":\"second_target\",\"key2a\":\"value2\",\"key2b\":123,\"key3c\":-123,\"key3d\":123.0,\"key2e\":true,\"key2f\":false,\"key2g\":\"c\",\"key2\\\"g\":\"MyDisplay\",\"null_key\":null,\"serde_map\":{\"a\":1,\"b\":\"2\",\"c\":{\"d\":3.0}},\"serde_array\":[1,2,3],\"serde_tuple\":[1,2.0,\"3\"],\"log.origin.file.name\":\"file2\",\"log.origin.file.line\":111}\n",
+        "{\"log.level\":\"critical\",\"message\":\"panicking!\",\"ecs.version\":\"1.6.0\",\"log.logger\":\"panic\",\"error.message\":\"panicking!\",\"log.origin.file.name\":\"??\",\"log.origin.file.line\":0}\n",
+    ], add_timestamp_ecs);
+
+    // The backtrace of a panic is logged as the stack trace.
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[
+        ("backtrace", &"0: main\n1: start"),
+        ("thread_name", &"main"),
+    ];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("thread 'main' panicked"))
+        .level(Level::Error)
+        .target(PANIC_TARGET)
+        .key_values(kvs)
+        .build();
+    let got = format_record(&Ecs(()), &record, false);
+    let want = r#","log.logger":"panic","thread_name":"main","error.message":"thread 'main' panicked","error.stack_trace":"0: main\n1: start"}"#;
+    assert!(got.ends_with(&format!("{want}\n")), "{got}");
+}
+
+fn add_timestamp_ecs(want: String, timestamp: SystemTime, got: &str) -> String {
+    let mut want = want.to_owned();
+    let timestamp = add_timestamp(String::new(), timestamp, &got[11..]);
+    let timestamp = format!("\"@timestamp\":\"{}\",", &timestamp[4..timestamp.len() - 2]);
+    want.insert_str(1, &timestamp);
+    want
+}
+
+#[test]
+fn format_emf() {
+    let mut format = Emf::new("MyApp".into());
+    format.set_metrics(Box::new([
+        "latency_ms".into(),
+        "bytes".into(),
+        "name".into(),
+    ]));
+    format.set_dimensions(Box::new(["route".into(), "region".into()]));
+
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[
+        ("route", &"/"),
+        ("latency_ms", &12u64),
+        ("ratio", &0.5f64),
+        ("name", &"not a number"),
+    ];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("finished request"))
+        .level(Level::Info)
+        .target(REQUEST_TARGET)
+        .module_path_static(Some("my_module"))
+        .key_values(kvs)
+        .build();
+    let got = format_record(&format, &record, false);
+    let (got, metadata) = got.split_once(r#","_aws":{"Timestamp":"#).unwrap();
+    let want = r#""level":"INFO","message":"finished request","target":"request","module":"my_module","route":"/","latency_ms":12,"ratio":0.5,"name":"not a number""#;
+    assert!(got.ends_with(want), "{got}");
+    let (timestamp, metadata) = metadata.split_once(',').unwrap();
+    let timestamp: u128 = timestamp.parse().unwrap();
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    assert!(now - timestamp < 10_000, "{timestamp}");
+    let want = r#""CloudWatchMetrics":[{"Namespace":"MyApp","Dimensions":[["route"]],"Metrics":[{"Name":"latency_ms"}]}]}}"#;
+    assert_eq!(metadata, format!("{want}\n"));
+
+    // Records without metrics are logged as regular JSON.
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Info)
+        .target("some_target")
+        .key_values(&("route", "/"))
+        .build();
+    let got = format_record(&format, &record, false);
+    assert!(!got.contains("_aws"), "{got}");
+    assert!(got.ends_with("\"route\":\"/\"}\n"), "{got}");
+}
+
+#[test]
+fn format_datadog() {
+    format_test(&Datadog(()), &[
+        "{\"status\":\"info\",\"message\":\"some\\r\\n\\t\\nmessage\",\"logger\":{\"name\":\"some_target1\",\"method_name\":\"module_path1\",\"file_name\":\"file1\",\"line\":123},\"key1\":\"value1\"}\n",
+        "{\"status\":\"info\",\"message\":\"some\\r\\n\\t\\nmessage\",\"logger\":{\"name\":\"some_target1\",\"method_name\":\"module_path1\"},\"key1\":\"value1\"}\n",
+        #[cfg(not(feature = "serde1"))]
+        "{\"status\":\"warn\",\"message\":\"arguments2 with \\\"quotes\\\"\",\"logger\":{\"name\":\"second_target\",\"method_name\":\"module_path1\",\"file_name\":\"file2\",\"line\":111},\"key2a\":\"value2\",\"key2b\":123,\"key3c\":-123,\"key3d\":123.0,\"key2e\":true,\"key2f\":false,\"key2g\":\"c\",\"key2\\\"g\":\"MyDisplay\",\"null_key\":null}\n",
+        #[cfg(feature = "serde1")]
+        "{\"status\":\"warn\",\"message\":\"arguments2 with \\\"quotes\\\"\",\"logger\":{\"name\":\"second_target\",\"method_name\":\"module_path1\",\"file_name\":\"file2\",\"line\":111},\"key2a\":\"value2\",\"key2b\":123,\"key3c\":-123,\"key3d\":123.0,\"key2e\":true,\"key2f\":false,\"key2g\":\"c\",\"key2\\\"g\":\"MyDisplay\",\"null_key\":null,\"serde_map\":{\"a\":1,\"b\":\"2\",\"c\":{\"d\":3.0}},\"serde_array\":[1,2,3],\"serde_tuple\":[1,2.0,\"3\"]}\n",
+        "{\"status\":\"critical\",\"message\":\"panicking!\",\"logger\":{\"name\":\"panic\",\"method_name\":\"\",\"file_name\":\"??\",\"line\":0},\"error\":{\"kind\":\"panic\",\"message\":\"panicking!\"}}\n",
+    ], add_timestamp_json);
+
+    // Promoted `dd.*` key-values and the backtrace of a panic.
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[
+        ("dd.trace_id", &"123"),
+        ("backtrace", &"0: main\n1: start"),
+        ("dd.span_id", &456u64),
+        ("dd.other", &true),
+    ];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("thread 'main' panicked"))
+        .level(Level::Error)
+        .target(PANIC_TARGET)
+        .key_values(kvs)
+        .build();
+    let got = format_record(&Datadog(()), &record, false);
+    let want = r#""logger":{"name":"panic","method_name":""},"dd.other":true,"dd":{"trace_id":"123","span_id":456},"error":{"kind":"panic","message":"thread 'main' panicked","stack":"0: main\n1: start"}}"#;
+    assert!(got.ends_with(&format!("{want}\n")), "{got}");
+}
+
+#[test]
+fn format_msgpack() {
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[
+        ("str", &"value"),
+        ("uint", &300u64),
+        ("int", &-2i64),
+        ("float", &1.5f64),
+        ("bool", &true),
+        ("null", &kv::Value::null()),
+    ];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Info)
+        .target("target")
+        .module_path_static(Some("module"))
+        .file_static(Some("file"))
+        .line(Some(123))
+        .key_values(kvs)
+        .build();
+    let mut bufs = [IoSlice::new(&[]); BUFS_SIZE];
+    let mut buf = format::Buffer::new();
+    let bufs = MessagePack(()).format(&mut bufs, &mut buf, &record, &NoKvs, true);
+    let mut got = Vec::new();
+    let _ = got.write_vectored(bufs).unwrap();
+
+    let mut want = Vec::new();
+    want.extend_from_slice(b"\xa5level\xa4INFO");
+    want.extend_from_slice(b"\xa7message\xdb\x00\x00\x00\x0csome message");
+    want.extend_from_slice(b"\xa6target\xa6target\xa6module\xa6module");
+    want.extend_from_slice(b"\xa3str\xa5value\xa4uint\xcd\x01\x2c\xa3int\xfe");
+    want.extend_from_slice(b"\xa5float\xcb");
+    want.extend_from_slice(&1.5f64.to_be_bytes());
+    want.extend_from_slice(b"\xa4bool\xc3\xa4null\xc0");
+    want.extend_from_slice(b"\xa4file\xa4file\xa4line\x7b");
+
+    assert_eq!(&got[..4], &(got.len() as u32 - 4).to_be_bytes());
+    #[cfg(feature = "timestamp")]
+    let got = {
+        // Remove the timestamp (ext 8, timestamp 96).
+        assert_eq!(&got[9..22], b"\xa9timestamp\xc7\x0c\xff");
+        assert_eq!(&got[5..9], &13u32.to_be_bytes());
+        [&got[..9], &got[34..]].concat()
+    };
+    #[cfg(not(feature = "timestamp"))]
+    assert_eq!(&got[5..9], &12u32.to_be_bytes());
+    assert_eq!(got[4], 0xdf);
+    assert_eq!(&got[9..], &*want);
+}
+
+#[test]
+fn format_msgpack_too_large() {
+    // Larger than the 16 MiB the parser reads.
+    let msg = "a".repeat(17 * 1024 * 1024);
+    let format = |record: &Record| {
+        let mut bufs = [IoSlice::new(&[]); BUFS_SIZE];
+        let mut buf = format::Buffer::new();
+        let bufs = MessagePack(()).format(&mut bufs, &mut buf, record, &NoKvs, false);
+        let mut got = Vec::new();
+        let _ = got.write_vectored(bufs).unwrap();
+        got
+    };
+    let got = format(
+        &Record::builder()
+            .args(format_args!("{msg}"))
+            .level(Level::Info)
+            .target("target")
+            .key_values(&("key", "value"))
+            .build(),
+    );
+
+    let len = u32::from_be_bytes(got[..4].try_into().unwrap()) as usize;
+    assert_eq!(len, got.len() - 4);
+    assert!(len <= 16 * 1024 * 1024, "{len}");
+    let find = |needle: &[u8]| got.windows(needle.len()).any(|w| w == needle);
+    assert!(find(b"\xa9truncated\xc3"));
+    assert!(!find(b"\xa3key"));
+}
+
+#[test]
+fn format_custom() {
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[("key1", &"value1"), ("key2", &123u64)];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("some \"quoted\"\nmessage"))
+        .level(Level::Info)
+        .target("some_target")
+        .key_values(kvs)
+        .build();
+
+    let got = format_record(&Custom, &record, false);
+    let want = "{\"msg\":\"some \\\"quoted\\\"\\nmessage\",\"key1\":\"value1\",\"key2\":123} some \\\"quoted\\\"\\nmessage key1=\"value1\" key2=123\n";
+    assert_eq!(got, want);
+
+    // Formats should be able to share a buffer.
+    let mut buf = format::Buffer::new();
+    let json = Json::new();
+    for _ in 0..2 {
+        let mut bufs = [IoSlice::new(&[]); BUFS_SIZE];
+        let _ = Custom.format(&mut bufs, &mut buf, &record, &NoKvs, false);
+        let mut bufs = [IoSlice::new(&[]); BUFS_SIZE];
+        let bufs = json.format(&mut bufs, &mut buf, &record, &NoKvs, false);
+        let mut output = Vec::new();
+        let _ = output.write_vectored(bufs).unwrap();
+        let got = String::from_utf8(output).unwrap();
+        assert!(got.starts_with('{'), "{got}");
+        assert!(
+            got.ends_with(",\"key1\":\"value1\",\"key2\":123}\n"),
+            "{got}"
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "timestamp")]
+fn buffer_write_timestamp() {
+    let mut buf = format::Buffer::new();
+    buf.extend_from_slice(b"ts=\"");
+    buf.write_timestamp();
+    buf.extend_from_slice(b"\" ");
+    let got = str::from_utf8(buf.as_bytes()).unwrap();
+    let want = add_timestamp(String::new(), SystemTime::now(), got);
+    assert_eq!(got, want);
+}
+
 fn add_timestamp_json(want: String, timestamp: SystemTime, got: &str) -> String {
     let mut want = want.to_owned();
     let timestamp = add_timestamp(String::new(), timestamp, &got[10..]);
@@ -241,7 +881,7 @@ fn add_timestamp_json(want: String, timestamp: SystemTime, got: &str) -> String
     want
 }
 
-fn format_test<F, A>(expected: &[&str; 4], add_timestamp: A)
+fn format_test<F, A>(format: &F, expected: &[&str; 4], add_timestamp: A)
 where
     F: Format,
     A: Fn(String, SystemTime, &str) -> String,
@@ -322,7 +962,7 @@ where
     ];
 
     for ((record, debug), want) in tests.into_iter().zip(expected) {
-        let got = format_record::<F>(&record, debug);
+        let got = format_record(format, &record, debug);
         #[cfg(feature = "timestamp")]
         let want = add_timestamp(want.to_string(), SystemTime::now(), &got);
         assert_eq!(got, *want);
@@ -331,10 +971,10 @@ where
     let _ = add_timestamp;
 }
 
-fn format_record<F: Format>(record: &Record, debug: bool) -> String {
+fn format_record<F: Format>(format: &F, record: &Record, debug: bool) -> String {
     let mut bufs = [IoSlice::new(&[]); BUFS_SIZE];
     let mut buf = format::Buffer::new();
-    let bufs = F::format(&mut bufs, &mut buf, record, &NoKvs, debug);
+    let bufs = format.format(&mut bufs, &mut buf, record, &NoKvs, debug);
     let mut output = Vec::new();
     let _ = output.write_vectored(bufs).unwrap();
     String::from_utf8(output).unwrap()
@@ -383,3 +1023,799 @@ fn timestamp() {
         assert_eq!(got.micro, micros);
     }
 }
+
+/// Writer that writes at most three bytes at a time.
+struct SlowWriter(Vec<u8>);
+
+impl Write for SlowWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(3);
+        self.0.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn sink_writer() {
+    let sink = Writer::new(SlowWriter(Vec::new()));
+    let bufs = [
+        IoSlice::new(b"Hello"),
+        IoSlice::new(b""),
+        IoSlice::new(b" world"),
+        IoSlice::new(b"\n"),
+    ];
+    sink.write_record(&bufs).unwrap();
+    sink.write_record(&bufs[2..]).unwrap();
+    sink.flush().unwrap();
+    let got = sink.into_inner().0;
+    assert_eq!(got, b"Hello world\n world\n");
+}
+
+#[test]
+fn sink_background() {
+    let output = Arc::new(Writer::new(Vec::new()));
+    let sink = Background::with_capacity(output.clone(), 2, Overflow::Block);
+    for i in 0..100 {
+        let line = format!("line{i}\n");
+        sink.write_record(&[IoSlice::new(line.as_bytes())]).unwrap();
+    }
+    sink.flush().unwrap();
+    assert_eq!(sink.dropped(), 0);
+    drop(sink);
+    let got = Arc::try_unwrap(output).unwrap().into_inner();
+    let want: String = (0..100).map(|i| format!("line{i}\n")).collect();
+    assert_eq!(str::from_utf8(&got).unwrap(), want);
+}
+
+#[test]
+fn config_async_before_output() {
+    /// Sink that records the name of the thread it's written on.
+    struct ThreadName(Mutex<Option<String>>);
+
+    impl Sink for ThreadName {
+        fn write_record(&self, _: &[IoSlice<'_>]) -> io::Result<()> {
+            *self.0.lock().unwrap() = std::thread::current().name().map(str::to_owned);
+            Ok(())
+        }
+    }
+
+    let output = Arc::new(ThreadName(Mutex::new(None)));
+    let (logger, _, _) = crate::Config::logfmt()
+        .with_async(8, Overflow::Block)
+        // Should still be written on the background thread.
+        .with_output(output.clone())
+        .into_logger();
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Error)
+        .target("some_target")
+        .build();
+    logger.log(&record);
+    logger.flush();
+    assert_eq!(output.0.lock().unwrap().as_deref(), Some("std-logger"));
+}
+
+#[test]
+fn config_pretty_on_tty() {
+    /// Sink that pretends to be a terminal.
+    #[derive(Debug)]
+    struct Terminal(Writer<Vec<u8>>);
+
+    impl Sink for Terminal {
+        fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+            self.0.write_record(bufs)
+        }
+
+        fn is_terminal(&self) -> bool {
+            true
+        }
+    }
+
+    fn log<F, S>(config: crate::Config<F, NoKvs>, output: S)
+    where
+        F: Format + Send + Sync + 'static,
+        S: Sink + 'static,
+    {
+        let (logger, _, _) = config.with_output(output).into_logger();
+        let record = Record::builder()
+            .args(format_args!("some message"))
+            .level(Level::Error)
+            .target("some_target")
+            .build();
+        logger.log(&record);
+    }
+
+    let output = Arc::new(Terminal(Writer::new(Vec::new())));
+    log(crate::Config::json().with_pretty_on_tty(), output.clone());
+    let got = Arc::try_unwrap(output).unwrap().0.into_inner();
+    let got = String::from_utf8(got).unwrap();
+    assert!(!got.starts_with('{'), "{got}");
+    assert!(got.contains("ERROR"), "{got}");
+
+    let output = Arc::new(Writer::new(Vec::new()));
+    log(crate::Config::json().with_pretty_on_tty(), output.clone());
+    let got = Arc::try_unwrap(output).unwrap().into_inner();
+    let got = String::from_utf8(got).unwrap();
+    assert!(got.starts_with('{'), "{got}");
+
+    // No colors if the output is not a terminal.
+    let output = Arc::new(Writer::new(Vec::new()));
+    log(crate::Config::pretty(), output.clone());
+    let got = Arc::try_unwrap(output).unwrap().into_inner();
+    let got = String::from_utf8(got).unwrap();
+    assert!(got.contains("ERROR"), "{got}");
+    assert!(!got.contains('\x1b'), "{got}");
+}
+
+#[test]
+fn sink_log_file() {
+    let path = temp_path("sink_log_file.log");
+    let sink = LogFile::open(&path).unwrap();
+    sink.write_record(&[IoSlice::new(b"line1"), IoSlice::new(b"\n")])
+        .unwrap();
+    drop(sink);
+    // Should append to the file.
+    let sink = LogFile::open(&path).unwrap();
+    sink.write_record(&[IoSlice::new(b"line2\n")]).unwrap();
+    let got = fs::read_to_string(&path).unwrap();
+    assert_eq!(got, "line1\nline2\n");
+    fs::remove_file(&path).unwrap();
+}
+
+/// Returns a path in the temporary directory unique to this process.
+fn temp_path(name: &str) -> PathBuf {
+    let mut path = env::temp_dir();
+    path.push(format!("std_logger_{}_{name}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn sink_log_file_reopen() {
+    let path = temp_path("sink_log_file_reopen.log");
+    let moved = temp_path("sink_log_file_reopen.log.1");
+    let sink = LogFile::open(&path).unwrap();
+    sink.write_record(&[IoSlice::new(b"line1\n")]).unwrap();
+    // Move the file like logrotate would.
+    fs::rename(&path, &moved).unwrap();
+    sink.write_record(&[IoSlice::new(b"line2\n")]).unwrap();
+    crate::reopen();
+    sink.write_record(&[IoSlice::new(b"line3\n")]).unwrap();
+
+    assert_eq!(fs::read_to_string(&moved).unwrap(), "line1\nline2\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "line3\n");
+    fs::remove_file(path).unwrap();
+    fs::remove_file(moved).unwrap();
+}
+
+#[test]
+fn sink_log_file_reopen_failure() {
+    let dir = temp_path("sink_log_file_reopen_failure");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir(&dir).unwrap();
+    let path = dir.join("test.log");
+    let moved = temp_path("sink_log_file_reopen_failure.log.1");
+    let sink = LogFile::open(&path).unwrap();
+    sink.write_record(&[IoSlice::new(b"line1\n")]).unwrap();
+    fs::rename(&path, &moved).unwrap();
+    fs::remove_dir(&dir).unwrap();
+    crate::reopen();
+    // Can't reopen the file, should keep writing to the old file.
+    sink.write_record(&[IoSlice::new(b"line2\n")]).unwrap();
+    // Should try again on the next record.
+    fs::create_dir(&dir).unwrap();
+    sink.write_record(&[IoSlice::new(b"line3\n")]).unwrap();
+
+    assert_eq!(fs::read_to_string(&moved).unwrap(), "line1\nline2\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "line3\n");
+    fs::remove_dir_all(dir).unwrap();
+    fs::remove_file(moved).unwrap();
+}
+
+#[test]
+fn sink_rotating_file_size() {
+    let path = temp_path("sink_rotating_file_size.log");
+    let rotated = |n| PathBuf::from(format!("{}.{n}", path.display()));
+    let rotation = Rotation::new().with_max_size(12).with_max_files(2);
+    let sink = RotatingFile::open(&path, rotation).unwrap();
+    // Should never rotate an empty file.
+    sink.write_record(&[IoSlice::new(b"long line 0\n")])
+        .unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "long line 0\n");
+    assert!(!rotated(1).exists());
+
+    for line in ["line1\n", "line2\n", "line3\n", "line4\n", "line5\n"] {
+        sink.write_record(&[IoSlice::new(line.as_bytes())]).unwrap();
+    }
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "line5\n");
+    assert_eq!(fs::read_to_string(rotated(1)).unwrap(), "line3\nline4\n");
+    assert_eq!(fs::read_to_string(rotated(2)).unwrap(), "line1\nline2\n");
+    assert!(!rotated(3).exists());
+
+    for path in [rotated(1), rotated(2), path] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn sink_rotating_file_gzip() {
+    use std::io::Read;
+
+    let path = temp_path("sink_rotating_file_gzip.log");
+    let rotated = PathBuf::from(format!("{}.1.gz", path.display()));
+    let rotation = Rotation::new().with_max_size(1).with_compression(true);
+    let sink = RotatingFile::open(&path, rotation).unwrap();
+    sink.write_record(&[IoSlice::new(b"line1\n")]).unwrap();
+    sink.write_record(&[IoSlice::new(b"line2\n")]).unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "line2\n");
+    let mut got = String::new();
+    let _ = flate2::read::GzDecoder::new(fs::File::open(&rotated).unwrap())
+        .read_to_string(&mut got)
+        .unwrap();
+    assert_eq!(got, "line1\n");
+
+    fs::remove_file(path).unwrap();
+    fs::remove_file(rotated).unwrap();
+}
+
+#[test]
+fn rotation_interval() {
+    // 2021-01-01T12:34:56Z.
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1609504496);
+    // 2021-01-01T13:00:00Z.
+    assert_eq!(Interval::Hourly.next(time), 1609506000);
+    // 2021-01-02T00:00:00Z.
+    assert_eq!(Interval::Daily.next(time), 1609545600);
+}
+
+#[test]
+fn format_journald() {
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[
+        ("user_id", &123u64),
+        ("_trusted.Key-1", &"value"),
+        ("multi", &"line1\nline2"),
+        ("123", &"invalid"),
+    ];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Warn)
+        .target("some_target")
+        .module_path_static(Some("module_path"))
+        .file_static(Some("file.rs"))
+        .line(Some(123))
+        .key_values(kvs)
+        .build();
+    let got = format_record(&Journald(()), &record, false);
+    let want = "PRIORITY=4\nMESSAGE=some message\nTARGET=some_target\nCODE_MODULE=module_path\nCODE_FILE=file.rs\nCODE_LINE=123\nUSER_ID=123\nTRUSTED_KEY_1=value\nMULTI\n\x0b\0\0\0\0\0\0\0line1\nline2\n";
+    assert_eq!(got, want);
+
+    let record = Record::builder()
+        .args(format_args!("panicking!\nbacktrace"))
+        .level(Level::Error)
+        .target("panic")
+        .build();
+    let got = format_record(&Journald(()), &record, true);
+    let want = "PRIORITY=2\nMESSAGE\n\x14\0\0\0\0\0\0\0panicking!\nbacktrace\nTARGET=panic\n";
+    assert_eq!(got, want);
+}
+
+#[test]
+fn format_dynamic() {
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Info)
+        .target("some_target")
+        .build();
+    let tests = [
+        ("logfmt", r#"lvl="INFO" msg="some message""#),
+        ("JSON", r#""level":"INFO","message":"some message""#),
+        ("gcloud", r#""severity":"INFO","message":"some message""#),
+        ("ecs", r#""log.level":"info","message":"some message""#),
+        ("datadog", r#""status":"info","message":"some message""#),
+        ("gelf", r#""short_message":"some message""#),
+        (
+            "otel",
+            r#""severityText":"INFO","body":{"stringValue":"some message"}"#,
+        ),
+        ("syslog", "<14>1 "),
+        ("pretty", "INFO "),
+    ];
+    for (name, want) in tests {
+        let format = Dynamic::from_name(name).unwrap();
+        let got = format_record(&format, &record, false);
+        assert!(got.contains(want), "{name}: {got}");
+    }
+    assert!(Dynamic::from_name("unknown").is_none());
+}
+
+#[test]
+fn format_syslog() {
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[("user_id", &123u64), ("quote", &"a\"b]")];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Warn)
+        .target("some_target")
+        .file_static(Some("file.rs"))
+        .line(Some(123))
+        .key_values(kvs)
+        .build();
+    let got = format_record(&Syslog(()), &record, true);
+    let parts: Vec<&str> = got.splitn(7, ' ').collect();
+    assert_eq!(parts[0], "<12>1");
+    #[cfg(feature = "timestamp")]
+    assert_eq!(parts[1].len(), 27);
+    #[cfg(not(feature = "timestamp"))]
+    assert_eq!(parts[1], "-");
+    assert!(!parts[2].is_empty());
+    assert_eq!(parts[3], app_name());
+    assert_eq!(parts[4], std::process::id().to_string());
+    assert_eq!(parts[5], "some_target");
+    let want = "[kv@32473 user_id=\"123\" quote=\"a\\\"b\\]\" file=\"file.rs:123\"] some message\n";
+    assert_eq!(parts[6], want);
+
+    let record = Record::builder()
+        .args(format_args!("panicking!"))
+        .level(Level::Error)
+        .target("panic")
+        .build();
+    let got = format_record(&Syslog(()), &record, false);
+    assert!(got.starts_with("<10>1 "), "{got}");
+    assert!(got.ends_with(" panic - panicking!\n"), "{got}");
+}
+
+#[test]
+fn format_syslog_rfc3164() {
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[("user_id", &123u64)];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Info)
+        .target("some_target")
+        .key_values(kvs)
+        .build();
+    let got = format_record(&SyslogRfc3164(()), &record, false);
+    assert!(got.starts_with("<14>"), "{got}");
+    let want = format!(
+        "{}[{}]: some message user_id=123 target=\"some_target\"\n",
+        app_name(),
+        std::process::id()
+    );
+    assert!(got.ends_with(&want), "{got}");
+    #[cfg(feature = "timestamp")]
+    {
+        // E.g. `Jan  1 12:00:00 hostname `.
+        let header = &got[4..got.len() - want.len()];
+        assert_eq!(&header[9..10], ":", "{header}");
+        assert_eq!(&header[12..13], ":", "{header}");
+        assert!(header.ends_with(' '), "{header}");
+    }
+    #[cfg(not(feature = "timestamp"))]
+    assert_eq!(got, format!("<14>{want}"));
+}
+
+#[test]
+fn format_pretty() {
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[("user_id", &123u64), ("name", &"Bob")];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Warn)
+        .target("some_target")
+        .file(Some("file.rs"))
+        .line(Some(10))
+        .key_values(kvs)
+        .build();
+
+    let tests: [(bool, bool, &str); 3] = [
+        (
+            false,
+            false,
+            "WARN  some message user_id=123 name=\"Bob\" some_target\n",
+        ),
+        (
+            false,
+            true,
+            "WARN  some message user_id=123 name=\"Bob\" some_target (file.rs:10)\n",
+        ),
+        (
+            true,
+            false,
+            "\x1b[1;33mWARN \x1b[0m some message \x1b[2muser_id=123 name=\"Bob\"\x1b[0m \x1b[2msome_target\x1b[0m\n",
+        ),
+    ];
+    for (colors, add_loc, want) in tests {
+        let got = format_record(&Pretty { colors }, &record, add_loc);
+        assert_pretty(&got, want);
+    }
+
+    // Backtraces of panics are printed on the following lines.
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[("backtrace", &"  0: main\n  1: start\n")];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("thread 'main' panicked"))
+        .level(Level::Error)
+        .target(PANIC_TARGET)
+        .key_values(kvs)
+        .build();
+    let got = format_record(&Pretty { colors: false }, &record, false);
+    let want = "ERROR thread 'main' panicked panic\n  0: main\n  1: start\n";
+    assert_pretty(&got, want);
+}
+
+/// Assert that `got` is equal to `want` prefixed with a `HH:MM:SS.mmm `
+/// timestamp, if the timestamp feature is enabled.
+fn assert_pretty(got: &str, want: &str) {
+    #[cfg(feature = "timestamp")]
+    let got = {
+        let (timestamp, got) = got.split_at(13);
+        assert_eq!(timestamp.len(), 13, "{timestamp}");
+        assert_eq!(&timestamp[2..3], ":", "{timestamp}");
+        assert_eq!(&timestamp[5..6], ":", "{timestamp}");
+        assert_eq!(&timestamp[8..9], ".", "{timestamp}");
+        assert_eq!(&timestamp[12..], " ", "{timestamp}");
+        got
+    };
+    assert_eq!(got, want);
+}
+
+#[test]
+fn format_gelf() {
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[("user_id", &123u64), ("name", &"Bob")];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Warn)
+        .target("some_target")
+        .module_path_static(Some("some_module"))
+        .file_static(Some("file.rs"))
+        .line(Some(123))
+        .key_values(kvs)
+        .build();
+    let got = format_record(&Gelf(()), &record, true);
+    let want = r#"{"short_message":"some message","level":4,"_target":"some_target","_module":"some_module","_user_id":123,"_name":"Bob","_file":"file.rs","_line":123}"#;
+    assert_eq!(strip_gelf_header(&got), format!("{want}\n"));
+
+    // Only the first line is used as short message.
+    let record = Record::builder()
+        .args(format_args!("first line\nsecond \\n line"))
+        .level(Level::Info)
+        .target("some_target")
+        .build();
+    let got = format_record(&Gelf(()), &record, false);
+    let want = r#"{"short_message":"first line","full_message":"first line\nsecond \\n line","level":6,"_target":"some_target","_module":""}"#;
+    assert_eq!(strip_gelf_header(&got), format!("{want}\n"));
+
+    // The short message can't be empty.
+    for (msg, want) in [
+        ("", r#"{"short_message":"-","level":6,"#),
+        (
+            "\nsecond line",
+            r#"{"short_message":"-","full_message":"\nsecond line","level":6,"#,
+        ),
+    ] {
+        let got = format_record(
+            &Gelf(()),
+            &Record::builder()
+                .args(format_args!("{msg}"))
+                .level(Level::Info)
+                .target("some_target")
+                .build(),
+            false,
+        );
+        let got = strip_gelf_header(&got);
+        assert!(got.starts_with(want), "{got}");
+    }
+
+    // Invalid field names.
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[("id", &1u64), ("a \"b\"/c", &2u64)];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("some message"))
+        .level(Level::Info)
+        .target("some_target")
+        .key_values(kvs)
+        .build();
+    let got = format_record(&Gelf(()), &record, false);
+    let want = r#"{"short_message":"some message","level":6,"_target":"some_target","_module":"","__id":1,"_a__b__c":2}"#;
+    assert_eq!(strip_gelf_header(&got), format!("{want}\n"));
+
+    // Panics include the backtrace in the full message.
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[
+        ("backtrace", &"0: main\n1: start"),
+        ("thread_name", &"main"),
+    ];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("thread 'main' panicked"))
+        .level(Level::Error)
+        .target(PANIC_TARGET)
+        .key_values(kvs)
+        .build();
+    let got = format_record(&Gelf(()), &record, false);
+    let want = r#"{"short_message":"thread 'main' panicked","full_message":"thread 'main' panicked\n0: main\n1: start","level":2,"_target":"panic","_module":"","_thread_name":"main"}"#;
+    assert_eq!(strip_gelf_header(&got), format!("{want}\n"));
+}
+
+/// Removes the version, host and timestamp fields from a GELF message, after
+/// checking them.
+fn strip_gelf_header(got: &str) -> String {
+    let (header, rest) = got.split_once(r#","short_message""#).unwrap();
+    assert!(header.starts_with(r#"{"version":"1.1","host":""#), "{got}");
+    let got = format!("{{\"short_message\"{rest}");
+    #[cfg(feature = "timestamp")]
+    let got = {
+        let mut got = got;
+        let start = got.find(r#","timestamp":"#).unwrap();
+        let end = start + got[start..].find(r#","level""#).unwrap();
+        let timestamp: f64 = got[start + 13..end].parse().unwrap();
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64();
+        assert!((now - timestamp).abs() < 10.0, "{got}");
+        got.replace_range(start..end, "");
+        got
+    };
+    got
+}
+
+#[test]
+fn format_opentelemetry() {
+    let kvs: &[(&str, &dyn kv::ToValue)] = &[
+        ("user_id", &123u64),
+        ("name", &"Bob"),
+        ("ratio", &0.5f64),
+        ("admin", &false),
+        ("big", &u64::MAX),
+        ("trace_id", &"5b8efff798038103d269b633813fc60c"),
+        ("span_id", &"eee19b7ec3c1b174"),
+    ];
+    let kvs: &dyn kv::Source = &kvs;
+    let record = Record::builder()
+        .args(format_args!("some \"message\""))
+        .level(Level::Warn)
+        .target("some_target")
+        .module_path_static(Some("some_module"))
+        .file_static(Some("file.rs"))
+        .line(Some(123))
+        .key_values(kvs)
+        .build();
+    let got = format_record(&OpenTelemetry::new(), &record, true);
+    let want = format!(
+        r#"{{"resourceLogs":[{{"resource":{{"attributes":[{{"key":"service.name","value":{{"stringValue":"{}"}}}}]}},"scopeLogs":[{{"scope":{{"name":"some_target"}},"logRecords":[{{"#,
+        app_name()
+    );
+    assert!(got.starts_with(&want), "{got}");
+    let got = strip_otel_timestamp(&got[want.len()..]);
+    let want = r#""severityNumber":13,"severityText":"WARN","body":{"stringValue":"some \"message\""},"attributes":[{"key":"user_id","value":{"intValue":"123"}},{"key":"name","value":{"stringValue":"Bob"}},{"key":"ratio","value":{"doubleValue":0.5}},{"key":"admin","value":{"boolValue":false}},{"key":"big","value":{"stringValue":"18446744073709551615"}},{"key":"code.namespace","value":{"stringValue":"some_module"}},{"key":"code.filepath","value":{"stringValue":"file.rs"}},{"key":"code.lineno","value":{"intValue":"123"}}],"traceId":"5b8efff798038103d269b633813fc60c","spanId":"eee19b7ec3c1b174"}]}]}]}"#;
+    assert_eq!(got, format!("{want}\n"));
+
+    let record = Record::builder()
+        .args(format_args!("panicking!"))
+        .level(Level::Error)
+        .target(PANIC_TARGET)
+        .build();
+    let resource: &[(&str, &dyn kv::ToValue)] = &[("service.name", &"app"), ("version", &1u64)];
+    let format = OpenTelemetry::with_resource(&resource);
+    let got = format_record(&format, &record, false);
+    let want = r#"{"resourceLogs":[{"resource":{"attributes":[{"key":"service.name","value":{"stringValue":"app"}},{"key":"version","value":{"intValue":"1"}}]},"scopeLogs":[{"scope":{"name":"panic"},"logRecords":[{"#;
+    assert!(got.starts_with(want), "{got}");
+    let got = strip_otel_timestamp(&got[want.len()..]);
+    let want = r#""severityNumber":21,"severityText":"FATAL","body":{"stringValue":"panicking!"},"attributes":[]}]}]}]}"#;
+    assert_eq!(got, format!("{want}\n"));
+}
+
+/// Removes the leading `"timeUnixNano":"..",`, after checking it.
+fn strip_otel_timestamp(got: &str) -> &str {
+    #[cfg(feature = "timestamp")]
+    let got = {
+        let got = got.strip_prefix(r#""timeUnixNano":""#).unwrap();
+        let (nanos, got) = got.split_once("\",").unwrap();
+        let nanos: u128 = nanos.parse().unwrap();
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        assert!(now - nanos < 10_000_000_000, "{nanos}");
+        got
+    };
+    got
+}
+
+/// Name of the test executable, as used by the syslog formats.
+fn app_name() -> String {
+    let arg0 = env::args_os().next().unwrap();
+    let path = std::path::Path::new(&arg0);
+    path.file_name().unwrap().to_string_lossy().into_owned()
+}
+
+#[test]
+#[cfg(unix)]
+fn sink_journald() {
+    use std::os::unix::net::UnixDatagram;
+
+    let path = temp_path("sink_journald.sock");
+    let socket = UnixDatagram::bind(&path).unwrap();
+    let sink = crate::sink::Journald::with_path(&path);
+    sink.write_record(&[IoSlice::new(b"PRIORITY=6\n"), IoSlice::new(b"MESSAGE=hi\n")])
+        .unwrap();
+    sink.write_record(&[IoSlice::new(b"MESSAGE=2\n")]).unwrap();
+
+    let mut buf = [0; 64];
+    let n = socket.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"PRIORITY=6\nMESSAGE=hi\n");
+    let n = socket.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"MESSAGE=2\n");
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn sink_journald_not_running() {
+    let sink = crate::sink::Journald::with_path(temp_path("sink_journald_not_running.sock"));
+    // Should drop the records, not return an error.
+    sink.write_record(&[IoSlice::new(b"MESSAGE=1\n")]).unwrap();
+    sink.write_record(&[IoSlice::new(b"MESSAGE=2\n")]).unwrap();
+    assert_eq!(sink.dropped(), 2);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn sink_journald_memfd() {
+    use std::fs::File;
+    use std::io::{Read, Seek, SeekFrom};
+    use std::mem::{size_of, MaybeUninit};
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::os::unix::net::UnixDatagram;
+
+    let path = temp_path("sink_journald_memfd.sock");
+    let socket = UnixDatagram::bind(&path).unwrap();
+    let sink = crate::sink::Journald::with_path(&path);
+    // Too large for a single datagram.
+    let mut record = b"MESSAGE=".to_vec();
+    record.resize(1024 * 1024, b'a');
+    record.push(b'\n');
+    sink.write_record(&[IoSlice::new(&record)]).unwrap();
+    assert_eq!(sink.dropped(), 0);
+
+    let mut control = [0u64; 4];
+    let mut msg: libc::msghdr = unsafe { MaybeUninit::zeroed().assume_init() };
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = size_of::<[u64; 4]>() as _;
+    let n = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, 0) };
+    assert_eq!(n, 0);
+    let cmsg = unsafe { &*libc::CMSG_FIRSTHDR(&msg) };
+    assert_eq!(cmsg.cmsg_level, libc::SOL_SOCKET);
+    assert_eq!(cmsg.cmsg_type, libc::SCM_RIGHTS);
+    let fd: libc::c_int = unsafe { libc::CMSG_DATA(cmsg).cast::<libc::c_int>().read_unaligned() };
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    let mut got = Vec::new();
+    let _ = file.seek(SeekFrom::Start(0)).unwrap();
+    let _ = file.read_to_end(&mut got).unwrap();
+    assert!(got == record);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn sink_syslog_unix() {
+    use std::os::unix::net::UnixDatagram;
+
+    let path = temp_path("sink_syslog.sock");
+    let socket = UnixDatagram::bind(&path).unwrap();
+    let sink = crate::sink::Syslog::unix(&path);
+    sink.write_record(&[IoSlice::new(b"<14>1 - - - - - - msg"), IoSlice::new(b"\n")])
+        .unwrap();
+
+    let mut buf = [0; 64];
+    let n = socket.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"<14>1 - - - - - - msg");
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn sink_syslog_udp() {
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sink = crate::sink::Syslog::udp(socket.local_addr().unwrap()).unwrap();
+    sink.write_record(&[IoSlice::new(b"<14>1 - - - - - - msg"), IoSlice::new(b"\n")])
+        .unwrap();
+
+    let mut buf = [0; 64];
+    let n = socket.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"<14>1 - - - - - - msg");
+}
+
+#[test]
+fn sink_syslog_collector_down() {
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let udp = crate::sink::Syslog::udp(socket.local_addr().unwrap()).unwrap();
+    drop(socket);
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let tcp = crate::sink::Syslog::tcp(listener.local_addr().unwrap()).unwrap();
+    drop(listener);
+
+    // Should drop the records, not return an error.
+    for _ in 0..3 {
+        udp.write_record(&[IoSlice::new(b"<14>1 - - - - - - msg\n")])
+            .unwrap();
+        tcp.write_record(&[IoSlice::new(b"<14>1 - - - - - - msg\n")])
+            .unwrap();
+    }
+    #[cfg(target_os = "linux")]
+    {
+        assert!(udp.dropped() != 0);
+        assert!(tcp.dropped() != 0);
+    }
+}
+
+#[test]
+fn sink_gelf() {
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let sink = crate::sink::Gelf::udp(socket.local_addr().unwrap())
+        .unwrap()
+        .with_chunk_size(16);
+    sink.write_record(&[IoSlice::new(b"{\"a\":1}"), IoSlice::new(b"\n")])
+        .unwrap();
+    let mut buf = [0; 64];
+    let n = socket.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"{\"a\":1}");
+
+    // Larger records are chunked, using chunks of 4 bytes.
+    sink.write_record(&[IoSlice::new(b"{\"abcd\":\"efghijklm\"}\n")])
+        .unwrap();
+    let mut id = None;
+    let mut message = Vec::new();
+    for seq in 0..5 {
+        let n = socket.recv(&mut buf).unwrap();
+        let chunk = &buf[..n];
+        assert_eq!(&chunk[..2], &[0x1e, 0x0f]);
+        assert_eq!(*id.get_or_insert(chunk[2..10].to_vec()), &chunk[2..10]);
+        assert_eq!(chunk[10], seq);
+        assert_eq!(chunk[11], 5);
+        message.extend_from_slice(&chunk[12..]);
+    }
+    assert_eq!(message, b"{\"abcd\":\"efghijklm\"}");
+
+    // Records that need more than 128 chunks are dropped.
+    assert_eq!(sink.dropped(), 0);
+    let record = vec![b'a'; 4 * 128 + 1];
+    sink.write_record(&[IoSlice::new(&record)]).unwrap();
+    assert_eq!(sink.dropped(), 1);
+
+    // Send failures don't return an error either.
+    let addr = socket.local_addr().unwrap();
+    drop(socket);
+    let sink = crate::sink::Gelf::udp(addr).unwrap();
+    for _ in 0..3 {
+        sink.write_record(&[IoSlice::new(b"{\"a\":1}\n")]).unwrap();
+    }
+    #[cfg(target_os = "linux")]
+    assert!(sink.dropped() != 0);
+}
+
+#[test]
+fn sink_syslog_tcp() {
+    use std::io::Read;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let sink = crate::sink::Syslog::tcp(listener.local_addr().unwrap()).unwrap();
+    sink.write_record(&[IoSlice::new(b"<14>1 - - - - - - msg1\n")])
+        .unwrap();
+    sink.write_record(&[IoSlice::new(b"<14>1 - - - - - - msg2\n")])
+        .unwrap();
+    drop(sink);
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut got = String::new();
+    let _ = stream.read_to_string(&mut got).unwrap();
+    assert_eq!(got, "22 <14>1 - - - - - - msg122 <14>1 - - - - - - msg2");
+}
@@ -0,0 +1,96 @@
+//! Tests for `Config::with_async` and the `Background` sink.
+
+use std::io::{self, IoSlice};
+use std::sync::{Arc, Condvar, Mutex};
+
+use log::info;
+use std_logger::sink::{Background, Overflow, Sink};
+
+/// Sink that blocks writing until it's opened.
+#[derive(Clone, Default)]
+struct Gate(Arc<(Mutex<GateState>, Condvar)>);
+
+#[derive(Default)]
+struct GateState {
+    open: bool,
+    entered: bool,
+    output: Vec<u8>,
+}
+
+impl Gate {
+    fn wait_entered(&self) {
+        let (state, cond) = &*self.0;
+        let mut state = state.lock().unwrap();
+        while !state.entered {
+            state = cond.wait(state).unwrap();
+        }
+    }
+
+    fn open(&self) {
+        let (state, cond) = &*self.0;
+        state.lock().unwrap().open = true;
+        cond.notify_all();
+    }
+
+    fn output(&self) -> String {
+        String::from_utf8(self.0 .0.lock().unwrap().output.clone()).unwrap()
+    }
+}
+
+impl Sink for Gate {
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        let (state, cond) = &*self.0;
+        let mut state = state.lock().unwrap();
+        state.entered = true;
+        cond.notify_all();
+        while !state.open {
+            state = cond.wait(state).unwrap();
+        }
+        for buf in bufs {
+            state.output.extend_from_slice(buf);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn async_output() {
+    let gate = Gate::default();
+    std_logger::Config::logfmt()
+        .with_call_location(false)
+        .with_output(gate.clone())
+        .with_async(2, Overflow::DropNewest)
+        .init();
+
+    info!("message1");
+    // Wait until the writer thread is blocked on the first message.
+    gate.wait_entered();
+    info!("message2");
+    info!("message3");
+    // Queue is full, so this is dropped.
+    info!("message4");
+    gate.open();
+    log::logger().flush();
+
+    let got = gate.output();
+    let got: Vec<&str> = got.lines().collect();
+    // The number of dropped records is reported on standard error, not logged.
+    assert_eq!(got.len(), 3, "{got:?}");
+    for (i, line) in got.iter().enumerate() {
+        let want = format!(r#"lvl="INFO" msg="message{}" target="background""#, i + 1);
+        assert!(line.contains(&want), "{got:?}");
+    }
+
+    // Using the sink directly.
+    let gate = Gate::default();
+    let sink = Background::with_capacity(gate.clone(), 1, Overflow::DropOldest);
+    sink.write_record(&[IoSlice::new(b"1\n")]).unwrap();
+    gate.wait_entered();
+    sink.write_record(&[IoSlice::new(b"2\n")]).unwrap();
+    // Drops record 2.
+    sink.write_record(&[IoSlice::new(b"3\n")]).unwrap();
+    gate.open();
+    sink.flush().unwrap();
+    assert_eq!(gate.output(), "1\n3\n");
+    assert_eq!(sink.dropped(), 1);
+}
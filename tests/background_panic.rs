@@ -0,0 +1,41 @@
+//! Tests for a panicking sink wrapped in `Background`.
+
+use std::io::{self, IoSlice};
+
+use log::info;
+use std_logger::sink::{Background, Overflow, Sink};
+
+/// Sink that panics on every write.
+struct Panic;
+
+impl Sink for Panic {
+    fn write_record(&self, _: &[IoSlice<'_>]) -> io::Result<()> {
+        panic!("oops");
+    }
+}
+
+#[test]
+fn panicking_sink() {
+    std_logger::Config::logfmt()
+        .with_output(Panic)
+        .with_async(4, Overflow::Block)
+        .init();
+
+    // The panic hook logs the panic on the writer thread, flushing there
+    // would make the writer thread wait on itself.
+    info!("message1");
+    log::logger().flush();
+    // The writer thread stopped, so these are dropped.
+    for _ in 0..10 {
+        info!("message2");
+    }
+    log::logger().flush();
+
+    // Using the sink directly.
+    let sink = Background::with_capacity(Panic, 1, Overflow::Block);
+    sink.write_record(&[IoSlice::new(b"1\n")]).unwrap();
+    sink.flush().unwrap();
+    sink.write_record(&[IoSlice::new(b"2\n")]).unwrap();
+    sink.flush().unwrap();
+    assert_eq!(sink.dropped(), 2);
+}
@@ -0,0 +1,93 @@
+//! Tests for `Fanout`.
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use log::{debug, info, LevelFilter};
+use std_logger::sink::Writer;
+use std_logger::{request, Config, Fanout};
+
+/// Shared buffer.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn lines(&self) -> Vec<String> {
+        let output = self.0.lock().unwrap();
+        let output = String::from_utf8(output.clone()).unwrap();
+        output.lines().map(ToOwned::to_owned).collect()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn fanout() {
+    let debug_output = Output::default();
+    let info_output = Output::default();
+    let request_output = Output::default();
+    let handle = Fanout::new()
+        .with(
+            Config::logfmt()
+                .with_level(LevelFilter::Debug)
+                .with_call_location(false)
+                .with_output(Writer::new(debug_output.clone()))
+                .with_request_output(Writer::new(request_output.clone())),
+        )
+        .with(
+            Config::gcloud()
+                .with_level(LevelFilter::Info)
+                .with_output(Writer::new(info_output.clone()))
+                .with_request_output(Writer::new(request_output.clone())),
+        )
+        .init_with_handle();
+
+    assert_eq!(log::max_level(), LevelFilter::Debug);
+    debug!("debug message");
+    info!("info message");
+    request!("request message");
+
+    let got = debug_output.lines();
+    assert_eq!(got.len(), 2, "{got:?}");
+    assert!(
+        got[0].contains(r#"lvl="DEBUG" msg="debug message""#),
+        "{got:?}"
+    );
+    assert!(
+        got[1].contains(r#"lvl="INFO" msg="info message""#),
+        "{got:?}"
+    );
+
+    let got = info_output.lines();
+    assert_eq!(got.len(), 1, "{got:?}");
+    assert!(
+        got[0].contains(r#""severity":"INFO","message":"info message""#),
+        "{got:?}"
+    );
+
+    // Both destinations log requests.
+    let got = request_output.lines();
+    assert_eq!(got.len(), 2, "{got:?}");
+    assert!(got[0].contains(r#"msg="request message""#), "{got:?}");
+    assert!(got[1].contains(r#""message":"request message""#), "{got:?}");
+
+    // Changing a single destination keeps the severity of the others.
+    let handle = handle.destination(0).unwrap();
+    assert!(handle.destination(2).is_none());
+    handle.set_level(LevelFilter::Warn);
+    assert_eq!(handle.level(), LevelFilter::Warn);
+    assert_eq!(log::max_level(), LevelFilter::Info);
+    info!("info message2");
+    assert_eq!(debug_output.lines().len(), 2);
+    let got = info_output.lines();
+    assert_eq!(got.len(), 2, "{got:?}");
+    assert!(got[1].contains(r#""message":"info message2""#), "{got:?}");
+}
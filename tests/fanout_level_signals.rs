@@ -0,0 +1,79 @@
+//! Tests for `Config::with_level_signals` when using `Fanout`.
+
+#![cfg(unix)]
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+use log::LevelFilter;
+use std_logger::sink::Writer;
+use std_logger::{Config, Fanout};
+
+/// Shared buffer.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    /// Wait until a line is logged and return it.
+    fn wait_for_line(&self) -> String {
+        for _ in 0..200 {
+            let output = std::mem::take(&mut *self.0.lock().unwrap());
+            if !output.is_empty() {
+                return String::from_utf8(output).unwrap();
+            }
+            sleep(Duration::from_millis(10));
+        }
+        panic!("no line logged");
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn fanout_level_signals() {
+    let debug_output = Output::default();
+    let warn_output = Output::default();
+    let handle = Fanout::new()
+        .with(
+            Config::logfmt()
+                .with_level(LevelFilter::Debug)
+                .with_call_location(false)
+                .with_output(Writer::new(debug_output.clone()))
+                .with_level_signals(true),
+        )
+        .with(
+            Config::logfmt()
+                .with_level(LevelFilter::Warn)
+                .with_output(Writer::new(warn_output.clone())),
+        )
+        .init_with_handle();
+    let debug_handle = handle.destination(0).unwrap();
+    let warn_handle = handle.destination(1).unwrap();
+
+    // Each destination changes relative to its own severity.
+    let tests = [
+        (libc::SIGUSR1, LevelFilter::Trace, LevelFilter::Info),
+        (libc::SIGUSR2, LevelFilter::Debug, LevelFilter::Warn),
+        (libc::SIGUSR2, LevelFilter::Info, LevelFilter::Error),
+    ];
+    for (signal, want_debug, want_warn) in tests {
+        assert_eq!(unsafe { libc::raise(signal) }, 0);
+        let got = debug_output.wait_for_line();
+        let want_line = format!(r#"msg="changed log level to {want_debug}""#);
+        assert!(got.contains(&want_line), "{got}");
+        assert_eq!(debug_handle.level(), want_debug);
+        assert_eq!(warn_handle.level(), want_warn);
+        assert_eq!(handle.level(), want_debug);
+        assert_eq!(log::max_level(), want_debug);
+    }
+}
@@ -0,0 +1,72 @@
+//! Tests for `Config::init_with_guard`.
+
+use std::io::{self, IoSlice};
+use std::thread::sleep;
+use std::time::Duration;
+use std::{env, fs, process};
+
+use log::info;
+use std_logger::sink::{LogFile, Overflow, Sink};
+
+/// Environment variable set for the child process.
+const CHILD_ENV: &str = "STD_LOGGER_FLUSH_TEST_CHILD";
+
+/// Sink that writes slowly.
+struct Slow(LogFile);
+
+impl Sink for Slow {
+    fn write_record(&self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        sleep(Duration::from_millis(50));
+        self.0.write_record(bufs)
+    }
+}
+
+fn init(path: &str) -> std_logger::FlushGuard {
+    std_logger::Config::logfmt()
+        .with_call_location(false)
+        .with_output(Slow(LogFile::open(path).unwrap()))
+        .with_async(16, Overflow::Block)
+        .init_with_guard()
+}
+
+#[test]
+fn flush_on_drop_and_exit() {
+    if let Ok(path) = env::var(CHILD_ENV) {
+        // Running as child process, see below.
+        let _guard = init(&path);
+        info!("message1");
+        info!("message2");
+        info!("message3");
+        // Destructors are not run, but the logger should still be flushed.
+        process::exit(0);
+    }
+
+    let mut path = env::temp_dir();
+    path.push(format!("std_logger_flush_{}.log", process::id()));
+    let path = path.to_str().unwrap().to_owned();
+
+    // Flush when exiting the process, only supported on Unix.
+    #[cfg(unix)]
+    {
+        let status = std::process::Command::new(env::current_exe().unwrap())
+            .args(["--exact", "flush_on_drop_and_exit", "--nocapture"])
+            .env(CHILD_ENV, &path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let got = fs::read_to_string(&path).unwrap();
+        assert_eq!(got.lines().count(), 3, "{got}");
+        assert!(got.contains("msg=\"message3\""), "{got}");
+        fs::remove_file(&path).unwrap();
+    }
+
+    // Flush when the guard is dropped.
+    let guard = init(&path);
+    info!("message1");
+    info!("message2");
+    drop(guard);
+    let got = fs::read_to_string(&path).unwrap();
+    assert_eq!(got.lines().count(), 2, "{got}");
+    assert!(got.contains("msg=\"message2\""), "{got}");
+    fs::remove_file(&path).unwrap();
+}
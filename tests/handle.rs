@@ -0,0 +1,70 @@
+//! Tests for `LoggerHandle`.
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use log::{debug, info, LevelFilter};
+use std_logger::sink::Writer;
+
+/// Shared buffer.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn take(&self) -> String {
+        let output = std::mem::take(&mut *self.0.lock().unwrap());
+        String::from_utf8(output).unwrap()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+mod db {
+    pub fn log() {
+        log::info!("db message");
+    }
+}
+
+#[test]
+fn logger_handle() {
+    let output = Output::default();
+    let handle = std_logger::Config::logfmt()
+        .with_level(LevelFilter::Info)
+        .with_call_location(false)
+        .with_output(Writer::new(output.clone()))
+        .init_with_handle();
+    assert_eq!(handle.level(), LevelFilter::Info);
+    assert_eq!(log::max_level(), LevelFilter::Info);
+
+    debug!("debug message1");
+    handle.set_level(LevelFilter::Debug);
+    assert_eq!(handle.level(), LevelFilter::Debug);
+    assert_eq!(log::max_level(), LevelFilter::Debug);
+    debug!("debug message2");
+    let got = output.take();
+    assert!(!got.contains("debug message1"), "{got}");
+    assert!(got.contains("debug message2"), "{got}");
+
+    handle.set_targets(["handle::db"]);
+    info!("info message");
+    db::log();
+    let got = output.take();
+    assert!(!got.contains("info message"), "{got}");
+    assert!(got.contains("db message"), "{got}");
+
+    handle.set_targets(Vec::<String>::new());
+    handle.set_excluded_targets(["handle::db"]);
+    info!("info message");
+    db::log();
+    let got = output.take();
+    assert!(got.contains("info message"), "{got}");
+    assert!(!got.contains("db message"), "{got}");
+}
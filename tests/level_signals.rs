@@ -0,0 +1,69 @@
+//! Tests for `Config::with_level_signals`.
+
+#![cfg(unix)]
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+use log::LevelFilter;
+use std_logger::sink::Writer;
+
+/// Shared buffer.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    /// Wait until a line is logged and return it.
+    fn wait_for_line(&self) -> String {
+        for _ in 0..200 {
+            let output = std::mem::take(&mut *self.0.lock().unwrap());
+            if !output.is_empty() {
+                return String::from_utf8(output).unwrap();
+            }
+            sleep(Duration::from_millis(10));
+        }
+        panic!("no line logged");
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn level_signals() {
+    let output = Output::default();
+    let handle = std_logger::Config::logfmt()
+        .with_level(LevelFilter::Info)
+        .with_call_location(false)
+        .with_output(Writer::new(output.clone()))
+        .with_level_signals(true)
+        .init_with_handle();
+
+    let tests = [
+        (libc::SIGUSR1, LevelFilter::Debug, "DEBUG"),
+        (libc::SIGUSR1, LevelFilter::Trace, "TRACE"),
+        (libc::SIGUSR1, LevelFilter::Trace, "TRACE"),
+        (libc::SIGUSR2, LevelFilter::Debug, "DEBUG"),
+        (libc::SIGUSR2, LevelFilter::Info, "INFO"),
+        (libc::SIGUSR2, LevelFilter::Warn, "WARN"),
+        (libc::SIGUSR2, LevelFilter::Error, "ERROR"),
+        (libc::SIGUSR2, LevelFilter::Error, "ERROR"),
+    ];
+    for (signal, want, lvl) in tests {
+        assert_eq!(unsafe { libc::raise(signal) }, 0);
+        let got = output.wait_for_line();
+        let want_line = format!(r#"lvl="{lvl}" msg="changed log level to {want}""#);
+        assert!(got.contains(&want_line), "{got}");
+        assert_eq!(handle.level(), want);
+        assert_eq!(log::max_level(), want);
+    }
+}
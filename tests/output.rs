@@ -0,0 +1,60 @@
+//! Tests for `Config::with_output` and `Config::with_request_output`.
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use log::{info, warn};
+use std_logger::request;
+use std_logger::sink::Writer;
+
+/// Shared buffer.
+#[derive(Clone)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn custom_outputs() {
+    let output = Output(Arc::new(Mutex::new(Vec::new())));
+    let request_output = Output(Arc::new(Mutex::new(Vec::new())));
+    std_logger::Config::logfmt()
+        .with_call_location(false)
+        .with_output(Writer::new(output.clone()))
+        .with_request_output(Writer::new(request_output.clone()))
+        .init();
+
+    info!("info message");
+    request!("request message");
+    warn!(key = "value"; "warn message");
+    log::logger().flush();
+
+    let got = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    let got: Vec<&str> = got.lines().collect();
+    assert_eq!(got.len(), 2, "{got:?}");
+    assert!(
+        got[0].ends_with(r#"lvl="INFO" msg="info message" target="output" module="output""#),
+        "{got:?}"
+    );
+    assert!(
+        got[1].ends_with(
+            r#"lvl="WARN" msg="warn message" key="value" target="output" module="output""#
+        ),
+        "{got:?}"
+    );
+
+    let got = String::from_utf8(request_output.0.lock().unwrap().clone()).unwrap();
+    assert!(
+        got.ends_with(
+            "lvl=\"INFO\" msg=\"request message\" target=\"request\" module=\"output\"\n"
+        ),
+        "{got:?}"
+    );
+}
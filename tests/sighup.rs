@@ -0,0 +1,41 @@
+//! Tests for `Config::with_reopen_on_sighup`.
+
+#![cfg(unix)]
+
+use std::{env, fs};
+
+use log::info;
+use std_logger::sink::LogFile;
+
+#[test]
+fn reopen_on_sighup() {
+    let mut path = env::temp_dir();
+    path.push(format!(
+        "std_logger_{}_reopen_on_sighup.log",
+        std::process::id()
+    ));
+    let moved = path.with_extension("log.1");
+
+    std_logger::Config::logfmt()
+        .with_call_location(false)
+        .with_output(LogFile::open(&path).unwrap())
+        .with_reopen_on_sighup(true)
+        .init();
+
+    info!("message 1");
+    fs::rename(&path, &moved).unwrap();
+    info!("message 2");
+    assert_eq!(unsafe { libc::raise(libc::SIGHUP) }, 0);
+    info!("message 3");
+
+    let got = fs::read_to_string(&moved).unwrap();
+    assert_eq!(got.lines().count(), 2, "{got}");
+    assert!(got.contains("msg=\"message 1\""), "{got}");
+    assert!(got.contains("msg=\"message 2\""), "{got}");
+    let got = fs::read_to_string(&path).unwrap();
+    assert_eq!(got.lines().count(), 1, "{got}");
+    assert!(got.contains("msg=\"message 3\""), "{got}");
+
+    fs::remove_file(path).unwrap();
+    fs::remove_file(moved).unwrap();
+}